@@ -0,0 +1,46 @@
+// ============================================================
+// Infinity OS — Policy Engine Benchmarks
+//
+// Measures the full JSON-in / JSON-out path the orchestration
+// layer pays on every AI action.
+//
+//   cargo bench --bench evaluate_policy
+// ============================================================
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use infinity_os_policy_engine::{validate_ai_action, PolicyEngine};
+
+const CONTEXT: &str = r#"{"user_role":"power_user","mfa_verified":true,"session_age_seconds":60,"trusted_network":true,"failed_attempts_last_hour":0}"#;
+
+fn request(action: &str) -> String {
+    format!(
+        r#"{{"action":"{}","target_resource":"res:1","risk_score":10,"requesting_module":"bench","user_id":"user-1","organisation_id":"org-1","metadata":null}}"#,
+        action
+    )
+}
+
+fn bench_free_function(c: &mut Criterion) {
+    for (name, action) in [
+        ("read", "read_public_cache"),
+        ("write", "write_notification"),
+        ("system", "schedule_background_task"),
+        ("blocked", "modify_kernel_scheduler"),
+        ("unlisted", "unknown_action"),
+    ] {
+        let request = request(action);
+        c.bench_function(&format!("validate_ai_action/{}", name), |b| {
+            b.iter(|| validate_ai_action(black_box(&request), black_box(CONTEXT), 0))
+        });
+    }
+}
+
+fn bench_engine(c: &mut Criterion) {
+    let mut engine = PolicyEngine::new();
+    let request = request("read_public_cache");
+    c.bench_function("engine/read", |b| {
+        b.iter(|| engine.validate_ai_action(black_box(&request), black_box(CONTEXT), 0))
+    });
+}
+
+criterion_group!(benches, bench_free_function, bench_engine);
+criterion_main!(benches);
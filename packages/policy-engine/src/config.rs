@@ -0,0 +1,1041 @@
+// ============================================================
+// Infinity OS — Policy Engine Configuration
+//
+// Tunable knobs for the deterministic rule cascade.
+// Every default reproduces the hardcoded baseline policy, so an
+// empty config ({}) behaves exactly like the built-in engine.
+// ============================================================
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Hashed lookups with `std`; ordered-map fallback for `no_std` builds
+#[cfg(feature = "std")]
+type CategoryMap = std::collections::HashMap<String, ActionCategory>;
+#[cfg(not(feature = "std"))]
+type CategoryMap = BTreeMap<String, ActionCategory>;
+
+use crate::{
+    PolicyError, SecurityContext, ALLOWED_READ_ACTIONS, ALLOWED_SYSTEM_ACTIONS,
+    ALLOWED_WRITE_ACTIONS, APPLIED_RULES, BLOCKED_ACTIONS, DEFAULT_ROUTE, GDPR_REQUEST_TYPES,
+    KNOWN_ROLES, MAX_FAILED_ATTEMPTS, MAX_PLAUSIBLE_FAILED_ATTEMPTS, MAX_PLAUSIBLE_SESSION_AGE,
+    MAX_RISK_SCORE, MAX_SESSION_AGE_SENSITIVE, RULESET_VERSION,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct PolicyConfig {
+    /// Version stamp of the ruleset this config describes
+    pub ruleset_version: String,
+    /// Maintenance freeze: deny every non-read action with
+    /// MAINTENANCE_FREEZE. Hard-blocked actions keep their HARD_BLOCK.
+    pub freeze_writes: bool,
+    /// Incident switch: deny every action, reads and hard-blocked ones
+    /// included, with PANIC_MODE until an operator clears it
+    pub panic_mode: bool,
+    /// Whitelisted read actions
+    pub read_actions: Vec<String>,
+    /// Whitelisted write actions
+    pub write_actions: Vec<String>,
+    /// Whitelisted system actions (MFA + elevated role)
+    pub system_actions: Vec<String>,
+    /// Always-denied actions. Must contain every built-in blocked action;
+    /// config can add to the blocklist but never shrink it.
+    pub blocked_actions: Vec<String>,
+    /// Expected `blocklist_checksum()` of the blocklist. When set, every
+    /// evaluation re-verifies it and denies all non-read actions with
+    /// INTEGRITY_FAILURE on mismatch.
+    pub expected_blocklist_checksum: Option<String>,
+    /// Recognized roles, lowest privilege first. Position is the role's rank.
+    pub role_hierarchy: Vec<String>,
+    /// Maximum risk score allowed for any AI action
+    pub max_risk_score: u8,
+    /// Per-role risk ceilings replacing `max_risk_score` for that role, e.g.
+    /// a higher one for `super_admin`. Unrecognized roles get the lowest
+    /// ceiling configured anywhere.
+    pub role_max_risk: BTreeMap<String, u8>,
+    /// Absolute cap no `role_max_risk` ceiling may exceed
+    pub role_max_risk_cap: u8,
+    /// Per-action floor on the reported risk score. A score below the
+    /// floor suggests a buggy or compromised caller under-reporting risk.
+    pub min_risk_for_action: BTreeMap<String, u8>,
+    /// Maximum session age (seconds) for write/system actions. Fallback
+    /// for whichever of the per-category limits below is unset.
+    pub max_session_age_sensitive: u64,
+    /// Maximum session age (seconds) for write actions
+    pub max_session_age_write: Option<u64>,
+    /// Maximum session age (seconds) for system actions. System actions
+    /// always get the stricter of this and the write limit.
+    pub max_session_age_system: Option<u64>,
+    /// Actions that require MFA, whatever their category. `None` means
+    /// every system action; a list replaces that default outright.
+    pub mfa_required: Option<Vec<String>>,
+    /// Maximum age (seconds) of the MFA verification for MFA-gated actions;
+    /// `None` accepts MFA from any point in the session
+    pub max_mfa_age_seconds: Option<u64>,
+    /// Per-action MFA age limits, overriding `max_mfa_age_seconds`. Every
+    /// action listed must be MFA-gated.
+    pub max_mfa_age_for_action: BTreeMap<String, u64>,
+    /// Deny MFA-gated actions under an age limit when the context does not
+    /// report `mfa_age_seconds`, instead of trusting `mfa_verified` alone
+    pub require_mfa_age: bool,
+    /// Read actions that expose sensitive data and so are not open to every
+    /// role: callers need `authenticated_read_min_role` and, when
+    /// `authenticated_reads_check_session` is set, a fresh session
+    pub authenticated_reads: Vec<String>,
+    /// Least-privileged role allowed to perform an authenticated read
+    pub authenticated_read_min_role: String,
+    /// Apply the write session-age limit to authenticated reads
+    pub authenticated_reads_check_session: bool,
+    /// Grace band (seconds) past every session-age limit, absorbing client
+    /// clock skew. A session is expired only beyond `limit + tolerance`.
+    pub session_age_tolerance_seconds: u64,
+    /// Most concurrent sessions a user may hold for write and system
+    /// actions, per the context's `concurrent_sessions`; beyond it,
+    /// TOO_MANY_SESSIONS. Contexts without the count are not checked.
+    /// `None` disables the check.
+    pub max_concurrent_sessions: Option<u32>,
+    /// Largest `session_age_seconds` a working client can report. Beyond it
+    /// (bar the `u64::MAX` "unknown" default) the context is
+    /// CONTEXT_IMPLAUSIBLE. `None` disables the check.
+    pub max_plausible_session_age_seconds: Option<u64>,
+    /// Largest plausible `failed_attempts_last_hour`; `None` disables
+    pub max_plausible_failed_attempts: Option<u32>,
+    /// Least role allowed to perform each system action; system actions
+    /// not listed require `power_user`
+    pub system_action_min_role: BTreeMap<String, String>,
+    /// Highest role the AI may request through `request_role_elevation`
+    pub max_elevation_role: String,
+    /// Out-of-band human approvals, keyed by the SHA-256 (hex) of the
+    /// token a request carries in `approval_token`. A matching, unexpired
+    /// token lifts a risk or role denial for its one action and user.
+    pub approval_tokens: BTreeMap<String, ApprovalToken>,
+    /// Failed attempts in the last hour that trigger lockout
+    pub max_failed_attempts: u32,
+    /// Read actions still permitted during ACCOUNT_LOCKOUT, so the UI can
+    /// explain the lockout. Must be plain, non-sensitive reads.
+    pub lockout_exempt_reads: Vec<String>,
+    /// Write/system actions are denied when `SecurityContext.threat_score`
+    /// exceeds this. Like every threat setting, additive to the other gates.
+    pub threat_write_deny_above: u8,
+    /// Any action, reads included, needs MFA when the threat score exceeds this
+    pub threat_mfa_required_above: u8,
+    /// Accounts younger than this (seconds) need MFA for write/system actions
+    pub new_account_age_seconds: u64,
+    /// Report every unmet authentication requirement for write/system
+    /// actions in a single AUTH_REQUIREMENTS_NOT_MET decision.
+    /// When false, the legacy one-at-a-time cascade applies
+    /// (SESSION_EXPIRED, then MFA_REQUIRED, then INSUFFICIENT_ROLE).
+    pub consolidate_auth_requirements: bool,
+    /// Maximum byte length of each raw JSON input. Checked before
+    /// parsing so an oversized payload is never handed to serde.
+    pub max_input_bytes: usize,
+    /// Reject requests and contexts carrying fields the schema does not
+    /// define (`UNKNOWN_FIELD`) instead of ignoring them
+    pub strict_fields: bool,
+    /// Maximum nesting depth of `AiRequest.metadata`
+    pub max_metadata_depth: usize,
+    /// Maximum total number of values (at any depth) in `AiRequest.metadata`
+    pub max_metadata_entries: usize,
+    /// Per-action metadata ceilings: `action -> (metadata_key, max_value)`
+    pub metadata_conditions: BTreeMap<String, MetadataCondition>,
+    /// Per-action argument schemas: `action -> (argument -> constraint)`,
+    /// checked against `AiRequest.metadata`. Actions without one skip it.
+    pub action_arguments: BTreeMap<String, BTreeMap<String, ArgumentSchema>>,
+    /// Keys the AI may attach in `AiRequest.metadata`. When set, metadata
+    /// must be an object using only these keys. `None` skips the check.
+    pub allowed_metadata_keys: Option<Vec<String>>,
+    /// Anchored globs (`*` wildcard) that `target_resource` must never match,
+    /// e.g. `vault:*` or `*:secrets:*`
+    pub resource_deny_patterns: Vec<String>,
+    /// `target_resource` prefixes (e.g. `finance:`, `pii:`) marking data
+    /// that warrants extra scrutiny: every decision on one is audited, and
+    /// writes need MFA whatever the action (SENSITIVE_TARGET)
+    pub sensitive_resource_prefixes: Vec<String>,
+    /// Anchored globs (`*` wildcard) over `<module>@<version>` naming
+    /// vulnerable builds, e.g. `com.infinity-os.shell@1.2.*`. Only a
+    /// `requesting_module` with an `@version` suffix is checked.
+    pub deprecated_module_versions: Vec<String>,
+    /// Whitelisted actions slated for removal, mapped to their sunset
+    /// (epoch ms). Before it, decisions carry a `deprecation_warning`;
+    /// from it on, the action is treated as unlisted (NOT_IN_WHITELIST).
+    pub deprecated_actions: BTreeMap<String, u64>,
+    /// Actions that need a non-empty `AiRequest.organisation_id`
+    pub actions_requiring_org: Vec<String>,
+    /// Actions that need a non-empty `AiRequest.user_id`
+    pub actions_requiring_user: Vec<String>,
+    /// Actions that need non-null `AiRequest.metadata`
+    pub actions_requiring_metadata: Vec<String>,
+    /// Deny write and system actions whose metadata lacks a non-empty
+    /// string `trace_id` (MISSING_TRACE_ID). Reads are exempt.
+    pub require_trace_id: bool,
+    /// Actions denied whenever `trusted_network` is false, at any risk
+    pub trusted_network_required: Vec<String>,
+    /// Actions that may only touch the caller's own data: the request's
+    /// `user_id` must equal the context's `authenticated_user_id`
+    pub user_scoped_actions: Vec<String>,
+    /// Least role exempt from `user_scoped_actions`, e.g. an admin managing
+    /// other users' data. `None` exempts no one.
+    pub cross_user_exempt_role: Option<String>,
+    /// Actions under the two-person rule: the acting user and a distinct
+    /// second approver (`SecurityContext.second_approver_id`) must both hold
+    /// `dual_control_min_role` or higher
+    pub dual_control_actions: Vec<String>,
+    /// Least role either party to a dual-control action may hold
+    pub dual_control_min_role: String,
+    /// Least role that may file a GDPR request of each type (`erasure`,
+    /// `portability`) on someone else's behalf; unlisted types need
+    /// `org_admin`. Data subjects may always file their own.
+    pub gdpr_min_roles: BTreeMap<String, String>,
+    /// Renamed actions: `old_action -> canonical_action`, resolved before
+    /// any rule runs. Both sides must share the same block status.
+    pub action_aliases: BTreeMap<String, String>,
+    /// Per-user allotment of permits per quota window, keyed by action.
+    /// Enforced by `PolicyEngine` only; unlisted actions (including all
+    /// reads by default) are unmetered.
+    pub action_quotas: BTreeMap<String, u32>,
+    /// Quota window length in ms. Windows align to multiples of this
+    /// from the Unix epoch, so the default resets at each UTC midnight.
+    pub quota_window_ms: u64,
+    /// Actions that may only follow others: `action -> [prerequisite, ...]`.
+    /// Every prerequisite must have been permitted in the same session within
+    /// `prerequisite_window_ms`. Requires `PolicyEngine` session tracking;
+    /// stateless calls (e.g. the free `validate_ai_action`) do not enforce it.
+    pub action_prerequisites: BTreeMap<String, Vec<String>>,
+    /// How recently a prerequisite must have been permitted (ms)
+    pub prerequisite_window_ms: u64,
+    /// Groups of actions of which a session may perform only one: once any
+    /// member is permitted, the others are denied for the rest of the
+    /// session. Requires `PolicyEngine` session tracking, like prerequisites.
+    pub mutually_exclusive_actions: Vec<Vec<String>>,
+    /// Writes that need a warm session: some action permitted in the same
+    /// session within `warm_session_window_ms`, else COLD_SESSION. Reads are
+    /// exempt and are the natural warm-up. Requires `PolicyEngine` session
+    /// tracking, like prerequisites.
+    pub warm_session_actions: Vec<String>,
+    /// How recent the warming permit must be (ms); `0` disables the check
+    pub warm_session_window_ms: u64,
+    /// Minimum gap (ms) between two permits of the same action for one
+    /// user, e.g. `{"send_notification": 60000}`; a repeat inside it is
+    /// ACTION_COOLDOWN. Unlisted actions (reads included) have no
+    /// cooldown. Enforced by `PolicyEngine` only.
+    pub action_cooldown_ms: BTreeMap<String, u64>,
+    /// Flag (never deny) a request whose rate exceeds the user's moving
+    /// average by this factor. `None` disables velocity tracking.
+    /// Enforced by `PolicyEngine` only.
+    pub anomaly_rate_factor: Option<f64>,
+    /// Intervals observed for a user before spikes are flagged
+    pub anomaly_min_samples: u32,
+    /// Require human review when a request's risk score exceeds the highest
+    /// earlier score in its session by more than this. `None` disables.
+    /// Enforced by `PolicyEngine` only.
+    pub risk_regression_delta: Option<u8>,
+    /// After a RISK_SCORE_EXCEEDED denial, hold the same user's write and
+    /// system actions for human review for this long (ms). Reads are
+    /// unaffected. `0` disables. Enforced by `PolicyEngine` only.
+    pub risk_cooldown_ms: u64,
+    /// Escalate once a user has been denied the same action this many times
+    /// in a row: the decision is audited and carries `repeated_denial_count`.
+    /// A permit ends the streak. `None` disables. Enforced by `PolicyEngine` only.
+    pub repeated_denial_threshold: Option<u32>,
+    /// A denial more than this (ms) after the previous one starts a new streak
+    pub repeated_denial_window_ms: u64,
+    /// On reaching the threshold, deny the same user and action outright
+    /// with REPEATED_DENIAL_BLOCK for this long (ms), then count afresh.
+    /// `0` escalates without blocking.
+    pub repeated_denial_block_ms: u64,
+    /// Lower the risk compared against `max_risk_score` by this much for
+    /// each earlier trusted-network permit of the same `(user_id, action)`.
+    /// `None` disables. Never applies to system actions, and the reported
+    /// risk is still what decisions record. Enforced by `PolicyEngine` only.
+    pub risk_decay_per_success: Option<u8>,
+    /// Decay never lowers the effective risk below this
+    pub risk_decay_floor: u8,
+    /// Weights combining `AiRequest.risk_components` into the risk compared
+    /// against `max_risk_score`: the weighted sum, capped at 100. Requests
+    /// missing any weighted component use their flat `risk_score`; empty
+    /// disables composite risk.
+    pub risk_component_weights: BTreeMap<String, f64>,
+    /// Add every mapped control (ISO 27001, SOC 2, NIST 800-53) to each
+    /// decision's `controls`, not just the single ISO `iso_control`
+    pub include_all_controls: bool,
+    /// Retention hint (days) for audited decisions, keyed by action or by
+    /// applied rule (e.g. `HARD_BLOCK`); a rule entry wins over an action's.
+    /// Decisions with no entry omit the hint.
+    pub audit_retention_days: BTreeMap<String, u32>,
+    /// Per-tenant replacements for the emitted `iso_control`, keyed by
+    /// `organisation_id` and then by applied rule, for tenants mapping
+    /// rules to their own framework (e.g. `{"org-1": {"HARD_BLOCK":
+    /// "NIST800-53:CM-7"}}`). Rules without an entry keep the ISO default.
+    pub tenant_control_overrides: BTreeMap<String, BTreeMap<String, String>>,
+    /// Where hosts should send each decision, keyed by applied rule and
+    /// emitted as `route_hint`. Rules without an entry route to
+    /// `DEFAULT_ROUTE`. A supplied table replaces the default one.
+    pub route_hints: BTreeMap<String, String>,
+    /// Replace the request's `target_resource` and `user_id` with
+    /// `[redacted]` in decision reasons, for hosts whose decision logs must
+    /// not carry them. `applied_rule` and `iso_control` are never touched.
+    pub redact_reasons: bool,
+    /// Percentage (0-100) of low-risk permits to audit anyway, chosen by a
+    /// deterministic hash of `(user_id, action, timestamp_ms)`. 0 disables.
+    pub audit_sample_percent: u8,
+    /// Entries in `PolicyEngine`'s decision cache; 0 disables caching.
+    /// Only the pure rule cascade is cached — grants, prerequisites and
+    /// quotas are re-applied on every call.
+    pub decision_cache_capacity: usize,
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        PolicyConfig {
+            ruleset_version: RULESET_VERSION.to_string(),
+            freeze_writes: false,
+            panic_mode: false,
+            read_actions: owned(ALLOWED_READ_ACTIONS),
+            write_actions: owned(ALLOWED_WRITE_ACTIONS),
+            system_actions: owned(ALLOWED_SYSTEM_ACTIONS),
+            blocked_actions: owned(BLOCKED_ACTIONS),
+            expected_blocklist_checksum: None,
+            role_hierarchy: owned(KNOWN_ROLES),
+            max_risk_score: MAX_RISK_SCORE,
+            role_max_risk: BTreeMap::new(),
+            role_max_risk_cap: 100,
+            min_risk_for_action: BTreeMap::new(),
+            max_session_age_sensitive: MAX_SESSION_AGE_SENSITIVE,
+            max_session_age_write: None,
+            max_session_age_system: None,
+            mfa_required: None,
+            max_mfa_age_seconds: None,
+            max_mfa_age_for_action: BTreeMap::new(),
+            require_mfa_age: false,
+            authenticated_reads: Vec::new(),
+            authenticated_read_min_role: "power_user".to_string(),
+            authenticated_reads_check_session: true,
+            session_age_tolerance_seconds: 0,
+            max_concurrent_sessions: None,
+            max_plausible_session_age_seconds: Some(MAX_PLAUSIBLE_SESSION_AGE),
+            max_plausible_failed_attempts: Some(MAX_PLAUSIBLE_FAILED_ATTEMPTS),
+            system_action_min_role: BTreeMap::new(),
+            max_elevation_role: "org_admin".to_string(),
+            approval_tokens: BTreeMap::new(),
+            max_failed_attempts: MAX_FAILED_ATTEMPTS,
+            lockout_exempt_reads: Vec::new(),
+            threat_write_deny_above: 70,
+            threat_mfa_required_above: 40,
+            new_account_age_seconds: 3600,
+            consolidate_auth_requirements: true,
+            max_input_bytes: 64 * 1024,
+            strict_fields: false,
+            max_metadata_depth: 16,
+            max_metadata_entries: 1024,
+            metadata_conditions: BTreeMap::new(),
+            action_arguments: BTreeMap::new(),
+            allowed_metadata_keys: None,
+            resource_deny_patterns: Vec::new(),
+            sensitive_resource_prefixes: Vec::new(),
+            deprecated_module_versions: Vec::new(),
+            deprecated_actions: BTreeMap::new(),
+            actions_requiring_org: Vec::new(),
+            actions_requiring_user: Vec::new(),
+            actions_requiring_metadata: Vec::new(),
+            require_trace_id: false,
+            trusted_network_required: Vec::new(),
+            user_scoped_actions: vec!["write_user_preferences".to_string()],
+            cross_user_exempt_role: None,
+            dual_control_actions: Vec::new(),
+            dual_control_min_role: "org_admin".to_string(),
+            gdpr_min_roles: BTreeMap::new(),
+            action_aliases: BTreeMap::new(),
+            action_quotas: BTreeMap::new(),
+            quota_window_ms: 86_400_000,
+            action_prerequisites: BTreeMap::new(),
+            prerequisite_window_ms: 900_000,
+            mutually_exclusive_actions: Vec::new(),
+            warm_session_actions: Vec::new(),
+            warm_session_window_ms: 0,
+            action_cooldown_ms: BTreeMap::new(),
+            anomaly_rate_factor: None,
+            anomaly_min_samples: 10,
+            risk_regression_delta: None,
+            risk_cooldown_ms: 0,
+            repeated_denial_threshold: None,
+            repeated_denial_window_ms: 600_000,
+            repeated_denial_block_ms: 0,
+            risk_decay_per_success: None,
+            risk_decay_floor: 20,
+            risk_component_weights: BTreeMap::new(),
+            include_all_controls: false,
+            audit_retention_days: BTreeMap::new(),
+            tenant_control_overrides: BTreeMap::new(),
+            route_hints: [
+                ("HARD_BLOCK", "security-alerts"),
+                ("RISK_SCORE_EXCEEDED", "review-queue"),
+                ("WHITELIST_APPROVED", DEFAULT_ROUTE),
+            ]
+            .into_iter()
+            .map(|(rule, route)| (rule.to_string(), route.to_string()))
+            .collect(),
+            redact_reasons: false,
+            audit_sample_percent: 0,
+            decision_cache_capacity: 0,
+        }
+    }
+}
+
+impl PolicyConfig {
+    /// Parse a JSON config (missing fields take their defaults) and
+    /// reject any config that would weaken the built-in guarantees
+    pub fn from_json(config_json: &str) -> Result<PolicyConfig, PolicyError> {
+        let config: PolicyConfig = serde_json::from_str(config_json)
+            .map_err(|e| PolicyError::ConfigInvalid(format!("Invalid config JSON: {}", e)))?;
+        config.validate().map_err(PolicyError::ConfigInvalid)?;
+        Ok(config)
+    }
+
+    /// Structural safety checks on a config
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(missing) = BLOCKED_ACTIONS.iter().find(|a| !self.is_blocked(a)) {
+            return Err(format!(
+                "Config removes built-in blocked action '{}'. The blocklist can only grow.",
+                missing
+            ));
+        }
+        let whitelisted = self
+            .read_actions
+            .iter()
+            .chain(&self.write_actions)
+            .chain(&self.system_actions);
+        for action in whitelisted {
+            if self.is_blocked(action) {
+                return Err(format!(
+                    "Action '{}' is both whitelisted and blocked.",
+                    action
+                ));
+            }
+        }
+        for action in self.deprecated_actions.keys() {
+            let listed = self.is_read(action) || self.is_write(action) || self.is_system(action);
+            if !listed || self.is_blocked(action) {
+                return Err(format!(
+                    "Deprecated action '{}' is not a whitelisted action.",
+                    action
+                ));
+            }
+        }
+        for (rule, route) in &self.route_hints {
+            if !APPLIED_RULES.contains(&rule.as_str()) {
+                return Err(format!("route_hints names unknown rule '{}'.", rule));
+            }
+            if route.is_empty() {
+                return Err(format!("route_hints maps '{}' to an empty route.", rule));
+            }
+        }
+        for (tenant, overrides) in &self.tenant_control_overrides {
+            for (rule, control) in overrides {
+                if !APPLIED_RULES.contains(&rule.as_str()) {
+                    return Err(format!(
+                        "tenant_control_overrides for '{}' names unknown rule '{}'.",
+                        tenant, rule
+                    ));
+                }
+                if control.is_empty() {
+                    return Err(format!(
+                        "tenant_control_overrides for '{}' maps '{}' to an empty control.",
+                        tenant, rule
+                    ));
+                }
+            }
+        }
+        if self.role_rank(&self.authenticated_read_min_role).is_none() {
+            return Err(format!(
+                "authenticated_read_min_role '{}' is not in the role hierarchy.",
+                self.authenticated_read_min_role
+            ));
+        }
+        if self.repeated_denial_threshold == Some(0) {
+            return Err("repeated_denial_threshold must be at least 1.".to_string());
+        }
+        if self.max_plausible_failed_attempts.is_some_and(|max| max < self.max_failed_attempts) {
+            return Err(
+                "max_plausible_failed_attempts is below max_failed_attempts, so lockouts \
+                 would read as implausible."
+                    .to_string(),
+            );
+        }
+        if self.audit_sample_percent > 100 {
+            return Err(format!(
+                "audit_sample_percent {} is above 100.",
+                self.audit_sample_percent
+            ));
+        }
+        if self.role_rank(&self.max_elevation_role).is_none() {
+            return Err(format!(
+                "max_elevation_role '{}' is not in the role hierarchy.",
+                self.max_elevation_role
+            ));
+        }
+        for (action, role) in &self.system_action_min_role {
+            if self.role_rank(role).is_none() {
+                return Err(format!(
+                    "system_action_min_role for '{}' is '{}', which is not in the role hierarchy.",
+                    action, role
+                ));
+            }
+        }
+        for action in &self.system_actions {
+            let role = self.system_min_role(action);
+            if self.role_rank(role).is_none() {
+                return Err(format!(
+                    "System action '{}' requires role '{}', which is not in the role hierarchy.",
+                    action, role
+                ));
+            }
+        }
+        for (role, ceiling) in &self.role_max_risk {
+            if self.role_rank(role).is_none() {
+                return Err(format!(
+                    "role_max_risk names '{}', which is not in the role hierarchy.",
+                    role
+                ));
+            }
+            if *ceiling > self.role_max_risk_cap {
+                return Err(format!(
+                    "role_max_risk for '{}' is {}, above the cap of {}.",
+                    role, ceiling, self.role_max_risk_cap
+                ));
+            }
+        }
+        for request_type in self.gdpr_min_roles.keys() {
+            if !GDPR_REQUEST_TYPES.iter().any(|t| t.name == request_type) {
+                return Err(format!(
+                    "gdpr_min_roles names unknown request type '{}'.",
+                    request_type
+                ));
+            }
+        }
+        for request_type in GDPR_REQUEST_TYPES {
+            let role = self.gdpr_min_role(request_type.name);
+            if self.role_rank(role).is_none() {
+                return Err(format!(
+                    "GDPR {} requests require role '{}', which is not in the role hierarchy.",
+                    request_type.name, role
+                ));
+            }
+        }
+        if self.role_rank(&self.dual_control_min_role).is_none() {
+            return Err(format!(
+                "dual_control_min_role '{}' is not in the role hierarchy.",
+                self.dual_control_min_role
+            ));
+        }
+        if let Some(role) = &self.cross_user_exempt_role {
+            if self.role_rank(role).is_none() {
+                return Err(format!(
+                    "cross_user_exempt_role '{}' is not in the role hierarchy.",
+                    role
+                ));
+            }
+        }
+        for action in self.max_mfa_age_for_action.keys() {
+            if !self.requires_mfa(action, self.is_system(action)) {
+                return Err(format!(
+                    "max_mfa_age_for_action lists '{}', which does not require MFA.",
+                    action
+                ));
+            }
+        }
+        let trace_id_allowed = self
+            .allowed_metadata_keys
+            .as_ref()
+            .is_none_or(|keys| keys.iter().any(|k| k == "trace_id"));
+        if self.require_trace_id && !trace_id_allowed {
+            return Err(
+                "require_trace_id is set but allowed_metadata_keys omits 'trace_id'.".to_string(),
+            );
+        }
+        if let Some(read) = self.warm_session_actions.iter().find(|a| self.is_read(a)) {
+            return Err(format!("Read action '{}' cannot require a warm session.", read));
+        }
+        if self.sensitive_resource_prefixes.iter().any(String::is_empty) {
+            return Err("sensitive_resource_prefixes cannot contain an empty prefix.".to_string());
+        }
+        if self.max_concurrent_sessions == Some(0) {
+            return Err("max_concurrent_sessions must be at least 1.".to_string());
+        }
+        if let Some((action, _)) = self.action_cooldown_ms.iter().find(|(_, &ms)| ms == 0) {
+            return Err(format!("Cooldown for '{}' must be positive.", action));
+        }
+        for action in &self.lockout_exempt_reads {
+            if self.is_blocked(action)
+                || !self.is_read(action)
+                || self.authenticated_reads.contains(action)
+            {
+                return Err(format!(
+                    "Lockout-exempt action '{}' must be an unblocked, non-sensitive read.",
+                    action
+                ));
+            }
+        }
+        for (action, arguments) in &self.action_arguments {
+            for (name, argument) in arguments {
+                if let (Some(min), Some(max)) = (argument.min, argument.max) {
+                    if min > max {
+                        return Err(format!(
+                            "Argument '{}' of '{}' has min {} above max {}.",
+                            name, action, min, max
+                        ));
+                    }
+                }
+            }
+        }
+        for (alias, canonical) in &self.action_aliases {
+            if self.is_blocked(alias) != self.is_blocked(canonical) {
+                return Err(format!(
+                    "Alias '{}' -> '{}' would change block status.",
+                    alias, canonical
+                ));
+            }
+        }
+        for (component, weight) in &self.risk_component_weights {
+            if !weight.is_finite() || *weight < 0.0 {
+                return Err(format!(
+                    "Risk component weight for '{}' must be a non-negative number.",
+                    component
+                ));
+            }
+        }
+        if self.quota_window_ms == 0 {
+            return Err("quota_window_ms must be at least 1.".to_string());
+        }
+        if let Some(factor) = self.anomaly_rate_factor {
+            if !factor.is_finite() || factor <= 1.0 {
+                return Err(format!(
+                    "anomaly_rate_factor {} must be a finite number above 1.0.",
+                    factor
+                ));
+            }
+        }
+        for (hash, approval) in &self.approval_tokens {
+            if hash.len() != 64 || !hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+                return Err(format!(
+                    "Approval token key '{}' is not a lowercase SHA-256 hex digest.",
+                    hash
+                ));
+            }
+            if self.is_blocked(&approval.action) {
+                return Err(format!(
+                    "Approval token for '{}' targets a permanently blocked action.",
+                    approval.action
+                ));
+            }
+            if !self.is_read(&approval.action)
+                && !self.is_write(&approval.action)
+                && !self.is_system(&approval.action)
+            {
+                return Err(format!(
+                    "Approval token for '{}' targets an action outside the whitelist.",
+                    approval.action
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Risk from weighted `components`, or `risk_score` when composite risk
+    /// is off or a weighted component was not supplied. Unweighted
+    /// components are ignored.
+    pub fn composite_risk(
+        &self,
+        risk_score: u8,
+        components: Option<&BTreeMap<String, u8>>,
+    ) -> u8 {
+        let Some(components) = components.filter(|_| !self.risk_component_weights.is_empty())
+        else {
+            return risk_score;
+        };
+        let mut total = 0.0;
+        for (component, weight) in &self.risk_component_weights {
+            match components.get(component) {
+                Some(score) => total += weight * f64::from(*score),
+                None => return risk_score,
+            }
+        }
+        // Round half up without std's f64::round, which no_std lacks
+        (total + 0.5).clamp(0.0, 100.0) as u8
+    }
+
+    /// The approval `token` grants, if it is configured, bound to this
+    /// action and user, and unexpired at `timestamp_ms`
+    pub(crate) fn approval_for(
+        &self,
+        token: &str,
+        action: &str,
+        user_id: Option<&str>,
+        timestamp_ms: u64,
+    ) -> Option<&ApprovalToken> {
+        self.approval_tokens
+            .get(&sha256_hex(token.as_bytes()))
+            .filter(|approval| {
+                approval.action == action
+                    && Some(approval.user_id.as_str()) == user_id
+                    && timestamp_ms < approval.expires_at_ms
+            })
+    }
+
+    /// SHA-256 (hex) of the effective blocked set: sorted, de-duplicated,
+    /// newline-separated. Independent of list order in the config.
+    pub fn blocklist_checksum(&self) -> String {
+        let mut blocked: Vec<&str> = self.blocked_actions.iter().map(String::as_str).collect();
+        blocked.sort_unstable();
+        blocked.dedup();
+        sha256_hex(blocked.join("\n").as_bytes())
+    }
+
+    /// Compare the blocklist against `expected_blocklist_checksum`, if set
+    pub fn verify_blocklist(&self) -> Result<(), String> {
+        match &self.expected_blocklist_checksum {
+            Some(expected) => {
+                let actual = self.blocklist_checksum();
+                if &actual == expected {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "Blocklist checksum {} does not match expected {}.",
+                        actual, expected
+                    ))
+                }
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Resolve an action name through the alias map
+    pub fn canonical_action<'a>(&'a self, action: &'a str) -> &'a str {
+        self.action_aliases.get(action).map_or(action, |c| c.as_str())
+    }
+
+    /// Session-age limit in force for a write (`false`) or system (`true`) action
+    pub fn session_age_limit(&self, is_system: bool) -> u64 {
+        let write = self.max_session_age_write.unwrap_or(self.max_session_age_sensitive);
+        if is_system {
+            let system = self.max_session_age_system.unwrap_or(self.max_session_age_sensitive);
+            system.min(write)
+        } else {
+            write
+        }
+    }
+
+    /// Whether a session of this age is past its limit plus the skew tolerance
+    pub fn session_expired(&self, session_age_seconds: u64, is_system: bool) -> bool {
+        let limit = self.session_age_limit(is_system);
+        session_age_seconds > limit.saturating_add(self.session_age_tolerance_seconds)
+    }
+
+    /// Sunset of a deprecated `action`, if it has one
+    pub fn sunset_ms(&self, action: &str) -> Option<u64> {
+        self.deprecated_actions.get(action).copied()
+    }
+
+    /// Whether `action` was retired by its sunset as of `timestamp_ms`
+    pub fn sunset_passed(&self, action: &str, timestamp_ms: u64) -> bool {
+        self.sunset_ms(action).is_some_and(|sunset| timestamp_ms >= sunset)
+    }
+
+    /// Whether a verified MFA is too old for `action`: past its age limit,
+    /// or of unknown age under `require_mfa_age`. No limit, never stale.
+    pub fn mfa_stale(&self, action: &str, mfa_age_seconds: Option<u64>) -> bool {
+        let limit = self
+            .max_mfa_age_for_action
+            .get(action)
+            .copied()
+            .or(self.max_mfa_age_seconds);
+        match (limit, mfa_age_seconds) {
+            (None, _) => false,
+            (Some(limit), Some(age)) => age > limit,
+            (Some(_), None) => self.require_mfa_age,
+        }
+    }
+
+    /// Whether `action` demands MFA: listed in `mfa_required`, or a
+    /// system action when no list is configured
+    pub fn requires_mfa(&self, action: &str, is_system: bool) -> bool {
+        match &self.mfa_required {
+            Some(actions) => actions.iter().any(|a| a == action),
+            None => is_system,
+        }
+    }
+
+    /// Whether a session is past its limit but still inside the tolerance band
+    pub fn session_in_grace(&self, session_age_seconds: u64, is_system: bool) -> bool {
+        session_age_seconds > self.session_age_limit(is_system)
+            && !self.session_expired(session_age_seconds, is_system)
+    }
+
+    /// Rank of a role in the hierarchy (0 = least privileged);
+    /// `None` for unrecognized roles
+    pub fn role_rank(&self, role: &str) -> Option<usize> {
+        self.role_hierarchy.iter().position(|r| r == role)
+    }
+
+    /// Whether `role` ranks at or above `min_role`; unranked roles never
+    /// do, and nothing meets an unranked minimum
+    pub fn role_at_least(&self, role: &str, min_role: &str) -> bool {
+        matches!(
+            (self.role_rank(role), self.role_rank(min_role)),
+            (Some(rank), Some(min)) if rank >= min
+        )
+    }
+
+    /// Whether a role may act on other users' data in `user_scoped_actions`
+    pub fn cross_user_exempt(&self, role: &str) -> bool {
+        let exempt = self.cross_user_exempt_role.as_deref().and_then(|r| self.role_rank(r));
+        matches!((self.role_rank(role), exempt), (Some(rank), Some(min)) if rank >= min)
+    }
+
+    /// Least role that may file a `request_type` GDPR request for another
+    pub fn gdpr_min_role(&self, request_type: &str) -> &str {
+        self.gdpr_min_roles.get(request_type).map_or("org_admin", String::as_str)
+    }
+
+    /// Whether `target_resource` falls under a sensitive prefix
+    pub fn sensitive_target(&self, target_resource: &str) -> bool {
+        self.sensitive_resource_prefixes.iter().any(|p| target_resource.starts_with(p.as_str()))
+    }
+
+    /// Route for a decision under `rule`
+    pub fn route_hint(&self, rule: &str) -> &str {
+        self.route_hints.get(rule).map_or(DEFAULT_ROUTE, String::as_str)
+    }
+
+    /// The tenant's replacement `iso_control` for `rule`, if it set one
+    pub fn tenant_control(&self, organisation_id: Option<&str>, rule: &str) -> Option<&str> {
+        let overrides = self.tenant_control_overrides.get(organisation_id?)?;
+        overrides.get(rule).map(String::as_str)
+    }
+
+    /// Risk ceiling for `role`: its `role_max_risk` entry, else
+    /// `max_risk_score`. Unrecognized roles fail safe to the lowest of all.
+    pub fn risk_ceiling(&self, role: &str) -> u8 {
+        if self.role_rank(role).is_none() {
+            let lowest = self.role_max_risk.values().copied().min();
+            return lowest.map_or(self.max_risk_score, |l| l.min(self.max_risk_score));
+        }
+        self.role_max_risk.get(role).copied().unwrap_or(self.max_risk_score)
+    }
+
+    /// Whether a role may take part in a dual-control action; unranked
+    /// and absent roles never may
+    pub fn dual_control_eligible(&self, role: Option<&str>) -> bool {
+        let rank = role.and_then(|r| self.role_rank(r));
+        let min = self.role_rank(&self.dual_control_min_role);
+        matches!((rank, min), (Some(rank), Some(min)) if rank >= min)
+    }
+
+    /// Every context value beyond its plausibility limit, as `field=value`
+    pub fn implausible_context(&self, context: &SecurityContext) -> Vec<String> {
+        let mut implausible = Vec::new();
+        let session_age = context.session_age_seconds;
+        if session_age != u64::MAX
+            && self.max_plausible_session_age_seconds.is_some_and(|max| session_age > max)
+        {
+            implausible.push(format!("session_age_seconds={}", session_age));
+        }
+        let failed = context.failed_attempts_last_hour;
+        if self.max_plausible_failed_attempts.is_some_and(|max| failed > max) {
+            implausible.push(format!("failed_attempts_last_hour={}", failed));
+        }
+        implausible
+    }
+
+    /// Least role allowed to perform a system action
+    pub fn system_min_role(&self, action: &str) -> &str {
+        self.system_action_min_role
+            .get(action)
+            .map_or("power_user", String::as_str)
+    }
+
+    /// Category of an action by linear scan of the configured lists.
+    /// The hot path uses `ActionIndex`; this is the reference it must match.
+    #[cfg(test)]
+    pub(crate) fn action_category(&self, action: &str) -> ActionCategory {
+        if self.is_blocked(action) {
+            ActionCategory::Blocked
+        } else if self.is_system(action) {
+            ActionCategory::System
+        } else if self.is_write(action) {
+            ActionCategory::Write
+        } else if self.is_read(action) {
+            ActionCategory::Read
+        } else {
+            ActionCategory::Unlisted
+        }
+    }
+
+    pub fn is_blocked(&self, action: &str) -> bool {
+        self.blocked_actions.iter().any(|a| a == action)
+    }
+
+    pub fn is_read(&self, action: &str) -> bool {
+        self.read_actions.iter().any(|a| a == action)
+    }
+
+    pub fn is_write(&self, action: &str) -> bool {
+        self.write_actions.iter().any(|a| a == action)
+    }
+
+    pub fn is_system(&self, action: &str) -> bool {
+        self.system_actions.iter().any(|a| a == action)
+    }
+}
+
+/// Which list an action belongs to. When an action appears in several
+/// lists the most restrictive wins: blocked, then system, then write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ActionCategory {
+    Blocked,
+    System,
+    Write,
+    Read,
+    Unlisted,
+}
+
+impl ActionCategory {
+    /// Wire name, as reported by `classify_action_name`
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            ActionCategory::Blocked => "blocked",
+            ActionCategory::System => "system",
+            ActionCategory::Write => "write",
+            ActionCategory::Read => "read",
+            ActionCategory::Unlisted => "unknown",
+        }
+    }
+}
+
+/// Hashed action -> category lookup, built once per loaded config so the
+/// cascade does one map probe instead of scanning four lists
+#[derive(Debug, Clone)]
+pub(crate) struct ActionIndex {
+    categories: CategoryMap,
+    /// Let unconditioned reads skip the write/system-only rules.
+    /// Only turned off by tests comparing against the full cascade.
+    pub(crate) read_fast_path: bool,
+}
+
+impl ActionIndex {
+    pub(crate) fn new(config: &PolicyConfig) -> ActionIndex {
+        let mut categories = CategoryMap::new();
+        // Least restrictive first, so later inserts win on overlap
+        let lists = [
+            (&config.read_actions, ActionCategory::Read),
+            (&config.write_actions, ActionCategory::Write),
+            (&config.system_actions, ActionCategory::System),
+            (&config.blocked_actions, ActionCategory::Blocked),
+        ];
+        for (actions, category) in lists {
+            for action in actions {
+                categories.insert(action.clone(), category);
+            }
+        }
+        ActionIndex {
+            categories,
+            read_fast_path: true,
+        }
+    }
+
+    pub(crate) fn category(&self, action: &str) -> ActionCategory {
+        self.categories
+            .get(action)
+            .copied()
+            .unwrap_or(ActionCategory::Unlisted)
+    }
+}
+
+pub(crate) fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn owned(actions: &[&str]) -> Vec<String> {
+    actions.iter().map(|a| a.to_string()).collect()
+}
+
+/// A single numeric comparison against one metadata field.
+/// Intentionally minimal — this is not an expression engine.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MetadataCondition {
+    /// Top-level key in `AiRequest.metadata`
+    pub key: String,
+    /// Largest permitted value (inclusive)
+    pub max_value: f64,
+}
+
+/// Constraint on one argument an action carries in `AiRequest.metadata`.
+/// Every declared argument is required; unset bounds are not checked.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ArgumentSchema {
+    /// Values the argument may take, e.g. the known UI themes
+    pub allowed_values: Option<Vec<serde_json::Value>>,
+    /// Smallest permitted numeric value (inclusive)
+    pub min: Option<f64>,
+    /// Largest permitted numeric value (inclusive)
+    pub max: Option<f64>,
+}
+
+impl ArgumentSchema {
+    /// Fails closed: a missing argument, or a non-numeric one under a
+    /// numeric bound, is an error
+    pub fn check(&self, name: &str, metadata: Option<&serde_json::Value>) -> Result<(), String> {
+        let value = metadata
+            .and_then(|m| m.get(name))
+            .ok_or_else(|| format!("argument '{}' is missing.", name))?;
+        if let Some(allowed) = &self.allowed_values {
+            if !allowed.contains(value) {
+                return Err(format!("argument '{}' = {} is not an allowed value.", name, value));
+            }
+        }
+        if self.min.is_some() || self.max.is_some() {
+            let number = value
+                .as_f64()
+                .ok_or_else(|| format!("argument '{}' is not numeric.", name))?;
+            if self.min.is_some_and(|min| number < min) || self.max.is_some_and(|max| number > max)
+            {
+                return Err(format!("argument '{}' = {} is out of range.", name, number));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A human approval bound to one action for one user, until expiry
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ApprovalToken {
+    pub action: String,
+    pub user_id: String,
+    /// Token is honoured while the request timestamp is before this (Unix ms)
+    pub expires_at_ms: u64,
+}
+
+impl MetadataCondition {
+    /// Fails closed: a missing, non-numeric or oversized value is an error
+    pub fn check(&self, metadata: Option<&serde_json::Value>) -> Result<(), String> {
+        let value = metadata
+            .and_then(|m| m.get(&self.key))
+            .ok_or_else(|| format!("required metadata key '{}' is missing.", self.key))?;
+        let number = value
+            .as_f64()
+            .ok_or_else(|| format!("metadata key '{}' is not numeric.", self.key))?;
+        if number > self.max_value {
+            return Err(format!(
+                "metadata '{}' = {} exceeds the limit of {}.",
+                self.key, number, self.max_value
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,2311 @@
+// ============================================================
+// Infinity OS — Stateful Policy Engine
+//
+// Wraps the pure rule cascade with a loaded configuration.
+// The free `validate_ai_action` export always uses the
+// built-in defaults; hosts that manage policy in git load
+// their config once through `PolicyEngine::from_config`.
+// ============================================================
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::panic::{self, AssertUnwindSafe};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::config::{ActionCategory, ActionIndex};
+use crate::{
+    annotate_decision, audit_chain_hash, audit_record, check_request_metadata, denying_stage,
+    evaluate_at_risk, gdpr_request_decision, graph_node, merge_audit, parse_context, parse_inputs,
+    permitted_actions, preview_decision, redact_reason, ruleset_graph_json, ruleset_nodes,
+    serialize_decision, serialize_gdpr_decision, to_canonical_json, AiRequest, PolicyConfig,
+    PolicyDecision, PolicyError, SecurityContext, AUDIT_CHAIN_GENESIS, BUILTIN_RULES,
+    DECISION_SCHEMA_VERSION, PINNED_RULES,
+};
+
+/// Observe-only callback invoked with every evaluated decision
+pub type DecisionHook = Box<dyn Fn(&AiRequest, &PolicyDecision)>;
+
+/// An organisation-specific rule plugged into `PolicyEngine` with
+/// `add_rule`. Returning `Some` denial short-circuits evaluation; `None`
+/// passes to the next rule. A custom rule can only deny: a permitted
+/// decision it returns is treated as a pass.
+pub trait Rule {
+    fn evaluate(
+        &self,
+        request: &AiRequest,
+        context: &SecurityContext,
+        timestamp_ms: u64,
+    ) -> Option<PolicyDecision>;
+}
+
+impl<F> Rule for F
+where
+    F: Fn(&AiRequest, &SecurityContext, u64) -> Option<PolicyDecision>,
+{
+    fn evaluate(
+        &self,
+        request: &AiRequest,
+        context: &SecurityContext,
+        timestamp_ms: u64,
+    ) -> Option<PolicyDecision> {
+        self(request, context, timestamp_ms)
+    }
+}
+
+/// Priority of the built-in stage labelled `stage` (as in `ruleset_graph`,
+/// e.g. `"RULE 5"`), for placing custom rules among the built-ins. `None`
+/// for unknown stages and for the pinned head — panic mode, blocklist
+/// integrity and the hard block — which no custom rule can precede.
+pub fn builtin_rule_priority(stage: &str) -> Option<i32> {
+    BUILTIN_RULES
+        .iter()
+        .position(|rule| rule.stage == stage)
+        .filter(|&index| index >= PINNED_RULES)
+        .map(stage_priority)
+}
+
+/// Built-in stages sit 100 apart, in cascade order, leaving room for
+/// custom rules between any two
+fn stage_priority(index: usize) -> i32 {
+    index as i32 * 100
+}
+
+struct RegisteredRule {
+    priority: i32,
+    rule: Box<dyn Rule>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct PolicyEngine {
+    config: PolicyConfig,
+    /// Built from `config` at construction; the action lists never change after
+    index: ActionIndex,
+    grants: Vec<TemporaryGrant>,
+    decision_hook: Option<DecisionHook>,
+    /// Custom rules, ordered by priority (registration order within one)
+    rules: Vec<RegisteredRule>,
+    /// Quota consumption per (user_id, action) for the current window
+    quota_usage: HashMap<(String, String), QuotaUsage>,
+    /// Last permit time (ms) per session key, per action
+    recent_permits: HashMap<String, HashMap<String, u64>>,
+    decision_cache: DecisionCache,
+    /// Highest risk score seen per session key
+    session_max_risk: HashMap<String, u8>,
+    /// Request-interval baseline per user_id, for velocity anomalies
+    velocity: HashMap<String, Velocity>,
+    /// End of the high-risk cool-down (ms) per user_id
+    risk_cooldowns: HashMap<String, u64>,
+    /// Trusted-network permits per (user_id, action), for risk decay
+    trusted_permits: HashMap<(String, String), u32>,
+    /// Last permit time (ms) per (user_id, action), for action cooldowns
+    last_permits: HashMap<(String, String), u64>,
+    /// Consecutive denials per (user_id, action), for repeated-denial escalation
+    denial_streaks: HashMap<(String, String), DenialStreak>,
+    /// Decisions evaluated since construction (cache hits included)
+    evaluations: u64,
+    /// Why the supplied config was refused, when running degraded
+    degraded: Option<String>,
+    /// Most recent audited decisions, oldest first, for `audit_summary`
+    audit_log: VecDeque<AuditEntry>,
+    /// Latest `timestamp_ms` evaluated, the end of every summary window
+    latest_timestamp_ms: u64,
+    /// `this_hash` of the last chained audit record
+    audit_chain_head: String,
+}
+
+/// Length of the nonce prefixed to an encrypted config blob
+const CONFIG_NONCE_LEN: usize = 12;
+
+/// Open an encrypted config blob (`nonce || ciphertext`) as config JSON
+fn decrypt_config(blob: &[u8], key: &[u8]) -> Result<String, PolicyError> {
+    use chacha20poly1305::aead::{Aead, KeyInit};
+    use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+
+    let refuse = |reason: &str| PolicyError::ConfigInvalid(reason.to_string());
+    let cipher = ChaCha20Poly1305::new_from_slice(key)
+        .map_err(|_| refuse("Config key must be 32 bytes."))?;
+    if blob.len() < CONFIG_NONCE_LEN {
+        return Err(refuse("Encrypted config is too short to hold a nonce."));
+    }
+    let (nonce, ciphertext) = blob.split_at(CONFIG_NONCE_LEN);
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| refuse("Encrypted config failed to decrypt: wrong key or tampered blob."))?;
+    String::from_utf8(plaintext).map_err(|_| refuse("Decrypted config is not UTF-8."))
+}
+
+/// What `audit_summary` keeps of one audited decision
+#[derive(Debug, Clone)]
+struct AuditEntry {
+    timestamp_ms: u64,
+    action: String,
+    applied_rule: String,
+    organisation_id: Option<String>,
+    permitted: bool,
+}
+
+/// Audited decisions retained for `audit_summary`; older ones are dropped
+const AUDIT_LOG_CAPACITY: usize = 10_000;
+
+/// Denied actions listed in `audit_summary`'s `top_denied`
+const TOP_DENIED_LIMIT: usize = 5;
+
+/// Exponentially-weighted moving average of one user's request intervals
+#[derive(Debug, Clone, Copy)]
+struct Velocity {
+    last_ms: u64,
+    /// Baseline interval (ms); meaningful once `samples` > 0
+    ewma_interval_ms: f64,
+    /// Intervals folded into the baseline so far
+    samples: u32,
+}
+
+/// One user's run of denials for one action
+#[derive(Debug, Clone, Copy, Default)]
+struct DenialStreak {
+    count: u32,
+    last_ms: u64,
+    /// End (ms) of the REPEATED_DENIAL_BLOCK the streak triggered; 0 if none
+    blocked_until_ms: u64,
+}
+
+/// Weight of the newest interval in the moving average
+const VELOCITY_EWMA_ALPHA: f64 = 0.2;
+
+/// Bounded FIFO cache of pure-cascade decisions. Keys are the canonical
+/// JSON of the `(request, context)` pair, so field order in the caller's
+/// JSON does not matter and distinct inputs can never collide.
+/// Any change to the engine's config (a new engine, or `set_freeze`)
+/// starts from an empty cache.
+#[derive(Debug, Default)]
+struct DecisionCache {
+    capacity: usize,
+    entries: HashMap<String, PolicyDecision>,
+    /// Insertion order, oldest first, for eviction
+    order: VecDeque<String>,
+    hits: u64,
+    misses: u64,
+}
+
+impl DecisionCache {
+    fn with_capacity(capacity: usize) -> DecisionCache {
+        DecisionCache {
+            capacity,
+            ..Default::default()
+        }
+    }
+
+    fn get(&mut self, key: &str) -> Option<&PolicyDecision> {
+        let entry = self.entries.get(key);
+        if entry.is_some() {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+        }
+        entry
+    }
+
+    /// Drop every entry; hit/miss counters are kept
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+
+    fn insert(&mut self, key: String, decision: PolicyDecision) {
+        if self.entries.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.order.push_back(key.clone());
+        self.entries.insert(key, decision);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct QuotaUsage {
+    /// Window index (`timestamp_ms / quota_window_ms`)
+    window: u64,
+    used: u32,
+}
+
+/// Time-boxed permission for one user to perform one otherwise
+/// non-whitelisted action. Hard-blocked actions can never be granted.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TemporaryGrant {
+    pub action: String,
+    pub user_id: String,
+    /// Grant is honoured while the request timestamp is before this (Unix ms)
+    pub expires_at_ms: u64,
+}
+
+impl Default for PolicyEngine {
+    fn default() -> Self {
+        PolicyEngine::new()
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl PolicyEngine {
+    /// Engine running the built-in default policy
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new() -> PolicyEngine {
+        PolicyEngine::with_config(PolicyConfig::default())
+    }
+
+    /// Engine running a JSON config layered over the defaults.
+    /// Invalid or weakening configs are refused.
+    pub fn from_config(config_json: &str) -> Result<PolicyEngine, PolicyError> {
+        Ok(PolicyEngine::with_config(PolicyConfig::from_json(config_json)?))
+    }
+
+    /// Engine running a config kept encrypted at rest. `blob` is a 12-byte
+    /// nonce followed by the ChaCha20-Poly1305 ciphertext of the config
+    /// JSON; `key` is the 32-byte key. The host supplies the key at startup
+    /// (from its secret store or KMS) and must never embed it in the bundle
+    /// or storage holding the blob. A wrong key or tampered blob is refused
+    /// like an invalid config, so the engine never starts on a guess.
+    pub fn from_encrypted_config(blob: &[u8], key: &[u8]) -> Result<PolicyEngine, PolicyError> {
+        PolicyEngine::from_config(&decrypt_config(blob, key)?)
+    }
+
+    /// Engine for a config that may be bad. A refused config yields a
+    /// degraded engine instead of an error: it runs the built-in policy but
+    /// permits only built-in reads, audits every decision, and denies all
+    /// else with ENGINE_DEGRADED. `engine_status` reports the state.
+    pub fn from_config_or_degraded(config_json: &str) -> PolicyEngine {
+        PolicyEngine::from_config(config_json).unwrap_or_else(|error| PolicyEngine {
+            degraded: Some(error.to_string()),
+            ..PolicyEngine::new()
+        })
+    }
+
+    /// `{degraded, reason, ruleset_version}`: whether this engine is running
+    /// degraded and, if so, why its config was refused
+    pub fn engine_status(&self) -> String {
+        serde_json::json!({
+            "degraded": self.degraded.is_some(),
+            "reason": self.degraded,
+            "ruleset_version": self.config.ruleset_version,
+        })
+        .to_string()
+    }
+
+    /// Same contract as the free `validate_ai_action`, under this engine's config
+    pub fn validate_ai_action(
+        &mut self,
+        request_json: &str,
+        context_json: &str,
+        timestamp_ms: u64,
+    ) -> String {
+        self.validate_ai_action_v(request_json, context_json, timestamp_ms, DECISION_SCHEMA_VERSION)
+    }
+
+    /// Same contract as the free `validate_ai_action_v`, under this engine's config
+    pub fn validate_ai_action_v(
+        &mut self,
+        request_json: &str,
+        context_json: &str,
+        timestamp_ms: u64,
+        schema_version: u32,
+    ) -> String {
+        let decision = match self.try_validate(request_json, context_json, timestamp_ms) {
+            Ok(decision) => decision,
+            Err(error) => self.flag_degraded(error.into_decision(timestamp_ms)),
+        };
+        serialize_decision(&decision, schema_version)
+    }
+
+    /// Same contract as the free `validate_and_audit`, under this engine's
+    /// config, except that each record is chained to the one before it
+    /// through `prev_hash` and `this_hash` (see `verify_audit_chain`)
+    pub fn validate_and_audit(
+        &mut self,
+        request_json: &str,
+        context_json: &str,
+        timestamp_ms: u64,
+    ) -> String {
+        let (decision, request) =
+            match parse_inputs(&self.config, request_json, context_json) {
+                Ok((request, context)) => {
+                    (self.evaluate_parsed(&request, &context, timestamp_ms), Some(request))
+                }
+                Err(error) => (self.flag_degraded(error.into_decision(timestamp_ms)), None),
+            };
+        let decision_json = serialize_decision(&decision, DECISION_SCHEMA_VERSION);
+        let record_json = if decision.audit_required {
+            let mut record = audit_record(request.as_ref(), &decision, &decision_json);
+            record.prev_hash = std::mem::take(&mut self.audit_chain_head);
+            record.this_hash = audit_chain_hash(&record);
+            self.audit_chain_head = record.this_hash.clone();
+            serde_json::to_string(&record).unwrap_or_default()
+        } else {
+            "null".to_string()
+        };
+        format!(r#"{{"decision":{},"audit_record":{}}}"#, decision_json, record_json)
+    }
+
+    /// Allow `user_id` to perform a non-whitelisted `action` until
+    /// `expires_at_ms`. Refused for hard-blocked actions.
+    pub fn add_temporary_grant(
+        &mut self,
+        action: &str,
+        user_id: &str,
+        expires_at_ms: u64,
+    ) -> Result<(), String> {
+        if self.config.is_blocked(action) {
+            return Err(format!(
+                "Action '{}' is permanently blocked and cannot be granted.",
+                action
+            ));
+        }
+        self.grants.push(TemporaryGrant {
+            action: action.to_string(),
+            user_id: user_id.to_string(),
+            expires_at_ms,
+        });
+        Ok(())
+    }
+
+    /// All registered grants (including expired ones) as a JSON array
+    pub fn list_temporary_grants(&self) -> String {
+        serde_json::to_string(&self.grants).unwrap_or_default()
+    }
+
+    /// Effective resolved configuration (defaults + overrides) as
+    /// deterministically-ordered JSON, so GitOps reviews diff cleanly.
+    /// Every set-like list is sorted; `role_hierarchy` keeps its order,
+    /// which is the ranking.
+    pub fn dump_effective_policy(&self) -> String {
+        let mut effective = self.config.clone();
+        let config = &mut effective;
+        let sets = [
+            &mut config.read_actions,
+            &mut config.write_actions,
+            &mut config.system_actions,
+            &mut config.blocked_actions,
+            &mut config.authenticated_reads,
+            &mut config.lockout_exempt_reads,
+            &mut config.resource_deny_patterns,
+            &mut config.sensitive_resource_prefixes,
+            &mut config.deprecated_module_versions,
+            &mut config.actions_requiring_org,
+            &mut config.actions_requiring_user,
+            &mut config.actions_requiring_metadata,
+            &mut config.trusted_network_required,
+            &mut config.user_scoped_actions,
+            &mut config.dual_control_actions,
+            &mut config.warm_session_actions,
+        ];
+        let optional_sets = [&mut config.mfa_required, &mut config.allowed_metadata_keys];
+        sets.into_iter()
+            .chain(optional_sets.into_iter().flatten())
+            .chain(config.action_prerequisites.values_mut())
+            .chain(config.mutually_exclusive_actions.iter_mut())
+            .for_each(|set| set.sort());
+        config.mutually_exclusive_actions.sort();
+        to_canonical_json(&effective)
+    }
+
+    /// `permitted_actions_for_context` under this engine's config. Grants,
+    /// prerequisites and quotas are not consulted, and nothing is consumed.
+    pub fn permitted_actions_for_context(&self, context_json: &str, timestamp_ms: u64) -> String {
+        match parse_context(&self.config, context_json) {
+            Ok(context) => {
+                permitted_actions(&self.config, &self.index, &context, timestamp_ms).to_string()
+            }
+            Err(error) => {
+                serialize_decision(&error.into_decision(timestamp_ms), DECISION_SCHEMA_VERSION)
+            }
+        }
+    }
+
+    /// `preview_with_role` under this engine's config. Like
+    /// `permitted_actions_for_context`, only the cascade is consulted and
+    /// no counter, cache or session state is touched.
+    pub fn preview_with_role(
+        &self,
+        request_json: &str,
+        context_json: &str,
+        hypothetical_role: &str,
+        timestamp_ms: u64,
+    ) -> String {
+        let decision = preview_decision(
+            &self.config,
+            &self.index,
+            request_json,
+            context_json,
+            hypothetical_role,
+            timestamp_ms,
+        );
+        serialize_decision(&decision, DECISION_SCHEMA_VERSION)
+    }
+
+    /// `validate_gdpr_request` under this engine's `gdpr_min_roles`
+    pub fn validate_gdpr_request(
+        &self,
+        request_type: &str,
+        user_id: &str,
+        requesting_user_id: &str,
+        requester_role: &str,
+        timestamp_ms: u64,
+    ) -> String {
+        let decision = gdpr_request_decision(
+            &self.config,
+            request_type,
+            user_id,
+            requesting_user_id,
+            requester_role,
+            timestamp_ms,
+        );
+        serialize_gdpr_decision(&decision)
+    }
+
+    /// Turn the maintenance freeze on or off. Takes effect on the next call;
+    /// cached decisions are dropped since they predate the change.
+    pub fn set_freeze(&mut self, frozen: bool) {
+        if self.config.freeze_writes != frozen {
+            self.config.freeze_writes = frozen;
+            self.decision_cache.clear();
+        }
+    }
+
+    /// Turn panic mode on or off. Idempotent; takes effect on the next call,
+    /// and cached decisions are dropped since they predate the change.
+    pub fn set_panic_mode(&mut self, enabled: bool) {
+        if self.config.panic_mode != enabled {
+            self.config.panic_mode = enabled;
+            self.decision_cache.clear();
+        }
+    }
+
+    /// `ruleset_graph` for this engine: the configured cascade plus custom
+    /// rules and the stateful overlays (grants, prerequisites, exclusions,
+    /// quotas), in the order `validate_ai_action` applies them
+    pub fn ruleset_graph(&self) -> String {
+        let custom = |rule: &RegisteredRule| {
+            graph_node(
+                &format!("CUSTOM RULE (priority {})", rule.priority),
+                &[],
+                "Organisation-specific rule registered with add_rule".to_string(),
+                "deny",
+                "",
+                true,
+            )
+        };
+        let mut nodes = ruleset_nodes(&self.config);
+        let position = |nodes: &[serde_json::Value], stage: &str| {
+            nodes.iter().position(|n| n["stage"] == stage).unwrap_or(nodes.len())
+        };
+        // The overlays outrank every built-in denial but the pinned head's
+        let pinned = position(&nodes, BUILTIN_RULES[PINNED_RULES].stage);
+        let degraded = graph_node(
+            "DEGRADED",
+            &["ENGINE_DEGRADED"],
+            "The engine's config was refused and the action is not a built-in read".to_string(),
+            "deny",
+            "A.17.1.1",
+            self.degraded.is_some(),
+        );
+        let repeated = graph_node(
+            "REPEATED DENIAL",
+            &["REPEATED_DENIAL_BLOCK"],
+            format!(
+                "The user was denied the action {} times in a row within {}ms windows",
+                self.config.repeated_denial_threshold.unwrap_or_default(),
+                self.config.repeated_denial_window_ms
+            ),
+            "deny",
+            "A.9.4.3",
+            self.config.repeated_denial_threshold.is_some()
+                && self.config.repeated_denial_block_ms > 0,
+        );
+        nodes.splice(pinned..pinned, [degraded, repeated]);
+        nodes.push(graph_node(
+            "TEMPORARY GRANT",
+            &["TEMPORARY_GRANT"],
+            "A NOT_IN_WHITELIST denial for a user holding an unexpired grant".to_string(),
+            "permit",
+            "A.9.2.2",
+            !self.grants.is_empty(),
+        ));
+        // Each custom rule goes ahead of the first built-in it outranks;
+        // past every built-in, it joins the overlays that see permits only
+        for rule in &self.rules {
+            let next = (PINNED_RULES..BUILTIN_RULES.len())
+                .find(|&index| stage_priority(index) > rule.priority)
+                .map_or(nodes.len(), |index| position(&nodes, BUILTIN_RULES[index].stage));
+            nodes.insert(next, custom(rule));
+        }
+        nodes.push(graph_node(
+            "PREREQUISITES",
+            &["MISSING_PREREQUISITE"],
+            format!(
+                "A prerequisite action was not permitted in this session within {}ms",
+                self.config.prerequisite_window_ms
+            ),
+            "deny",
+            "A.12.1.1",
+            !self.config.action_prerequisites.is_empty(),
+        ));
+        nodes.push(graph_node(
+            "WARM SESSION",
+            &["COLD_SESSION"],
+            format!(
+                "A warm-session action with no permit in this session in the last {}ms",
+                self.config.warm_session_window_ms
+            ),
+            "deny",
+            "A.9.4.2",
+            self.config.warm_session_window_ms > 0 && !self.config.warm_session_actions.is_empty(),
+        ));
+        nodes.push(graph_node(
+            "MUTUAL EXCLUSION",
+            &["MUTUALLY_EXCLUSIVE_ACTION"],
+            "Another member of the action's exclusion group was permitted this session"
+                .to_string(),
+            "deny",
+            "A.6.1.2",
+            !self.config.mutually_exclusive_actions.is_empty(),
+        ));
+        nodes.push(graph_node(
+            "COOLDOWN",
+            &["ACTION_COOLDOWN"],
+            "The user was permitted the action within its cooldown".to_string(),
+            "deny",
+            "A.12.1.3",
+            !self.config.action_cooldown_ms.is_empty(),
+        ));
+        nodes.push(graph_node(
+            "QUOTA",
+            &["QUOTA_EXCEEDED"],
+            format!(
+                "The user's quota for the action is spent for the current {}ms window",
+                self.config.quota_window_ms
+            ),
+            "deny",
+            "A.12.1.3",
+            !self.config.action_quotas.is_empty(),
+        ));
+        ruleset_graph_json(&self.config, nodes)
+    }
+
+    /// SHA-256 (hex) of this engine's effective blocklist
+    pub fn blocklist_checksum(&self) -> String {
+        self.config.blocklist_checksum()
+    }
+
+    /// Rollup of audited decisions in the `window_ms` ending at the latest
+    /// timestamp evaluated: `{from_ms, to_ms, audited, by_action, by_rule,
+    /// by_org, top_denied, truncated}`. Requests without an organisation
+    /// count under `""`. Only the most recent 10,000 audited decisions are
+    /// held in memory; `truncated` is set when older ones the window would
+    /// cover have been dropped, so hosts needing a complete trail must
+    /// persist records as they are produced.
+    pub fn audit_summary(&self, window_ms: u64) -> String {
+        let from_ms = self.latest_timestamp_ms.saturating_sub(window_ms);
+        let mut by_action: BTreeMap<&str, u64> = BTreeMap::new();
+        let mut by_rule: BTreeMap<&str, u64> = BTreeMap::new();
+        let mut by_org: BTreeMap<&str, u64> = BTreeMap::new();
+        let mut denied: BTreeMap<&str, u64> = BTreeMap::new();
+        let in_window = self.audit_log.iter().filter(|e| e.timestamp_ms >= from_ms);
+        let mut audited = 0;
+        for entry in in_window {
+            audited += 1;
+            *by_action.entry(&entry.action).or_insert(0) += 1;
+            *by_rule.entry(&entry.applied_rule).or_insert(0) += 1;
+            *by_org.entry(entry.organisation_id.as_deref().unwrap_or("")).or_insert(0) += 1;
+            if !entry.permitted {
+                *denied.entry(&entry.action).or_insert(0) += 1;
+            }
+        }
+        // Most denied first; ties in action-name order
+        let mut top_denied: Vec<(&str, u64)> = denied.into_iter().collect();
+        top_denied.sort_by_key(|&(_, count)| core::cmp::Reverse(count));
+        top_denied.truncate(TOP_DENIED_LIMIT);
+        let top_denied: Vec<serde_json::Value> = top_denied
+            .into_iter()
+            .map(|(action, count)| serde_json::json!({ "action": action, "count": count }))
+            .collect();
+        let truncated = self.audit_log.len() >= AUDIT_LOG_CAPACITY
+            && self.audit_log.front().is_some_and(|e| e.timestamp_ms > from_ms);
+        serde_json::json!({
+            "from_ms": from_ms,
+            "to_ms": self.latest_timestamp_ms,
+            "audited": audited,
+            "by_action": by_action,
+            "by_rule": by_rule,
+            "by_org": by_org,
+            "top_denied": top_denied,
+            "truncated": truncated,
+        })
+        .to_string()
+    }
+
+    /// Engine counters as JSON: total evaluations and decision-cache stats
+    pub fn metrics_snapshot(&self) -> String {
+        serde_json::json!({
+            "evaluations": self.evaluations,
+            "decision_cache": {
+                "capacity": self.decision_cache.capacity,
+                "entries": self.decision_cache.entries.len(),
+                "hits": self.decision_cache.hits,
+                "misses": self.decision_cache.misses,
+            },
+        })
+        .to_string()
+    }
+}
+
+impl PolicyEngine {
+    fn with_config(config: PolicyConfig) -> PolicyEngine {
+        PolicyEngine {
+            index: ActionIndex::new(&config),
+            grants: Vec::new(),
+            decision_hook: None,
+            rules: Vec::new(),
+            quota_usage: HashMap::new(),
+            recent_permits: HashMap::new(),
+            decision_cache: DecisionCache::with_capacity(config.decision_cache_capacity),
+            session_max_risk: HashMap::new(),
+            velocity: HashMap::new(),
+            risk_cooldowns: HashMap::new(),
+            denial_streaks: HashMap::new(),
+            last_permits: HashMap::new(),
+            trusted_permits: HashMap::new(),
+            evaluations: 0,
+            degraded: None,
+            audit_log: VecDeque::new(),
+            latest_timestamp_ms: 0,
+            audit_chain_head: AUDIT_CHAIN_GENESIS.to_string(),
+            config,
+        }
+    }
+
+    /// The resolved configuration this engine evaluates against
+    pub fn config(&self) -> &PolicyConfig {
+        &self.config
+    }
+
+    /// Register a callback invoked after every evaluation, for logging or
+    /// metrics in native (non-WASM) hosts. Replaces any previous hook.
+    ///
+    /// The hook is observe-only: it receives shared references to an
+    /// already-final decision and cannot alter what the engine returns.
+    /// It runs after all engine state for the evaluation is settled, and a
+    /// panic inside it is caught and discarded (when built with unwinding),
+    /// so a faulty hook can neither corrupt engine state nor change a verdict.
+    ///
+    /// Not exported to WASM; JS hosts observe the returned JSON instead.
+    pub fn set_decision_hook(&mut self, hook: DecisionHook) {
+        self.decision_hook = Some(hook);
+    }
+
+    /// Remove the decision hook, if any
+    pub fn clear_decision_hook(&mut self) {
+        self.decision_hook = None;
+    }
+
+    /// Register a custom rule at `priority` in the pipeline of built-in
+    /// rules (see `builtin_rule_priority`). It runs after every built-in at
+    /// or below `priority` and before the rest; rules sharing a priority run
+    /// in registration order. Above the `PERMITTED` stage a rule sees permits
+    /// only. Rules cannot be removed once registered, and none can pre-empt
+    /// or override panic mode, the blocklist integrity check or the hard
+    /// block.
+    ///
+    /// Not exported to WASM, like the decision hook.
+    pub fn add_rule(&mut self, priority: i32, rule: Box<dyn Rule>) {
+        let at = self.rules.partition_point(|registered| registered.priority <= priority);
+        self.rules.insert(at, RegisteredRule { priority, rule });
+    }
+
+    /// Evaluate typed values, for Rust hosts that already hold them.
+    /// Same decision and side effects (grants, quotas, session state, hook)
+    /// as `validate_ai_action` on the same values serialized.
+    pub fn evaluate(
+        &mut self,
+        request: &AiRequest,
+        context: &SecurityContext,
+        timestamp_ms: u64,
+    ) -> PolicyDecision {
+        match check_request_metadata(&self.config, request) {
+            Err(error) => self.flag_degraded(error.into_decision(timestamp_ms)),
+            Ok(()) => self.evaluate_parsed(request, context, timestamp_ms),
+        }
+    }
+
+    /// `validate_ai_action` under this engine's config, reporting a refused
+    /// input as its error. Engine state advances only for evaluated inputs.
+    pub fn try_validate(
+        &mut self,
+        request_json: &str,
+        context_json: &str,
+        timestamp_ms: u64,
+    ) -> Result<PolicyDecision, PolicyError> {
+        let (request, context) = parse_inputs(&self.config, request_json, context_json)?;
+        Ok(self.evaluate_parsed(&request, &context, timestamp_ms))
+    }
+
+    /// Evaluate inputs that passed parsing limits, then notify the decision hook
+    fn evaluate_parsed(
+        &mut self,
+        request: &AiRequest,
+        context: &SecurityContext,
+        timestamp_ms: u64,
+    ) -> PolicyDecision {
+        self.evaluations += 1;
+        self.latest_timestamp_ms = self.latest_timestamp_ms.max(timestamp_ms);
+        let decision = self.evaluate_with_overlays(request, context, timestamp_ms);
+        let mut decision = self.flag_degraded(decision);
+        if self.config.redact_reasons {
+            redact_reason(&mut decision, request);
+        }
+        if self.velocity_anomaly(request, timestamp_ms) {
+            decision.audit_required = true;
+            decision.anomaly_detected = true;
+        }
+        if let Some(previous_max) = self.risk_regression(request, context) {
+            merge_audit(&mut decision, true, true);
+            decision.previous_max_risk = Some(previous_max);
+            decision.risk_delta = Some(request.risk_score - previous_max);
+        }
+        if let Some(until) = self.risk_cooldown(request, &decision, timestamp_ms) {
+            merge_audit(&mut decision, true, true);
+            decision.cooldown_until_ms = Some(until);
+        }
+        if let Some(count) = self.track_denial(request, &decision, timestamp_ms) {
+            merge_audit(&mut decision, true, false);
+            decision.repeated_denial_count = Some(count);
+        }
+        if decision.permitted {
+            if let Some(key) = self.trust_key(request, context) {
+                *self.trusted_permits.entry(key).or_insert(0) += 1;
+            }
+            let key = self.streak_key(request);
+            if self.config.action_cooldown_ms.contains_key(&key.1) {
+                self.last_permits.insert(key, timestamp_ms);
+            }
+            let action = self.config.canonical_action(&request.action).to_string();
+            self.recent_permits
+                .entry(session_key(request, context))
+                .or_default()
+                .insert(action, timestamp_ms);
+        }
+        // Overlays and flags above may have changed the rule or raised audit
+        annotate_decision(
+            &self.config,
+            &self.index,
+            request,
+            context,
+            timestamp_ms,
+            &mut decision,
+        );
+        let action = self.config.canonical_action(&request.action);
+        if decision.audit_required {
+            if self.audit_log.len() >= AUDIT_LOG_CAPACITY {
+                self.audit_log.pop_front();
+            }
+            self.audit_log.push_back(AuditEntry {
+                timestamp_ms,
+                action: action.to_string(),
+                applied_rule: decision.applied_rule.clone(),
+                organisation_id: request.organisation_id.clone(),
+                permitted: decision.permitted,
+            });
+        }
+        if let Some(hook) = &self.decision_hook {
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| hook(request, &decision)));
+        }
+        decision
+    }
+
+    /// Mark (and audit) every decision a degraded engine returns
+    fn flag_degraded(&self, mut decision: PolicyDecision) -> PolicyDecision {
+        if self.degraded.is_some() {
+            decision.degraded = true;
+            decision.audit_required = true;
+        }
+        decision
+    }
+
+    /// Pure rule cascade followed by the engine's stateful overlays
+    fn evaluate_with_overlays(
+        &mut self,
+        request: &AiRequest,
+        context: &SecurityContext,
+        timestamp_ms: u64,
+    ) -> PolicyDecision {
+        let mut decision = self.evaluate_cached(request, context, timestamp_ms);
+        let stage = if decision.permitted { None } else { denying_stage(&decision.applied_rule) };
+
+        // The pinned head is final; custom rules never see its denials
+        if stage.is_some_and(|stage| stage < PINNED_RULES) {
+            return decision;
+        }
+        // Degraded, the built-in reads are the only actions still served
+        let action = self.config.canonical_action(&request.action);
+        if self.degraded.is_some() && self.index.category(action) != ActionCategory::Read {
+            return PolicyDecision {
+                permitted: false,
+                applied_rule: "ENGINE_DEGRADED".to_string(),
+                reason: format!(
+                    "Action '{}' denied: the policy engine is degraded and serves reads only.",
+                    action
+                ),
+                iso_control: "A.17.1.1".to_string(),
+                timestamp_ms,
+                audit_required: true,
+                ..Default::default()
+            }
+            .finalize();
+        }
+        if let Some(block) = self.repeated_denial_block(request, timestamp_ms) {
+            return block;
+        }
+        // Built-in rules are pure, so running them first (or serving them
+        // from the cache) and then only the custom rules ordered ahead of
+        // the deciding stage decides exactly as the merged pipeline would.
+        // A permit leaves every custom rule its veto.
+        let reached = match stage {
+            Some(stage) => self.rules.partition_point(|r| r.priority < stage_priority(stage)),
+            None => self.rules.len(),
+        };
+        if let Some(denial) = custom_denial(&self.rules[..reached], request, context, timestamp_ms)
+        {
+            return denial;
+        }
+
+        // A default-deny on the whitelist is the only denial a grant can lift;
+        // every earlier rule (hard block, risk, lockout) still applies
+        if decision.applied_rule == "NOT_IN_WHITELIST" {
+            if let Some(grant) = self.active_grant(request, timestamp_ms) {
+                return PolicyDecision {
+                    permitted: true,
+                    applied_rule: "TEMPORARY_GRANT".to_string(),
+                    reason: format!(
+                        "Action '{}' permitted by temporary grant for user '{}' until {}.",
+                        grant.action, grant.user_id, grant.expires_at_ms
+                    ),
+                    iso_control: "A.9.2.2".to_string(),
+                    timestamp_ms,
+                    audit_required: true,
+                    ..Default::default()
+                }
+                .finalize();
+            }
+        }
+
+        if decision.permitted {
+            if let Some(missing) = self.missing_prerequisite(request, context, timestamp_ms) {
+                return missing;
+            }
+            if let Some(cold) = self.cold_session(request, context, timestamp_ms) {
+                return cold;
+            }
+            if let Some(conflict) = self.exclusive_conflict(request, context, timestamp_ms) {
+                return conflict;
+            }
+            if let Some(cooldown) = self.action_cooldown(request, timestamp_ms) {
+                return cooldown;
+            }
+        }
+
+        // Quotas only consume on a permit, so denied attempts are free
+        if decision.permitted {
+            if let Some(exceeded) = self.consume_quota(request, &mut decision, timestamp_ms) {
+                return exceeded;
+            }
+        }
+        decision
+    }
+
+    /// The pure rule cascade, served from the decision cache when enabled.
+    /// A hit is the stored decision with the current timestamp, so only
+    /// decisions that are a function of the inputs alone are cached.
+    fn evaluate_cached(
+        &mut self,
+        request: &AiRequest,
+        context: &SecurityContext,
+        timestamp_ms: u64,
+    ) -> PolicyDecision {
+        let effective_risk = self.effective_risk(request, context);
+        let evaluate = |engine: &PolicyEngine| {
+            evaluate_at_risk(
+                &engine.config,
+                &engine.index,
+                request,
+                context,
+                effective_risk,
+                timestamp_ms,
+            )
+        };
+        // Audit sampling hashes the timestamp, an approval token expires,
+        // a deprecated action retires at its sunset, and trust decay
+        // depends on history, so none of these decisions is reusable
+        // across calls
+        if self.decision_cache.capacity == 0
+            || self.config.audit_sample_percent > 0
+            || request.approval_token.is_some()
+            || self.config.sunset_ms(self.config.canonical_action(&request.action)).is_some()
+            || effective_risk != self.base_risk(request)
+        {
+            return evaluate(self);
+        }
+        let key = to_canonical_json(&(request, context));
+        if let Some(cached) = self.decision_cache.get(&key) {
+            return PolicyDecision {
+                timestamp_ms,
+                // No rule ran for a hit, so the stored stages would mislead
+                #[cfg(feature = "trace-timing")]
+                rule_timings: Vec::new(),
+                ..cached.clone()
+            };
+        }
+        let decision = evaluate(self);
+        self.decision_cache.insert(key, decision.clone());
+        decision
+    }
+
+    /// The (user_id, action) pair whose trusted permits decay this request's
+    /// risk, or `None` when decay is off or cannot apply: anonymous callers,
+    /// untrusted networks and system actions never earn trust
+    fn trust_key(
+        &self,
+        request: &AiRequest,
+        context: &SecurityContext,
+    ) -> Option<(String, String)> {
+        self.config.risk_decay_per_success?;
+        let user = request.user_id.as_ref()?;
+        let action = self.config.canonical_action(&request.action);
+        if !context.trusted_network || self.index.category(action) == ActionCategory::System {
+            return None;
+        }
+        Some((user.clone(), action.to_string()))
+    }
+
+    /// Risk compared against `max_risk_score`: the base risk lowered by
+    /// `risk_decay_per_success` per earlier trusted permit, but never below
+    /// `risk_decay_floor` (or the base risk, if that is lower)
+    fn effective_risk(&self, request: &AiRequest, context: &SecurityContext) -> u8 {
+        let base = self.base_risk(request);
+        let (Some(step), Some(key)) =
+            (self.config.risk_decay_per_success, self.trust_key(request, context))
+        else {
+            return base;
+        };
+        let successes = self.trusted_permits.get(&key).copied().unwrap_or(0);
+        let decay = u32::from(step).saturating_mul(successes).min(u32::from(u8::MAX)) as u8;
+        let floor = self.config.risk_decay_floor.min(base);
+        base.saturating_sub(decay).max(floor)
+    }
+
+    /// Composite risk when configured, else the reported score
+    fn base_risk(&self, request: &AiRequest) -> u8 {
+        self.config.composite_risk(request.risk_score, request.risk_components.as_ref())
+    }
+
+    /// Record this request against the user's interval baseline and report
+    /// whether its rate exceeds the baseline by `anomaly_rate_factor`.
+    /// A soft signal only: the caller flags the decision, never denies it.
+    fn velocity_anomaly(&mut self, request: &AiRequest, timestamp_ms: u64) -> bool {
+        let Some(factor) = self.config.anomaly_rate_factor else {
+            return false;
+        };
+        let user = request.user_id.clone().unwrap_or_default();
+        let Some(velocity) = self.velocity.get_mut(&user) else {
+            self.velocity.insert(
+                user,
+                Velocity {
+                    last_ms: timestamp_ms,
+                    ewma_interval_ms: 0.0,
+                    samples: 0,
+                },
+            );
+            return false;
+        };
+        let interval = timestamp_ms.saturating_sub(velocity.last_ms) as f64;
+        // Rate is the inverse of interval: a spike is an interval `factor` times shorter
+        let anomalous = velocity.samples >= self.config.anomaly_min_samples
+            && interval * factor < velocity.ewma_interval_ms;
+        velocity.ewma_interval_ms = if velocity.samples == 0 {
+            interval
+        } else {
+            VELOCITY_EWMA_ALPHA * interval + (1.0 - VELOCITY_EWMA_ALPHA) * velocity.ewma_interval_ms
+        };
+        velocity.samples = velocity.samples.saturating_add(1);
+        velocity.last_ms = timestamp_ms;
+        anomalous
+    }
+
+    /// Mutual-exclusion check: deny if this session was already permitted
+    /// a different member of any exclusion group containing this action
+    fn exclusive_conflict(
+        &self,
+        request: &AiRequest,
+        context: &SecurityContext,
+        timestamp_ms: u64,
+    ) -> Option<PolicyDecision> {
+        let action = self.config.canonical_action(&request.action);
+        let history = self.recent_permits.get(&session_key(request, context))?;
+        let performed = self
+            .config
+            .mutually_exclusive_actions
+            .iter()
+            .filter(|group| group.iter().any(|a| a == action))
+            .flatten()
+            .find(|other| other.as_str() != action && history.contains_key(other.as_str()))?;
+        Some(
+            PolicyDecision {
+                permitted: false,
+                applied_rule: "MUTUALLY_EXCLUSIVE_ACTION".to_string(),
+                reason: format!(
+                    "Action '{}' cannot follow '{}' in the same session.",
+                    action, performed
+                ),
+                iso_control: "A.6.1.2".to_string(),
+                timestamp_ms,
+                audit_required: true,
+                ..Default::default()
+            }
+            .finalize(),
+        )
+    }
+
+    /// Record this request's risk against its session's running maximum.
+    /// Returns the previous maximum when the new score jumps past it by
+    /// more than `risk_regression_delta`.
+    fn risk_regression(&mut self, request: &AiRequest, context: &SecurityContext) -> Option<u8> {
+        let delta = self.config.risk_regression_delta?;
+        let max = self
+            .session_max_risk
+            .entry(session_key(request, context))
+            .or_insert(request.risk_score);
+        let previous = *max;
+        *max = previous.max(request.risk_score);
+        (request.risk_score > previous.saturating_add(delta)).then_some(previous)
+    }
+
+    /// Start a cool-down on a RISK_SCORE_EXCEEDED denial; otherwise return
+    /// the cool-down's end when this is a write or system action inside one.
+    /// Requests without a user_id share a single anonymous cool-down.
+    fn risk_cooldown(
+        &mut self,
+        request: &AiRequest,
+        decision: &PolicyDecision,
+        timestamp_ms: u64,
+    ) -> Option<u64> {
+        if self.config.risk_cooldown_ms == 0 {
+            return None;
+        }
+        let user_id = request.user_id.clone().unwrap_or_default();
+        if decision.applied_rule == "RISK_SCORE_EXCEEDED" {
+            let until = timestamp_ms.saturating_add(self.config.risk_cooldown_ms);
+            self.risk_cooldowns.insert(user_id, until);
+            return None;
+        }
+        let category = self.index.category(self.config.canonical_action(&request.action));
+        if !matches!(category, ActionCategory::Write | ActionCategory::System) {
+            return None;
+        }
+        self.risk_cooldowns.get(&user_id).copied().filter(|&until| timestamp_ms < until)
+    }
+
+    /// The (user_id, action) pair a denial streak is tracked under; requests
+    /// without a user_id share one anonymous streak per action
+    fn streak_key(&self, request: &AiRequest) -> (String, String) {
+        let action = self.config.canonical_action(&request.action);
+        (request.user_id.clone().unwrap_or_default(), action.to_string())
+    }
+
+    /// REPEATED_DENIAL_BLOCK while this user and action are inside a block
+    fn repeated_denial_block(
+        &self,
+        request: &AiRequest,
+        timestamp_ms: u64,
+    ) -> Option<PolicyDecision> {
+        let (user_id, action) = self.streak_key(request);
+        let until = self
+            .denial_streaks
+            .get(&(user_id, action.clone()))?
+            .blocked_until_ms;
+        (timestamp_ms < until).then(|| {
+            PolicyDecision {
+                permitted: false,
+                applied_rule: "REPEATED_DENIAL_BLOCK".to_string(),
+                reason: format!(
+                    "Action '{}' is blocked until {} after repeated denials.",
+                    action, until
+                ),
+                iso_control: "A.9.4.3".to_string(),
+                timestamp_ms,
+                audit_required: true,
+                retry_after_ms: Some(until - timestamp_ms),
+                ..Default::default()
+            }
+            .finalize()
+        })
+    }
+
+    /// Fold the final decision into its denial streak. Returns the streak's
+    /// length once it reaches `repeated_denial_threshold`, starting a block
+    /// when one is configured. Block denials themselves are not counted.
+    fn track_denial(
+        &mut self,
+        request: &AiRequest,
+        decision: &PolicyDecision,
+        timestamp_ms: u64,
+    ) -> Option<u32> {
+        let threshold = self.config.repeated_denial_threshold?;
+        let key = self.streak_key(request);
+        if decision.permitted {
+            self.denial_streaks.remove(&key);
+            return None;
+        }
+        if decision.applied_rule == "REPEATED_DENIAL_BLOCK" {
+            return None;
+        }
+        let streak = self.denial_streaks.entry(key).or_default();
+        if timestamp_ms.saturating_sub(streak.last_ms) > self.config.repeated_denial_window_ms {
+            streak.count = 0;
+        }
+        streak.count = streak.count.saturating_add(1);
+        streak.last_ms = timestamp_ms;
+        if streak.count < threshold {
+            return None;
+        }
+        let count = streak.count;
+        if self.config.repeated_denial_block_ms > 0 {
+            streak.blocked_until_ms =
+                timestamp_ms.saturating_add(self.config.repeated_denial_block_ms);
+            streak.count = 0;
+        }
+        Some(count)
+    }
+
+    /// Prerequisite check: every action this one depends on must have been
+    /// permitted in the same session within the prerequisite window.
+    /// Only the stateful engine tracks sessions, so stateless calls skip it.
+    fn missing_prerequisite(
+        &self,
+        request: &AiRequest,
+        context: &SecurityContext,
+        timestamp_ms: u64,
+    ) -> Option<PolicyDecision> {
+        let action = self.config.canonical_action(&request.action);
+        let prerequisites = self.config.action_prerequisites.get(action)?;
+        let history = self.recent_permits.get(&session_key(request, context));
+        let unmet = prerequisites.iter().find(|prerequisite| {
+            let last = history.and_then(|h| h.get(prerequisite.as_str()));
+            !matches!(last, Some(&at) if timestamp_ms.saturating_sub(at) <= self.config.prerequisite_window_ms)
+        })?;
+        Some(
+            PolicyDecision {
+                permitted: false,
+                applied_rule: "MISSING_PREREQUISITE".to_string(),
+                reason: format!(
+                    "Action '{}' requires '{}' to have been performed in this session within the last {}ms.",
+                    action, unmet, self.config.prerequisite_window_ms
+                ),
+                iso_control: "A.12.1.1".to_string(),
+                timestamp_ms,
+                audit_required: false,
+                ..Default::default()
+            }
+            .finalize(),
+        )
+    }
+
+    /// Warm-session check: a gated action needs some earlier permit in the
+    /// same session within `warm_session_window_ms`
+    fn cold_session(
+        &self,
+        request: &AiRequest,
+        context: &SecurityContext,
+        timestamp_ms: u64,
+    ) -> Option<PolicyDecision> {
+        let window = self.config.warm_session_window_ms;
+        let action = self.config.canonical_action(&request.action);
+        if window == 0 || !self.config.warm_session_actions.iter().any(|a| a == action) {
+            return None;
+        }
+        let last_permit = self
+            .recent_permits
+            .get(&session_key(request, context))
+            .and_then(|history| history.values().max());
+        if matches!(last_permit, Some(&at) if timestamp_ms.saturating_sub(at) <= window) {
+            return None;
+        }
+        Some(
+            PolicyDecision {
+                permitted: false,
+                applied_rule: "COLD_SESSION".to_string(),
+                reason: format!(
+                    "Action '{}' needs a session with a permitted action in the last {}ms. \
+                     Perform a routine action first.",
+                    action, window
+                ),
+                iso_control: "A.9.4.2".to_string(),
+                timestamp_ms,
+                audit_required: false,
+                ..Default::default()
+            }
+            .finalize(),
+        )
+    }
+
+    /// ACTION_COOLDOWN when this user was permitted the action less than its
+    /// `action_cooldown_ms` ago. Keyed like denial streaks.
+    fn action_cooldown(&self, request: &AiRequest, timestamp_ms: u64) -> Option<PolicyDecision> {
+        let (user_id, action) = self.streak_key(request);
+        let cooldown = *self.config.action_cooldown_ms.get(&action)?;
+        let last = *self.last_permits.get(&(user_id, action.clone()))?;
+        let until = last.saturating_add(cooldown);
+        (timestamp_ms < until).then(|| {
+            PolicyDecision {
+                permitted: false,
+                applied_rule: "ACTION_COOLDOWN".to_string(),
+                reason: format!(
+                    "Action '{}' was permitted {}ms ago; it has a {}ms cooldown.",
+                    action,
+                    timestamp_ms.saturating_sub(last),
+                    cooldown
+                ),
+                iso_control: "A.12.1.3".to_string(),
+                timestamp_ms,
+                audit_required: false,
+                retry_after_ms: Some(until - timestamp_ms),
+                ..Default::default()
+            }
+            .finalize()
+        })
+    }
+
+    /// Charge one unit of the action's quota for this user. On success the
+    /// remaining budget is written into the decision; when the budget is
+    /// exhausted, returns the QUOTA_EXCEEDED denial to use instead.
+    /// Requests without a user_id share a single anonymous budget.
+    fn consume_quota(
+        &mut self,
+        request: &AiRequest,
+        decision: &mut PolicyDecision,
+        timestamp_ms: u64,
+    ) -> Option<PolicyDecision> {
+        let action = self.config.canonical_action(&request.action).to_string();
+        let allotment = *self.config.action_quotas.get(&action)?;
+        let window = timestamp_ms / self.config.quota_window_ms;
+        let user_id = request.user_id.clone().unwrap_or_default();
+        let usage = self
+            .quota_usage
+            .entry((user_id, action.clone()))
+            .or_insert(QuotaUsage { window, used: 0 });
+        if usage.window != window {
+            *usage = QuotaUsage { window, used: 0 };
+        }
+
+        if usage.used >= allotment {
+            return Some(PolicyDecision {
+                permitted: false,
+                applied_rule: "QUOTA_EXCEEDED".to_string(),
+                reason: format!(
+                    "Quota of {} '{}' actions per window exhausted.",
+                    allotment, action
+                ),
+                iso_control: "A.12.1.3".to_string(),
+                timestamp_ms,
+                audit_required: false,
+                quota_remaining: Some(0),
+                ..Default::default()
+            }
+            .finalize());
+        }
+        usage.used += 1;
+        decision.quota_remaining = Some(allotment - usage.used);
+        None
+    }
+
+    fn active_grant(&self, request: &AiRequest, timestamp_ms: u64) -> Option<&TemporaryGrant> {
+        let user_id = request.user_id.as_deref()?;
+        let action = self.config.canonical_action(&request.action);
+        self.grants.iter().find(|g| {
+            g.action == action && g.user_id == user_id && timestamp_ms < g.expires_at_ms
+        })
+    }
+}
+
+/// First denial among `rules`, in order. Permits returned by custom rules
+/// are passes, so a plug-in can never widen what the built-ins allow.
+fn custom_denial(
+    rules: &[RegisteredRule],
+    request: &AiRequest,
+    context: &SecurityContext,
+    timestamp_ms: u64,
+) -> Option<PolicyDecision> {
+    rules
+        .iter()
+        .filter_map(|registered| registered.rule.evaluate(request, context, timestamp_ms))
+        .find(|decision| !decision.permitted)
+        .map(PolicyDecision::finalize)
+}
+
+/// Forensic replay: evaluate a past request under the config that was in
+/// effect, on a fresh engine, so no live counters, grants or cache entries
+/// leak in. Returns `{ruleset_version, decision}`, or `{"error"}` when the
+/// config is refused.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn replay_decision(
+    request_json: &str,
+    context_json: &str,
+    config_json: &str,
+    timestamp_ms: u64,
+) -> String {
+    let mut engine = match PolicyEngine::from_config(config_json) {
+        Ok(engine) => engine,
+        Err(e) => return serde_json::json!({ "error": e.to_string() }).to_string(),
+    };
+    let decision = match engine.try_validate(request_json, context_json, timestamp_ms) {
+        Ok(decision) => decision,
+        Err(error) => error.into_decision(timestamp_ms),
+    };
+    serde_json::json!({
+        "ruleset_version": engine.config.ruleset_version,
+        "decision": decision,
+    })
+    .to_string()
+}
+
+/// Key for per-session state: the context's session_id, else the
+/// request's user_id, else a shared anonymous bucket
+fn session_key(request: &AiRequest, context: &SecurityContext) -> String {
+    context
+        .session_id
+        .clone()
+        .or_else(|| request.user_id.clone())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request_json(action: &str) -> String {
+        serde_json::json!({
+            "action": action,
+            "target_resource": "incident:42",
+            "risk_score": 10,
+            "requesting_module": "com.infinity-os.shell",
+            "user_id": "user-123",
+            "organisation_id": "org-456",
+            "metadata": null
+        })
+        .to_string()
+    }
+
+    const CONTEXT: &str = r#"{"user_role":"power_user","mfa_verified":true,"session_age_seconds":60,"trusted_network":true,"failed_attempts_last_hour":0,"authenticated_user_id":"user-123"}"#;
+
+    fn decide(engine: &mut PolicyEngine, action: &str, timestamp_ms: u64) -> serde_json::Value {
+        serde_json::from_str(&engine.validate_ai_action(&request_json(action), CONTEXT, timestamp_ms))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_from_config_overrides_defaults() {
+        let engine = PolicyEngine::from_config(r#"{"max_risk_score": 20}"#).unwrap();
+        assert_eq!(engine.config().max_risk_score, 20);
+        assert_eq!(engine.config().max_failed_attempts, 5);
+    }
+
+    #[test]
+    fn test_from_config_cannot_unblock() {
+        let result = PolicyEngine::from_config(r#"{"blocked_actions": []}"#);
+        assert!(matches!(result, Err(PolicyError::ConfigInvalid(_))));
+
+        let result = PolicyEngine::from_config(
+            r#"{"read_actions": ["read_public_cache", "access_vault_secrets"]}"#,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_dump_effective_policy_is_deterministic() {
+        let a = PolicyEngine::from_config(r#"{"write_actions": ["b_write", "a_write"]}"#).unwrap();
+        let b = PolicyEngine::from_config(r#"{"write_actions": ["a_write", "b_write"]}"#).unwrap();
+        assert_eq!(a.dump_effective_policy(), b.dump_effective_policy());
+
+        let dump: serde_json::Value = serde_json::from_str(&a.dump_effective_policy()).unwrap();
+        assert_eq!(dump["write_actions"], serde_json::json!(["a_write", "b_write"]));
+        assert_eq!(dump["ruleset_version"], "1.0.0");
+        assert_eq!(dump["max_risk_score"], 50);
+
+        // Every set-like list sorts, nested ones included
+        let sets = |order: [&str; 2]| {
+            let [first, second] = order;
+            PolicyEngine::from_config(
+                &serde_json::json!({
+                    "mfa_required": [first, second],
+                    "trusted_network_required": [first, second],
+                    "lockout_exempt_reads": [first, second],
+                    "actions_requiring_org": [first, second],
+                    "user_scoped_actions": [first, second],
+                    "sensitive_resource_prefixes": [first, second],
+                    "action_prerequisites": {"write_ai_suggestion": [first, second]},
+                    "mutually_exclusive_actions": [[second, first], [first, second]],
+                })
+                .to_string(),
+            )
+            .unwrap()
+            .dump_effective_policy()
+        };
+        let order = ["read_file_metadata", "read_public_cache"];
+        let sorted = sets(order);
+        assert_eq!(sorted, sets([order[1], order[0]]));
+        let dump: serde_json::Value = serde_json::from_str(&sorted).unwrap();
+        assert_eq!(dump["trusted_network_required"], serde_json::json!(order));
+        assert_eq!(dump["action_prerequisites"]["write_ai_suggestion"], serde_json::json!(order));
+        assert_eq!(dump["mutually_exclusive_actions"], serde_json::json!([order, order]));
+        // The hierarchy is a ranking, not a set
+        assert_eq!(dump["role_hierarchy"][0], "user");
+    }
+
+    #[test]
+    fn test_temporary_grant_permits_until_expiry() {
+        let mut engine = PolicyEngine::new();
+        engine.add_temporary_grant("rotate_incident_token", "user-123", 1_000).unwrap();
+
+        let decision = decide(&mut engine, "rotate_incident_token", 999);
+        assert_eq!(decision["permitted"], true);
+        assert_eq!(decision["applied_rule"], "TEMPORARY_GRANT");
+        assert_eq!(decision["audit_required"], true);
+
+        let decision = decide(&mut engine, "rotate_incident_token", 1_000);
+        assert_eq!(decision["permitted"], false);
+        assert_eq!(decision["applied_rule"], "NOT_IN_WHITELIST");
+    }
+
+    #[test]
+    fn test_escalated_permits_list_cleared_gates() {
+        let mut engine =
+            PolicyEngine::from_config(r#"{"sensitive_resource_prefixes": ["pii:"]}"#).unwrap();
+        let request = request_json("read_public_cache")
+            .replace(r#""risk_score":10"#, r#""risk_score":5"#)
+            .replace("incident:42", "pii:x");
+        let decision: serde_json::Value =
+            serde_json::from_str(&engine.validate_ai_action(&request, CONTEXT, 0)).unwrap();
+        assert_eq!(decision["audit_required"], true);
+        assert!(!decision["cleared_gates"].as_array().unwrap().is_empty());
+
+        // Overlay permits are audited, so they carry the list too
+        let mut engine = PolicyEngine::new();
+        engine.add_temporary_grant("rotate_incident_token", "user-123", 1_000).unwrap();
+        let decision = decide(&mut engine, "rotate_incident_token", 0);
+        assert_eq!(decision["applied_rule"], "TEMPORARY_GRANT");
+        assert!(!decision["cleared_gates"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_temporary_grant_is_user_bound() {
+        let mut engine = PolicyEngine::new();
+        engine.add_temporary_grant("rotate_incident_token", "user-999", 1_000).unwrap();
+        let decision = decide(&mut engine, "rotate_incident_token", 0);
+        assert_eq!(decision["applied_rule"], "NOT_IN_WHITELIST");
+    }
+
+    #[test]
+    fn test_temporary_grant_refused_for_blocked_action() {
+        let mut engine = PolicyEngine::new();
+        assert!(engine.add_temporary_grant("access_vault_secrets", "user-123", 1_000).is_err());
+        assert_eq!(engine.list_temporary_grants(), "[]");
+    }
+
+    #[test]
+    fn test_decision_hook_observes_every_decision() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&seen);
+        let mut engine = PolicyEngine::new();
+        engine.set_decision_hook(Box::new(move |request, decision| {
+            sink.borrow_mut()
+                .push((request.action.clone(), decision.applied_rule.clone()));
+        }));
+
+        decide(&mut engine, "read_public_cache", 0);
+        decide(&mut engine, "disable_mfa", 0);
+        assert_eq!(
+            *seen.borrow(),
+            vec![
+                ("read_public_cache".to_string(), "WHITELIST_APPROVED".to_string()),
+                ("disable_mfa".to_string(), "HARD_BLOCK".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_panicking_hook_does_not_change_decision() {
+        let mut engine = PolicyEngine::new();
+        engine.set_decision_hook(Box::new(|_, _| panic!("hook failure")));
+        let decision = decide(&mut engine, "read_public_cache", 0);
+        assert_eq!(decision["permitted"], true);
+        // Engine remains usable afterwards
+        let decision = decide(&mut engine, "disable_mfa", 0);
+        assert_eq!(decision["applied_rule"], "HARD_BLOCK");
+    }
+
+    #[test]
+    fn test_quota_reports_remaining_and_denies_when_exhausted() {
+        let mut engine = PolicyEngine::from_config(
+            r#"{"action_quotas": {"write_ai_suggestion": 2}}"#,
+        )
+        .unwrap();
+
+        let decision = decide(&mut engine, "write_ai_suggestion", 0);
+        assert_eq!(decision["permitted"], true);
+        assert_eq!(decision["quota_remaining"], 1);
+
+        let decision = decide(&mut engine, "write_ai_suggestion", 1);
+        assert_eq!(decision["quota_remaining"], 0);
+
+        let decision = decide(&mut engine, "write_ai_suggestion", 2);
+        assert_eq!(decision["permitted"], false);
+        assert_eq!(decision["applied_rule"], "QUOTA_EXCEEDED");
+
+        // Next day boundary resets the budget
+        let decision = decide(&mut engine, "write_ai_suggestion", 86_400_000);
+        assert_eq!(decision["permitted"], true);
+        assert_eq!(decision["quota_remaining"], 1);
+    }
+
+    #[test]
+    fn test_zero_quota_window_is_refused() {
+        let config = r#"{"action_quotas": {"write_ai_suggestion": 2}, "quota_window_ms": 0}"#;
+        assert!(PolicyEngine::from_config(config).is_err());
+    }
+
+    #[test]
+    fn test_decision_cache_hits_refresh_timestamp_and_keep_quotas() {
+        let mut engine = PolicyEngine::from_config(
+            r#"{"decision_cache_capacity": 1, "action_quotas": {"write_ai_suggestion": 1}}"#,
+        )
+        .unwrap();
+
+        let first = decide(&mut engine, "write_ai_suggestion", 10);
+        assert_eq!(first["permitted"], true);
+        // Identical inputs hit the cache, but the quota overlay still runs
+        let second = decide(&mut engine, "write_ai_suggestion", 20);
+        assert_eq!(second["applied_rule"], "QUOTA_EXCEEDED");
+        assert_eq!(second["timestamp_ms"], 20);
+
+        // Capacity 1: a different action evicts the first entry
+        decide(&mut engine, "read_public_cache", 30);
+        decide(&mut engine, "write_ai_suggestion", 40);
+
+        let metrics: serde_json::Value =
+            serde_json::from_str(&engine.metrics_snapshot()).unwrap();
+        assert_eq!(metrics["evaluations"], 4);
+        assert_eq!(metrics["decision_cache"]["hits"], 1);
+        assert_eq!(metrics["decision_cache"]["misses"], 3);
+        assert_eq!(metrics["decision_cache"]["entries"], 1);
+    }
+
+    #[test]
+    fn test_anomaly_rate_factor_must_exceed_one() {
+        for factor in ["0.0", "-2.0", "1.0"] {
+            let config = format!(r#"{{"anomaly_rate_factor": {}}}"#, factor);
+            assert!(PolicyEngine::from_config(&config).is_err(), "{}", factor);
+        }
+        let config = PolicyConfig { anomaly_rate_factor: Some(f64::NAN), ..PolicyConfig::default() };
+        assert!(config.validate().is_err());
+        assert!(PolicyEngine::from_config(r#"{"anomaly_rate_factor": 1.5}"#).is_ok());
+    }
+
+    #[test]
+    fn test_velocity_spike_flags_without_denying() {
+        let mut engine = PolicyEngine::from_config(
+            r#"{"anomaly_rate_factor": 5.0, "anomaly_min_samples": 3}"#,
+        )
+        .unwrap();
+        // Steady baseline: one request a minute
+        for minute in 0..5 {
+            let decision = decide(&mut engine, "read_public_cache", minute * 60_000);
+            assert!(decision.get("anomaly_detected").is_none());
+            assert_eq!(decision["audit_required"], false);
+        }
+        // A request one second later is a 60x spike
+        let decision = decide(&mut engine, "read_public_cache", 4 * 60_000 + 1_000);
+        assert_eq!(decision["permitted"], true);
+        assert_eq!(decision["anomaly_detected"], true);
+        assert_eq!(decision["audit_required"], true);
+    }
+
+    #[test]
+    fn test_maintenance_freeze_blocks_non_reads() {
+        let mut engine = PolicyEngine::from_config(r#"{"decision_cache_capacity": 8}"#).unwrap();
+        assert_eq!(decide(&mut engine, "write_notification", 0)["permitted"], true);
+        engine.add_temporary_grant("export_report", "user-123", 1_000).unwrap();
+
+        engine.set_freeze(true);
+        assert!(engine.dump_effective_policy().contains(r#""freeze_writes":true"#));
+        for action in ["write_notification", "clear_expired_cache", "export_report"] {
+            let decision = decide(&mut engine, action, 1);
+            assert_eq!(decision["applied_rule"], "MAINTENANCE_FREEZE", "{}", action);
+        }
+        assert_eq!(decide(&mut engine, "read_public_cache", 1)["permitted"], true);
+        assert_eq!(decide(&mut engine, "disable_mfa", 1)["applied_rule"], "HARD_BLOCK");
+
+        engine.set_freeze(false);
+        assert_eq!(decide(&mut engine, "write_notification", 2)["permitted"], true);
+    }
+
+    #[test]
+    fn test_mutually_exclusive_actions_per_session() {
+        let mut engine = PolicyEngine::from_config(
+            r#"{"mutually_exclusive_actions": [["write_user_preferences", "adjust_ui_theme"]]}"#,
+        )
+        .unwrap();
+        assert_eq!(decide(&mut engine, "write_user_preferences", 0)["permitted"], true);
+        // Repeating the same member is fine
+        assert_eq!(decide(&mut engine, "write_user_preferences", 1)["permitted"], true);
+
+        let decision = decide(&mut engine, "adjust_ui_theme", 2);
+        assert_eq!(decision["applied_rule"], "MUTUALLY_EXCLUSIVE_ACTION");
+        assert_eq!(decision["audit_required"], true);
+
+        // Another session is unaffected
+        let context = CONTEXT.replace('}', r#","session_id":"other"}"#);
+        let decision: serde_json::Value = serde_json::from_str(&engine.validate_ai_action(
+            &request_json("adjust_ui_theme"),
+            &context,
+            3,
+        ))
+        .unwrap();
+        assert_eq!(decision["permitted"], true);
+    }
+
+    #[test]
+    fn test_risk_regression_requires_review() {
+        let mut engine = PolicyEngine::from_config(r#"{"risk_regression_delta": 15}"#).unwrap();
+        let decide_risk = |engine: &mut PolicyEngine, risk: u8| -> serde_json::Value {
+            let request = request_json("read_public_cache")
+                .replace(r#""risk_score":10"#, &format!(r#""risk_score":{}"#, risk));
+            serde_json::from_str(&engine.validate_ai_action(&request, CONTEXT, 0)).unwrap()
+        };
+        assert!(decide_risk(&mut engine, 5).get("requires_human_review").is_none());
+        // Within the delta of the running max
+        assert!(decide_risk(&mut engine, 20).get("requires_human_review").is_none());
+
+        let decision = decide_risk(&mut engine, 40);
+        assert_eq!(decision["permitted"], true);
+        assert_eq!(decision["requires_human_review"], true);
+        assert_eq!(decision["audit_required"], true);
+        assert_eq!(decision["previous_max_risk"], 20);
+        assert_eq!(decision["risk_delta"], 20);
+    }
+
+    #[test]
+    fn test_risk_cooldown_holds_writes_for_review() {
+        let mut engine = PolicyEngine::from_config(r#"{"risk_cooldown_ms": 1000}"#).unwrap();
+        let risky =
+            request_json("read_public_cache").replace(r#""risk_score":10"#, r#""risk_score":90"#);
+        let denial: serde_json::Value =
+            serde_json::from_str(&engine.validate_ai_action(&risky, CONTEXT, 0)).unwrap();
+        assert_eq!(denial["applied_rule"], "RISK_SCORE_EXCEEDED");
+        assert!(denial.get("cooldown_until_ms").is_none());
+
+        let write = decide(&mut engine, "write_ai_suggestion", 500);
+        assert_eq!(write["permitted"], true);
+        assert_eq!(write["requires_human_review"], true);
+        assert_eq!(write["cooldown_until_ms"], 1000);
+        let unreviewed = |engine: &mut PolicyEngine, action: &str, timestamp_ms: u64| {
+            decide(engine, action, timestamp_ms).get("requires_human_review").is_none()
+        };
+        // Reads are untouched, and the hold lapses with the window
+        assert!(unreviewed(&mut engine, "read_public_cache", 500));
+        assert!(unreviewed(&mut engine, "write_ai_suggestion", 1000));
+
+        // Off by default
+        let mut engine = PolicyEngine::new();
+        engine.validate_ai_action(&risky, CONTEXT, 0);
+        assert!(unreviewed(&mut engine, "write_ai_suggestion", 500));
+    }
+
+    #[test]
+    fn test_risk_decay_for_trusted_repeats() {
+        let mut engine = PolicyEngine::from_config(
+            r#"{"risk_decay_per_success": 10, "risk_decay_floor": 20}"#,
+        )
+        .unwrap();
+        let decide_risk = |engine: &mut PolicyEngine, action: &str, risk: u8| -> serde_json::Value {
+            let request = request_json(action)
+                .replace(r#""risk_score":10"#, &format!(r#""risk_score":{}"#, risk));
+            serde_json::from_str(&engine.validate_ai_action(&request, CONTEXT, 0)).unwrap()
+        };
+        let decision = decide_risk(&mut engine, "read_public_cache", 70);
+        assert_eq!(decision["applied_rule"], "RISK_SCORE_EXCEEDED");
+        for _ in 0..3 {
+            let decision = decide_risk(&mut engine, "read_public_cache", 10);
+            assert!(decision.get("effective_risk_score").is_none());
+        }
+        // Three trusted permits take 70 down to 40, under the default max of 50
+        let decision = decide_risk(&mut engine, "read_public_cache", 70);
+        assert_eq!(decision["permitted"], true);
+        assert_eq!(decision["effective_risk_score"], 40);
+        assert!(decision["reason"].as_str().unwrap().contains("Risk score: 70/50"));
+
+        // However many permits accrue, the floor holds
+        for _ in 0..10 {
+            decide_risk(&mut engine, "read_public_cache", 10);
+        }
+        assert_eq!(decide_risk(&mut engine, "read_public_cache", 70)["effective_risk_score"], 20);
+
+        // System actions never decay
+        for _ in 0..5 {
+            assert_eq!(decide_risk(&mut engine, "clear_expired_cache", 10)["permitted"], true);
+        }
+        let decision = decide_risk(&mut engine, "clear_expired_cache", 70);
+        assert_eq!(decision["applied_rule"], "RISK_SCORE_EXCEEDED");
+        assert!(decision.get("effective_risk_score").is_none());
+    }
+
+    #[test]
+    fn test_risk_decay_needs_trusted_network() {
+        let mut engine = PolicyEngine::from_config(r#"{"risk_decay_per_success": 10}"#).unwrap();
+        let untrusted = CONTEXT.replace(r#""trusted_network":true"#, r#""trusted_network":false"#);
+        for _ in 0..5 {
+            engine.validate_ai_action(&request_json("read_public_cache"), &untrusted, 0);
+        }
+        let request = request_json("read_public_cache")
+            .replace(r#""risk_score":10"#, r#""risk_score":60"#);
+        let decision: serde_json::Value =
+            serde_json::from_str(&engine.validate_ai_action(&request, CONTEXT, 0)).unwrap();
+        assert_eq!(decision["applied_rule"], "RISK_SCORE_EXCEEDED");
+    }
+
+    fn deny_as(rule: &'static str) -> Box<dyn Rule> {
+        Box::new(move |request: &AiRequest, _: &SecurityContext, timestamp_ms: u64| {
+            Some(PolicyDecision {
+                permitted: false,
+                applied_rule: rule.to_string(),
+                reason: format!("Custom rule {} denied '{}'.", rule, request.action),
+                iso_control: "A.5.1".to_string(),
+                timestamp_ms,
+                audit_required: true,
+                ..Default::default()
+            })
+        })
+    }
+
+    #[test]
+    fn test_custom_rules_run_in_priority_order() {
+        let last = builtin_rule_priority("PERMITTED").unwrap();
+        let mut engine = PolicyEngine::new();
+        engine.add_rule(last, deny_as("ORG_AFTER"));
+        engine.add_rule(-5, deny_as("ORG_BEFORE"));
+        engine.add_rule(-5, deny_as("ORG_BEFORE_SECOND"));
+        // Below every unpinned built-in, a rule wins over their denials too
+        assert_eq!(decide(&mut engine, "launch_rockets", 0)["applied_rule"], "ORG_BEFORE");
+        assert_eq!(decide(&mut engine, "read_public_cache", 0)["applied_rule"], "ORG_BEFORE");
+
+        let mut engine = PolicyEngine::new();
+        engine.add_rule(last, deny_as("ORG_AFTER"));
+        // Past every built-in, a rule only sees permits
+        assert_eq!(decide(&mut engine, "read_public_cache", 0)["applied_rule"], "ORG_AFTER");
+        assert_eq!(decide(&mut engine, "launch_rockets", 0)["applied_rule"], "NOT_IN_WHITELIST");
+    }
+
+    #[test]
+    fn test_custom_rules_interleave_with_builtins() {
+        let risk = builtin_rule_priority("RULE 2").unwrap();
+        assert!(risk < builtin_rule_priority("RULE 7").unwrap());
+        for stage in ["PANIC MODE", "RULE 0", "RULE 1", "RULE 99"] {
+            assert_eq!(builtin_rule_priority(stage), None, "{}", stage);
+        }
+
+        // Risk 10 over a ceiling of 5: RULE 2 denies
+        let config = r#"{"max_risk_score": 5}"#;
+        let mut engine = PolicyEngine::from_config(config).unwrap();
+        engine.add_rule(risk - 1, deny_as("ORG_BEFORE_RISK"));
+        assert_eq!(decide(&mut engine, "read_public_cache", 0)["applied_rule"], "ORG_BEFORE_RISK");
+
+        let mut engine = PolicyEngine::from_config(config).unwrap();
+        engine.add_rule(risk, deny_as("ORG_AFTER_RISK"));
+        let decision = decide(&mut engine, "read_public_cache", 0);
+        assert_eq!(decision["applied_rule"], "RISK_SCORE_EXCEEDED");
+        // ...but ahead of the whitelist it still outranks a later denial
+        let mut engine = PolicyEngine::new();
+        engine.add_rule(risk, deny_as("ORG_AFTER_RISK"));
+        assert_eq!(decide(&mut engine, "launch_rockets", 0)["applied_rule"], "ORG_AFTER_RISK");
+    }
+
+    #[test]
+    fn test_custom_rules_cannot_preempt_hard_block_or_permit() {
+        let mut engine = PolicyEngine::new();
+        engine.add_rule(i32::MIN, deny_as("ORG_FIRST"));
+        assert_eq!(decide(&mut engine, "delete_user_data", 0)["applied_rule"], "HARD_BLOCK");
+
+        let mut engine = PolicyEngine::new();
+        engine.add_rule(
+            -1,
+            Box::new(|_: &AiRequest, _: &SecurityContext, _: u64| {
+                Some(PolicyDecision {
+                    permitted: true,
+                    applied_rule: "ORG_ALLOW".to_string(),
+                    ..Default::default()
+                })
+            }),
+        );
+        assert_eq!(decide(&mut engine, "launch_rockets", 0)["applied_rule"], "NOT_IN_WHITELIST");
+        let decision = decide(&mut engine, "read_public_cache", 0);
+        assert_eq!(decision["applied_rule"], "WHITELIST_APPROVED");
+    }
+
+    // Compares whole decisions, and timings differ run to run
+    #[cfg(not(feature = "trace-timing"))]
+    #[test]
+    fn test_typed_evaluate_matches_json() {
+        let config = r#"{"action_quotas": {"write_ai_suggestion": 1}}"#;
+        let mut typed = PolicyEngine::from_config(config).unwrap();
+        let mut json = PolicyEngine::from_config(config).unwrap();
+        let request: AiRequest =
+            serde_json::from_str(&request_json("write_ai_suggestion")).unwrap();
+        let context: SecurityContext = serde_json::from_str(CONTEXT).unwrap();
+        // Twice, so the quota state advances identically on both engines
+        for ts in [0, 1] {
+            let from_typed = serde_json::to_value(typed.evaluate(&request, &context, ts)).unwrap();
+            assert_eq!(from_typed, decide(&mut json, "write_ai_suggestion", ts));
+        }
+    }
+
+    #[test]
+    fn test_set_panic_mode_is_instant_and_reversible() {
+        let mut engine = PolicyEngine::from_config(r#"{"decision_cache_capacity": 8}"#).unwrap();
+        engine.add_temporary_grant("launch_rockets", "user-123", u64::MAX).unwrap();
+        assert_eq!(decide(&mut engine, "read_public_cache", 0)["permitted"], true);
+
+        engine.set_panic_mode(true);
+        engine.set_panic_mode(true);
+        for action in ["read_public_cache", "launch_rockets", "delete_user_data"] {
+            assert_eq!(decide(&mut engine, action, 1)["applied_rule"], "PANIC_MODE");
+        }
+        let policy: serde_json::Value =
+            serde_json::from_str(&engine.dump_effective_policy()).unwrap();
+        assert_eq!(policy["panic_mode"], true);
+
+        engine.set_panic_mode(false);
+        assert_eq!(decide(&mut engine, "read_public_cache", 2)["permitted"], true);
+        assert_eq!(decide(&mut engine, "launch_rockets", 2)["applied_rule"], "TEMPORARY_GRANT");
+    }
+
+    #[test]
+    fn test_ruleset_graph_includes_custom_rules_and_overlays() {
+        let mut engine = PolicyEngine::new();
+        engine.add_rule(-5, deny_as("ORG_BEFORE"));
+        engine.add_rule(builtin_rule_priority("RULE 5").unwrap() - 1, deny_as("ORG_MFA"));
+        engine.add_rule(builtin_rule_priority("PERMITTED").unwrap(), deny_as("ORG_AFTER"));
+        let graph: serde_json::Value = serde_json::from_str(&engine.ruleset_graph()).unwrap();
+        let nodes = graph["nodes"].as_array().unwrap();
+        let stages: Vec<&str> = nodes.iter().map(|n| n["stage"].as_str().unwrap()).collect();
+        let at = |stage: &str| stages.iter().position(|s| *s == stage).unwrap();
+        assert_eq!(at("DEGRADED"), at("RULE 1") + 1);
+        assert_eq!(at("REPEATED DENIAL"), at("DEGRADED") + 1);
+        assert_eq!(at("CUSTOM RULE (priority -5)"), at("REPEATED DENIAL") + 1);
+        let custom = |priority: i32| format!("CUSTOM RULE (priority {})", priority);
+        let mfa = builtin_rule_priority("RULE 5").unwrap() - 1;
+        assert_eq!(at(&custom(mfa)) + 1, at("RULE 5"));
+        let last = builtin_rule_priority("PERMITTED").unwrap();
+        assert_eq!(at(&custom(last)), at("TEMPORARY GRANT") + 1);
+        assert_eq!(stages.last(), Some(&"QUOTA"));
+
+        // Every rule a decision can carry appears, bar the combinator and
+        // serialization errors, which arise outside the cascade
+        let rules: Vec<&str> = nodes
+            .iter()
+            .flat_map(|n| n["rules"].as_array().unwrap())
+            .filter_map(|r| r.as_str())
+            .collect();
+        let outside = ["COMBINE_ERROR", "ESCALATE_ERROR", "SERIALIZE_ERROR"];
+        for rule in crate::APPLIED_RULES {
+            assert!(
+                rules.contains(rule) || outside.contains(rule),
+                "{}",
+                rule
+            );
+        }
+    }
+
+    #[test]
+    fn test_replay_ignores_live_engine_state() {
+        let config = r#"{"ruleset_version": "2026.03", "action_quotas": {"read_public_cache": 1}}"#;
+        let mut live = PolicyEngine::from_config(config).unwrap();
+        decide(&mut live, "read_public_cache", 0);
+        assert_eq!(decide(&mut live, "read_public_cache", 0)["applied_rule"], "QUOTA_EXCEEDED");
+
+        let request = request_json("read_public_cache");
+        let replayed: serde_json::Value =
+            serde_json::from_str(&replay_decision(&request, CONTEXT, config, 0)).unwrap();
+        assert_eq!(replayed["ruleset_version"], "2026.03");
+        assert_eq!(replayed["decision"]["applied_rule"], "WHITELIST_APPROVED");
+        assert_eq!(replayed["decision"]["quota_remaining"], 0);
+        // Timings differ run to run; everything else is reproducible
+        #[cfg(not(feature = "trace-timing"))]
+        assert_eq!(
+            replay_decision(&request, CONTEXT, config, 0),
+            replay_decision(&request, CONTEXT, config, 0)
+        );
+
+        let weakening = r#"{"blocked_actions": []}"#;
+        let refused: serde_json::Value =
+            serde_json::from_str(&replay_decision(&request, CONTEXT, weakening, 0)).unwrap();
+        assert!(refused["error"].is_string());
+    }
+
+    #[test]
+    fn test_redact_reasons_covers_engine_overlays() {
+        let mut engine = PolicyEngine::from_config(r#"{"redact_reasons": true}"#).unwrap();
+        engine.add_temporary_grant("launch_rockets", "user-123", 1000).unwrap();
+        let decision = decide(&mut engine, "launch_rockets", 0);
+        assert_eq!(decision["applied_rule"], "TEMPORARY_GRANT");
+        let reason = decision["reason"].as_str().unwrap();
+        assert!(!reason.contains("user-123") && reason.contains("[redacted]"));
+    }
+
+    #[test]
+    fn test_refused_config_degrades_to_builtin_reads() {
+        let mut engine = PolicyEngine::from_config_or_degraded(r#"{"blocked_actions": []}"#);
+        let status: serde_json::Value = serde_json::from_str(&engine.engine_status()).unwrap();
+        assert_eq!(status["degraded"], true);
+        assert!(status["reason"].is_string());
+
+        let read = decide(&mut engine, "read_public_cache", 0);
+        assert_eq!(read["applied_rule"], "WHITELIST_APPROVED");
+        assert_eq!(read["degraded"], true);
+        assert_eq!(read["audit_required"], true);
+
+        let write = decide(&mut engine, "write_ai_suggestion", 0);
+        assert_eq!(write["applied_rule"], "ENGINE_DEGRADED");
+        assert_eq!(write["iso_control"], "A.17.1.1");
+        assert_eq!(write["degraded"], true);
+        // The hard block keeps its precedence
+        assert_eq!(decide(&mut engine, "delete_user_data", 0)["applied_rule"], "HARD_BLOCK");
+        let unparseable = engine.validate_ai_action("{", CONTEXT, 0);
+        assert!(unparseable.contains(r#""degraded":true"#));
+
+        // A good config loads normally
+        let mut engine = PolicyEngine::from_config_or_degraded(r#"{"max_risk_score": 20}"#);
+        let status: serde_json::Value = serde_json::from_str(&engine.engine_status()).unwrap();
+        assert_eq!(status["degraded"], false);
+        assert!(decide(&mut engine, "write_ai_suggestion", 0).get("degraded").is_none());
+    }
+
+    #[test]
+    fn test_retention_follows_audit_raised_by_overlays() {
+        let mut engine = PolicyEngine::from_config(
+            r#"{"risk_cooldown_ms": 1000, "audit_retention_days": {"write_ai_suggestion": 90}}"#,
+        )
+        .unwrap();
+        let write = decide(&mut engine, "write_ai_suggestion", 0);
+        assert!(write.get("audit_retention_days").is_none());
+        let risky =
+            request_json("read_public_cache").replace(r#""risk_score":10"#, r#""risk_score":90"#);
+        engine.validate_ai_action(&risky, CONTEXT, 0);
+        // The cool-down audits the write, so its retention hint now applies
+        assert_eq!(decide(&mut engine, "write_ai_suggestion", 1)["audit_retention_days"], 90);
+    }
+
+    #[test]
+    fn test_repeated_denials_escalate_then_block() {
+        let mut engine = PolicyEngine::from_config(
+            r#"{"repeated_denial_threshold": 3, "repeated_denial_block_ms": 1000}"#,
+        )
+        .unwrap();
+        let stale =
+            CONTEXT.replace(r#""session_age_seconds":60"#, r#""session_age_seconds":99999"#);
+        let deny = |engine: &mut PolicyEngine, timestamp_ms| -> serde_json::Value {
+            let request = request_json("write_ai_suggestion");
+            serde_json::from_str(&engine.validate_ai_action(&request, &stale, timestamp_ms))
+                .unwrap()
+        };
+
+        // A permit or a gap past the window ends the streak
+        deny(&mut engine, 0);
+        assert_eq!(decide(&mut engine, "write_ai_suggestion", 1)["permitted"], true);
+        deny(&mut engine, 2);
+        deny(&mut engine, 700_000);
+        let second = deny(&mut engine, 700_001);
+        assert_eq!(second["applied_rule"], "SESSION_EXPIRED");
+        assert_eq!(second["audit_required"], false);
+        assert!(second.get("repeated_denial_count").is_none());
+
+        // The third in a row escalates, then the pair is blocked, fresh session or not
+        let third = deny(&mut engine, 700_002);
+        assert_eq!(third["applied_rule"], "SESSION_EXPIRED");
+        assert_eq!(third["audit_required"], true);
+        assert_eq!(third["repeated_denial_count"], 3);
+        let blocked = decide(&mut engine, "write_ai_suggestion", 700_500);
+        assert_eq!(blocked["applied_rule"], "REPEATED_DENIAL_BLOCK");
+        assert_eq!(blocked["retry_after_ms"], 502);
+        assert_eq!(decide(&mut engine, "read_public_cache", 700_500)["permitted"], true);
+        assert_eq!(decide(&mut engine, "write_ai_suggestion", 701_002)["permitted"], true);
+    }
+
+    #[test]
+    fn test_cold_session_needs_a_recent_permit() {
+        let mut engine = PolicyEngine::from_config(
+            r#"{"warm_session_actions": ["write_ai_suggestion"], "warm_session_window_ms": 1000}"#,
+        )
+        .unwrap();
+        let cold = decide(&mut engine, "write_ai_suggestion", 0);
+        assert_eq!(cold["applied_rule"], "COLD_SESSION");
+        assert_eq!(decide(&mut engine, "write_analytics_event", 0)["permitted"], true);
+
+        // A read warms the session for one window
+        assert_eq!(decide(&mut engine, "read_public_cache", 5000)["permitted"], true);
+        assert_eq!(decide(&mut engine, "write_ai_suggestion", 6000)["permitted"], true);
+        let lapsed = decide(&mut engine, "write_ai_suggestion", 7001);
+        assert_eq!(lapsed["applied_rule"], "COLD_SESSION");
+
+        // Off by default, and reads cannot be gated
+        assert_eq!(decide(&mut PolicyEngine::new(), "write_ai_suggestion", 0)["permitted"], true);
+        let gated_read = r#"{"warm_session_actions": ["read_public_cache"]}"#;
+        assert!(PolicyEngine::from_config(gated_read).is_err());
+    }
+
+    #[test]
+    fn test_action_cooldown_debounces_permits() {
+        let mut engine =
+            PolicyEngine::from_config(r#"{"action_cooldown_ms": {"write_notification": 60000}}"#)
+                .unwrap();
+        assert_eq!(decide(&mut engine, "write_notification", 0)["permitted"], true);
+        let repeat = decide(&mut engine, "write_notification", 45_000);
+        assert_eq!(repeat["applied_rule"], "ACTION_COOLDOWN");
+        assert_eq!(repeat["retry_after_ms"], 15_000);
+
+        // Denials don't restart it; other actions and users are unaffected
+        assert_eq!(decide(&mut engine, "write_notification", 60_000)["permitted"], true);
+        assert_eq!(decide(&mut engine, "read_public_cache", 60_001)["permitted"], true);
+        assert_eq!(decide(&mut engine, "read_public_cache", 60_002)["permitted"], true);
+        let other_user = request_json("write_notification").replace("user-123", "user-999");
+        let context = CONTEXT.replace("user-123", "user-999");
+        let decision = engine.validate_ai_action(&other_user, &context, 60_003);
+        assert!(decision.contains("\"permitted\":true"));
+
+        assert!(PolicyEngine::from_config(r#"{"action_cooldown_ms": {"x": 0}}"#).is_err());
+    }
+
+    #[test]
+    fn test_audit_records_form_a_verifiable_chain() {
+        let mut engine = PolicyEngine::new();
+        let mut records = Vec::new();
+        for (i, action) in ["delete_user_data", "read_public_cache", "modify_audit_logs"]
+            .into_iter()
+            .cycle()
+            .take(5)
+            .enumerate()
+        {
+            let raw = engine.validate_and_audit(&request_json(action), CONTEXT, i as u64);
+            let combined: serde_json::Value = serde_json::from_str(&raw).unwrap();
+            if !combined["audit_record"].is_null() {
+                records.push(combined["audit_record"].clone());
+            }
+        }
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0]["prev_hash"], crate::AUDIT_CHAIN_GENESIS);
+        assert_eq!(records[1]["prev_hash"], records[0]["this_hash"]);
+        let verify = |records: &[serde_json::Value]| -> serde_json::Value {
+            let json = serde_json::to_string(records).unwrap();
+            serde_json::from_str(&crate::verify_audit_chain(&json)).unwrap()
+        };
+        let report = verify(&records);
+        assert_eq!(report["valid"], true);
+        assert_eq!(report["records"], 3);
+
+        // Deletion, reordering and edits each break the chain where they occur
+        let mut deleted = records.clone();
+        deleted.remove(1);
+        assert_eq!(verify(&deleted)["first_break"]["index"], 1);
+        let mut reordered = records.clone();
+        reordered.swap(1, 2);
+        assert_eq!(verify(&reordered)["first_break"]["index"], 1);
+        let mut edited = records.clone();
+        edited[2]["permitted"] = serde_json::json!(true);
+        let report = verify(&edited);
+        assert_eq!(report["valid"], false);
+        assert_eq!(report["first_break"]["index"], 2);
+        assert_eq!(verify(&records[1..])["first_break"]["index"], 0);
+        assert!(crate::verify_audit_chain("{").contains("error"));
+    }
+
+    #[test]
+    fn test_preview_with_role_leaves_state_alone() {
+        let mut engine = PolicyEngine::from_config(
+            r#"{"action_cooldown_ms": {"clear_expired_cache": 60000}}"#,
+        )
+        .unwrap();
+        let context = CONTEXT.replace("power_user", "user");
+        let request = request_json("clear_expired_cache");
+        let metrics = engine.metrics_snapshot();
+        for _ in 0..2 {
+            let preview = engine.preview_with_role(&request, &context, "power_user", 0);
+            assert!(preview.contains("\"permitted\":true"));
+        }
+        assert_eq!(engine.metrics_snapshot(), metrics);
+
+        // No cooldown was started by the previews
+        let real = engine.validate_ai_action(&request, CONTEXT, 1);
+        assert!(real.contains("\"permitted\":true"));
+    }
+
+    #[test]
+    fn test_encrypted_config_round_trip() {
+        use chacha20poly1305::aead::{Aead, KeyInit};
+        use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+
+        let key = [7u8; 32];
+        let nonce = [1u8; CONFIG_NONCE_LEN];
+        let seal = |config: &str| {
+            let cipher = ChaCha20Poly1305::new_from_slice(&key).unwrap();
+            let sealed = cipher.encrypt(Nonce::from_slice(&nonce), config.as_bytes()).unwrap();
+            [nonce.as_slice(), &sealed].concat()
+        };
+        let blob = seal(r#"{"max_risk_score": 42}"#);
+        let engine = PolicyEngine::from_encrypted_config(&blob, &key).unwrap();
+        assert_eq!(engine.config().max_risk_score, 42);
+
+        // Wrong keys, tampering and truncation all refuse to start
+        let error = PolicyEngine::from_encrypted_config(&blob, &[8u8; 32]).err().unwrap();
+        assert_eq!(error.applied_rule(), "ENGINE_DEGRADED");
+        assert!(PolicyEngine::from_encrypted_config(&blob, &key[..16]).is_err());
+        let mut tampered = blob.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(PolicyEngine::from_encrypted_config(&tampered, &key).is_err());
+        assert!(PolicyEngine::from_encrypted_config(&blob[..4], &key).is_err());
+        // A decrypted config still passes validation like any other
+        let weakening = seal(r#"{"blocked_actions": []}"#);
+        assert!(PolicyEngine::from_encrypted_config(&weakening, &key).is_err());
+    }
+
+    #[test]
+    fn test_try_validate_returns_errors_for_refused_inputs() {
+        let mut engine = PolicyEngine::new();
+        let request = request_json("read_public_cache");
+        let error = engine.try_validate(&request, "not json", 0).unwrap_err();
+        assert!(matches!(error, PolicyError::ParseContext { .. }));
+        let result = engine.validate_ai_action_v(&request, "not json", 0, DECISION_SCHEMA_VERSION);
+        let decision: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(decision["applied_rule"], "CONTEXT_PARSE_ERROR");
+        assert_eq!(decision["reason"], error.to_string());
+
+        let decision = engine.try_validate(&request, CONTEXT, 0).unwrap();
+        assert!(decision.permitted);
+    }
+
+    #[test]
+    fn test_decision_cache_never_serves_an_expired_approval() {
+        let mut config = PolicyConfig { decision_cache_capacity: 8, ..PolicyConfig::default() };
+        config.approval_tokens.insert(
+            crate::config::sha256_hex(b"token-1"),
+            crate::ApprovalToken {
+                action: "write_ai_suggestion".to_string(),
+                user_id: "user-123".to_string(),
+                expires_at_ms: 5_000,
+            },
+        );
+        let mut engine = PolicyEngine::with_config(config);
+        let mut request: serde_json::Value =
+            serde_json::from_str(&request_json("write_ai_suggestion")).unwrap();
+        request["risk_score"] = serde_json::json!(90);
+        request["approval_token"] = serde_json::json!("token-1");
+        let mut decide_at = |timestamp_ms| -> serde_json::Value {
+            let result = engine.validate_ai_action(&request.to_string(), CONTEXT, timestamp_ms);
+            serde_json::from_str(&result).unwrap()
+        };
+
+        assert_eq!(decide_at(1_000)["applied_rule"], "PRE_APPROVED");
+        assert_eq!(decide_at(2_000)["applied_rule"], "PRE_APPROVED");
+        let expired = decide_at(6_000);
+        assert_eq!(expired["permitted"], false);
+        assert_eq!(expired["applied_rule"], "RISK_SCORE_EXCEEDED");
+    }
+
+    #[test]
+    fn test_decision_cache_never_serves_a_retired_action() {
+        let mut config = PolicyConfig { decision_cache_capacity: 16, ..PolicyConfig::default() };
+        config.deprecated_actions.insert("write_user_preferences".to_string(), 5_000);
+        let mut engine = PolicyEngine::with_config(config);
+        let request = request_json("write_user_preferences");
+        let mut decide_at = |timestamp_ms| -> serde_json::Value {
+            let result = engine.validate_ai_action(&request, CONTEXT, timestamp_ms);
+            serde_json::from_str(&result).unwrap()
+        };
+
+        assert_eq!(decide_at(1_000)["applied_rule"], "WHITELIST_APPROVED");
+        assert_eq!(decide_at(2_000)["applied_rule"], "WHITELIST_APPROVED");
+        let retired = decide_at(6_000);
+        assert_eq!(retired["permitted"], false);
+        assert_eq!(retired["applied_rule"], "NOT_IN_WHITELIST");
+    }
+
+    #[test]
+    fn test_audit_summary_rolls_up_the_window() {
+        let mut engine = PolicyEngine::new();
+        decide(&mut engine, "delete_user_data", 0);
+        decide(&mut engine, "delete_user_data", 5000);
+        decide(&mut engine, "modify_audit_logs", 6000);
+        decide(&mut engine, "format_disk", 6000);
+        // Unaudited permits never enter the rollup
+        decide(&mut engine, "read_public_cache", 7000);
+        let other_org = request_json("delete_user_data").replace("org-456", "org-789");
+        engine.validate_ai_action(&other_org, CONTEXT, 7000);
+
+        let summary: serde_json::Value =
+            serde_json::from_str(&engine.audit_summary(2000)).unwrap();
+        assert_eq!(summary["from_ms"], 5000);
+        assert_eq!(summary["to_ms"], 7000);
+        assert_eq!(summary["by_rule"]["HARD_BLOCK"], 3);
+        assert_eq!(summary["by_action"]["delete_user_data"], 2);
+        assert_eq!(summary["by_org"], serde_json::json!({ "org-456": 3, "org-789": 1 }));
+        assert_eq!(summary["audited"], 4);
+        assert_eq!(summary["top_denied"][0], serde_json::json!({
+            "action": "delete_user_data",
+            "count": 2,
+        }));
+        assert_eq!(summary["truncated"], false);
+
+        let everything: serde_json::Value =
+            serde_json::from_str(&engine.audit_summary(u64::MAX)).unwrap();
+        assert_eq!(everything["from_ms"], 0);
+        assert_eq!(everything["by_action"]["delete_user_data"], 3);
+    }
+
+    #[test]
+    fn test_unquotaed_actions_report_no_budget() {
+        let mut engine = PolicyEngine::from_config(
+            r#"{"action_quotas": {"write_ai_suggestion": 2}}"#,
+        )
+        .unwrap();
+        let decision = decide(&mut engine, "read_public_cache", 0);
+        assert_eq!(decision["permitted"], true);
+        assert!(decision.get("quota_remaining").is_none());
+    }
+
+    #[test]
+    fn test_prerequisite_must_run_first_in_window() {
+        let mut engine = PolicyEngine::from_config(
+            r#"{"action_prerequisites": {"compress_old_logs": ["clear_expired_cache"]},
+                "prerequisite_window_ms": 60000}"#,
+        )
+        .unwrap();
+
+        let decision = decide(&mut engine, "compress_old_logs", 0);
+        assert_eq!(decision["permitted"], false);
+        assert_eq!(decision["applied_rule"], "MISSING_PREREQUISITE");
+
+        assert_eq!(decide(&mut engine, "clear_expired_cache", 1_000)["permitted"], true);
+        assert_eq!(decide(&mut engine, "compress_old_logs", 2_000)["permitted"], true);
+
+        // Prerequisite has aged out of the window
+        let decision = decide(&mut engine, "compress_old_logs", 62_000);
+        assert_eq!(decision["applied_rule"], "MISSING_PREREQUISITE");
+    }
+}
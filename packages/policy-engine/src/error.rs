@@ -0,0 +1,112 @@
+// ============================================================
+// Infinity OS — Policy Errors
+//
+// Why an input never reached the rule cascade. Rust hosts get
+// these from the `try_*` functions; the WASM boundary turns each
+// one into its fail-closed decision, so JS callers still always
+// receive a decision and never an exception.
+// ============================================================
+
+use alloc::string::{String, ToString};
+use core::fmt;
+
+use crate::PolicyDecision;
+
+/// An input the engine refused before evaluating it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyError {
+    /// Malformed request JSON, with serde's line/column when it knows them
+    ParseRequest { message: String, position: Option<(usize, usize)> },
+    /// Malformed context JSON, with serde's line/column when it knows them
+    ParseContext { message: String, position: Option<(usize, usize)> },
+    /// An input over `max_input_bytes`, or metadata over its shape limits
+    InputTooLarge(String),
+    /// A top-level field strict mode does not know; `input` is
+    /// `"request"` or `"context"`
+    UnknownField { input: &'static str, field: String },
+    /// A decision that could not be serialized
+    Serialize(String),
+    /// A config refused as malformed or as weakening the built-in policy
+    ConfigInvalid(String),
+}
+
+impl PolicyError {
+    /// Parse error for `input` (`"request"` or `"context"`)
+    pub(crate) fn parse(input: &str, error: &serde_json::Error) -> PolicyError {
+        let message = error.to_string();
+        let position = (error.line() > 0).then(|| (error.line(), error.column()));
+        if input == "request" {
+            PolicyError::ParseRequest { message, position }
+        } else {
+            PolicyError::ParseContext { message, position }
+        }
+    }
+
+    /// Rule the error's fail-closed decision reports
+    pub fn applied_rule(&self) -> &'static str {
+        match self {
+            PolicyError::ParseRequest { .. } => "REQUEST_PARSE_ERROR",
+            PolicyError::ParseContext { .. } => "CONTEXT_PARSE_ERROR",
+            PolicyError::InputTooLarge(_) => "INPUT_TOO_LARGE",
+            PolicyError::UnknownField { .. } => "UNKNOWN_FIELD",
+            PolicyError::Serialize(_) => "SERIALIZE_ERROR",
+            PolicyError::ConfigInvalid(_) => "ENGINE_DEGRADED",
+        }
+    }
+
+    /// The fail-closed decision emitted in place of an evaluation
+    pub fn into_decision(self, timestamp_ms: u64) -> PolicyDecision {
+        let (iso_control, position) = match &self {
+            PolicyError::ParseRequest { position, .. }
+            | PolicyError::ParseContext { position, .. } => ("A.8.16", *position),
+            PolicyError::Serialize(_) => ("A.12.1.2", None),
+            PolicyError::ConfigInvalid(_) => ("A.17.1.1", None),
+            _ => ("A.8.16", None),
+        };
+        PolicyDecision {
+            permitted: false,
+            applied_rule: self.applied_rule().to_string(),
+            reason: self.to_string(),
+            iso_control: iso_control.to_string(),
+            timestamp_ms,
+            audit_required: true,
+            error_line: position.map(|(line, _)| line),
+            error_column: position.map(|(_, column)| column),
+            ..Default::default()
+        }
+        .finalize()
+    }
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyError::ParseRequest { message, .. } => {
+                write!(f, "Invalid request JSON: {}", message)
+            }
+            PolicyError::ParseContext { message, .. } => {
+                write!(f, "Invalid context JSON: {}", message)
+            }
+            PolicyError::InputTooLarge(reason) => f.write_str(reason),
+            PolicyError::UnknownField { input, field } => {
+                write!(f, "Unknown field '{}' in {} JSON (strict mode).", field, input)
+            }
+            PolicyError::Serialize(message) => {
+                write!(f, "Decision could not be serialized: {}", message)
+            }
+            PolicyError::ConfigInvalid(reason) => f.write_str(reason),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PolicyError {}
+
+/// Thrown to JS by the exports that return a `Result`, such as
+/// `PolicyEngine::from_config`
+#[cfg(feature = "wasm")]
+impl From<PolicyError> for wasm_bindgen::JsValue {
+    fn from(error: PolicyError) -> Self {
+        wasm_bindgen::JsValue::from_str(&error.to_string())
+    }
+}
@@ -14,22 +14,70 @@
 // Compile to WASM:
 //   cargo build --target wasm32-unknown-unknown --release
 //   wasm-pack build --target web
+//
+// Embedded (no_std + alloc) core — types and the rule cascade only.
+// The cdylib needs std, so build the rlib alone, for a no_std target:
+//   cargo rustc --lib --no-default-features --crate-type rlib \
+//     --target thumbv7em-none-eabihf
 // ============================================================
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+#[cfg(feature = "std")]
+use std::sync::OnceLock;
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
+/// Mark the start of a cascade stage for `trace-timing`. Expands to
+/// nothing without the feature, so untimed builds pay nothing.
+macro_rules! trace_rule {
+    ($stage:expr) => {
+        #[cfg(feature = "trace-timing")]
+        timing::mark($stage);
+    };
+}
+
+mod config;
+#[cfg(feature = "std")]
+mod engine;
+mod error;
+#[cfg(feature = "trace-timing")]
+mod timing;
+
+use config::{ActionCategory, ActionIndex};
+pub use config::{ApprovalToken, ArgumentSchema, MetadataCondition, PolicyConfig};
+#[cfg(feature = "std")]
+pub use engine::{
+    builtin_rule_priority, replay_decision, DecisionHook, PolicyEngine, Rule, TemporaryGrant,
+};
+pub use error::PolicyError;
+#[cfg(feature = "trace-timing")]
+pub use timing::RuleTiming;
+
 // ============================================================
 // TYPES
 // ============================================================
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "std", derive(JsonSchema))]
 pub struct AiRequest {
     /// The action the AI wants to perform
     pub action: String,
     /// The resource being targeted
     pub target_resource: String,
     /// Risk score 0-100 (AI-calculated)
+    #[cfg_attr(feature = "std", schemars(range(max = 100)))]
     pub risk_score: u8,
     /// The module requesting the action
     pub requesting_module: String,
@@ -39,9 +87,17 @@ pub struct AiRequest {
     pub organisation_id: Option<String>,
     /// Additional metadata
     pub metadata: Option<serde_json::Value>,
+    /// Out-of-band human approval, matched against `approval_tokens`
+    #[serde(default)]
+    pub approval_token: Option<String>,
+    /// Named risk sub-scores (0-100 each), combined by
+    /// `risk_component_weights` in place of `risk_score` when configured
+    #[serde(default)]
+    pub risk_components: Option<BTreeMap<String, u8>>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[cfg_attr(feature = "std", derive(JsonSchema))]
 pub struct PolicyDecision {
     /// Whether the action is permitted
     pub permitted: bool,
@@ -55,20 +111,335 @@ pub struct PolicyDecision {
     pub timestamp_ms: u64,
     /// Whether this decision should be audited
     pub audit_required: bool,
+    /// Deterministic ID from the request and timestamp (see `decision_id`),
+    /// predictable by the client before evaluation. Empty when the request
+    /// could not be parsed.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub decision_id: String,
+    /// The request's `metadata.trace_id`, when it carries a non-empty one,
+    /// so audit records can be joined with traces
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    /// Where the host should send this decision (e.g. `security-alerts`),
+    /// from the config's `route_hints`; unset only on input errors
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub route_hint: Option<String>,
+    /// Whether a human must review this decision: raised for elevation
+    /// requests and, by `PolicyEngine`, for risk regressions and risk
+    /// cool-downs; hosts can also force it via `merge_audit` /
+    /// `escalate_decision`
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub requires_human_review: bool,
+    /// Role named by an ELEVATION_REQUESTED decision, pending human approval
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requested_role: Option<String>,
+    /// Highest risk score earlier in the session, when this request's score
+    /// jumped past it by more than `risk_regression_delta`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_max_risk: Option<u8>,
+    /// How far this request's risk score exceeds `previous_max_risk`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub risk_delta: Option<u8>,
+    /// End (Unix ms) of the high-risk cool-down that put this write under
+    /// review (`risk_cooldown_ms`, stateful engine only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cooldown_until_ms: Option<u64>,
+    /// Consecutive denials of this action for this user, once the streak
+    /// reaches `repeated_denial_threshold` (stateful engine only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeated_denial_count: Option<u32>,
+    /// Risk compared against the maximum after trust decay, when it differs
+    /// from the reported `risk_score` (`risk_decay_per_success` only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective_risk_score: Option<u8>,
+    /// The request's `risk_components`, echoed when composite risk is on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub risk_components: Option<BTreeMap<String, u8>>,
+    /// Permits only: the smallest headroom left under any applicable
+    /// threshold (risk, session age, failed attempts), as a percentage of
+    /// that threshold. Small values flag permits that were almost denied.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub margin: Option<i32>,
+    /// Time spent in each cascade stage, in evaluation order
+    #[cfg(feature = "trace-timing")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rule_timings: Vec<RuleTiming>,
+    /// How long (days) the audit store should keep this record, when
+    /// `audit_retention_days` maps its rule or action. Audited decisions only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audit_retention_days: Option<u32>,
+    /// Every mapped control across frameworks (`include_all_controls` only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub controls: Vec<String>,
+    /// Every ISO 27001 control the rule touches, `iso_control` first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub iso_controls: Vec<String>,
+    /// Evaluated by a `PolicyEngine` whose config was refused, running the
+    /// built-in policy for reads only (`from_config_or_degraded`)
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub degraded: bool,
+    /// Request rate spiked well above the user's baseline (stateful engine only)
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub anomaly_detected: bool,
+    /// Every unmet authentication requirement (AUTH_REQUIREMENTS_NOT_MET only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub missing: Vec<String>,
+    /// The argument that failed its schema (INVALID_ARGUMENT only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invalid_argument: Option<String>,
+    /// Set for a deprecated action before its sunset; it is still allowed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecation_warning: Option<String>,
+    /// When a deprecated action stops being allowed (epoch ms)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sunset_timestamp_ms: Option<u64>,
+    /// Action-oriented hint for the user; empty when no user action will help
+    #[serde(default)]
+    pub remediation: String,
+    /// Original action name when an alias was resolved to a canonical action
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aliased_from: Option<String>,
+    /// Authentication challenge that would let the client retry successfully.
+    /// Never set when no authentication can help (HARD_BLOCK, NOT_IN_WHITELIST).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step_up_required: Option<StepUp>,
+    /// Every gate an audited permit cleared (`"<gate>: cleared"` / `"<gate>: n/a"`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cleared_gates: Vec<String>,
+    /// Remaining quota for this action in the current window (metered actions only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quota_remaining: Option<u32>,
+    /// On ACCOUNT_LOCKOUT: estimated wait (ms) until enough failures leave
+    /// the hourly window to lift the lock. Needs `oldest_failure_age_seconds`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after_ms: Option<u64>,
+    /// 1-based line of a JSON parse error, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_line: Option<usize>,
+    /// 1-based column of a JSON parse error, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_column: Option<usize>,
+}
+
+/// Route for decisions whose rule has no `route_hints` entry
+pub const DEFAULT_ROUTE: &str = "audit-log";
+
+/// Latest `PolicyDecision` wire schema. Version 1 is the original
+/// six-field shape; version 2 adds every optional field.
+pub const DECISION_SCHEMA_VERSION: u32 = 2;
+
+/// The version 1 decision shape, for clients that reject unknown keys
+#[derive(Serialize)]
+struct DecisionV1<'a> {
+    permitted: bool,
+    applied_rule: &'a str,
+    reason: &'a str,
+    iso_control: &'a str,
+    timestamp_ms: u64,
+    audit_required: bool,
+}
+
+/// Serialize a decision in the shape a client's schema version understands.
+/// A decision that cannot be serialized is replaced by its fail-closed
+/// `PolicyError::Serialize` decision.
+fn serialize_decision(decision: &PolicyDecision, schema_version: u32) -> String {
+    try_serialize_decision(decision, schema_version).unwrap_or_else(|error| {
+        serde_json::to_string(&error.into_decision(decision.timestamp_ms)).unwrap_or_default()
+    })
+}
+
+fn try_serialize_decision(
+    decision: &PolicyDecision,
+    schema_version: u32,
+) -> Result<String, PolicyError> {
+    let json = if schema_version == 1 {
+        serde_json::to_string(&DecisionV1 {
+            permitted: decision.permitted,
+            applied_rule: &decision.applied_rule,
+            reason: &decision.reason,
+            iso_control: &decision.iso_control,
+            timestamp_ms: decision.timestamp_ms,
+            audit_required: decision.audit_required,
+        })
+    } else {
+        serde_json::to_string(decision)
+    };
+    json.map_err(|e| PolicyError::Serialize(e.to_string()))
+}
+
+fn is_false(flag: &bool) -> bool {
+    !*flag
+}
+
+/// A step-up authentication challenge for the client to present
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(JsonSchema))]
+pub struct StepUp {
+    /// Challenge to present: `mfa` or `reauthenticate`
+    #[cfg_attr(feature = "std", schemars(schema_with = "step_up_method_schema"))]
+    pub method: String,
+    pub reason: String,
+}
+
+impl PolicyDecision {
+    /// Fill derived fields from the applied rule so they never drift
+    fn finalize(mut self) -> Self {
+        self.remediation = remediation_for(&self.applied_rule).to_string();
+        self.step_up_required = step_up_for(&self.applied_rule, &self.missing);
+        self
+    }
+}
+
+#[cfg(feature = "std")]
+fn step_up_method_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+    schemars::schema::SchemaObject {
+        instance_type: Some(schemars::schema::InstanceType::String.into()),
+        enum_values: Some(vec!["mfa".into(), "reauthenticate".into()]),
+        ..Default::default()
+    }
+    .into()
+}
+
+/// Step-up challenge per applied rule; `None` when authentication cannot
+/// change the outcome
+fn step_up_for(applied_rule: &str, missing: &[String]) -> Option<StepUp> {
+    let (method, reason) = match applied_rule {
+        "MFA_REQUIRED" => ("mfa", "This action requires MFA verification."),
+        "MFA_REAUTH_REQUIRED" => ("mfa", "MFA was verified too long ago for this action."),
+        "SENSITIVE_TARGET" => ("mfa", "Writes to this resource require MFA verification."),
+        "NEW_ACCOUNT_RESTRICTION" => ("mfa", "New accounts must verify MFA for this action."),
+        "SESSION_EXPIRED" => ("reauthenticate", "The session is too old for this action."),
+        "AUTH_REQUIREMENTS_NOT_MET"
+            if missing.iter().any(|m| m == "MFA_REQUIRED" || m == "MFA_REAUTH_REQUIRED") =>
+        {
+            ("mfa", "MFA verification is among the unmet requirements.")
+        }
+        "AUTH_REQUIRED_FOR_READ" if missing.iter().any(|m| m == "SESSION_EXPIRED") => {
+            ("reauthenticate", "The session is too old for this read.")
+        }
+        "ELEVATED_THREAT" if missing.iter().any(|m| m == "MFA_REQUIRED") => {
+            ("mfa", "MFA verification is required while the source threat score is elevated.")
+        }
+        "AUTH_REQUIREMENTS_NOT_MET" if missing.iter().any(|m| m == "SESSION_EXPIRED") => {
+            ("reauthenticate", "Re-authentication is among the unmet requirements.")
+        }
+        _ => return None,
+    };
+    Some(StepUp {
+        method: method.to_string(),
+        reason: reason.to_string(),
+    })
+}
+
+/// Remediation hint per applied rule. Empty means the decision
+/// cannot be changed by anything the user does.
+fn remediation_for(applied_rule: &str) -> &'static str {
+    match applied_rule {
+        "REQUEST_PARSE_ERROR" | "CONTEXT_PARSE_ERROR" => "Fix the malformed input and retry.",
+        "INPUT_TOO_LARGE" => "Reduce the request size and retry.",
+        "CONTEXT_IMPLAUSIBLE" => "Fix the client's security-context reporting and retry.",
+        "MISSING_TRACE_ID" => "Attach a trace_id to the request metadata and retry.",
+        "UNKNOWN_FIELD" => "Remove fields the policy schema does not define and retry.",
+        "RISK_SCORE_EXCEEDED" => "Submit the action for human review.",
+        "ACCOUNT_LOCKOUT" => "Wait for the lockout window to pass, then retry.",
+        "AUTH_REQUIREMENTS_NOT_MET" => "Resolve each missing requirement, then retry.",
+        "SESSION_EXPIRED" => "Re-authenticate.",
+        "TOO_MANY_SESSIONS" => "Sign out of your other sessions, then retry.",
+        "MFA_REQUIRED" | "NEW_ACCOUNT_RESTRICTION" => "Verify MFA and retry.",
+        "MFA_REAUTH_REQUIRED" => "Verify MFA again and retry.",
+        "SENSITIVE_TARGET" => "Verify MFA, then retry.",
+        "INSUFFICIENT_ROLE" => "Request elevation from an admin.",
+        "PANIC_MODE" => "Wait for an operator to clear panic mode.",
+        "ENGINE_DEGRADED" => "Retry once the engine's configuration is restored.",
+        "DUAL_CONTROL_REQUIRED" => "Have a second authorized approver co-sign, then retry.",
+        "REPEATED_DENIAL_BLOCK" => "Stop retrying the action and wait for the block to lift.",
+        "MAINTENANCE_FREEZE" => "Retry once the maintenance window has ended.",
+        "MISSING_ORG_CONTEXT" | "MISSING_USER_CONTEXT" => {
+            "Retry from a context that supplies the missing identifier."
+        }
+        "TRUSTED_NETWORK_REQUIRED" => "Retry from a trusted network.",
+        "DEPRECATED_MODULE" => "Update the requesting module to a supported version.",
+        "CROSS_USER_WRITE" => "Retry as the user whose data the action targets.",
+        "AUTH_REQUIRED_FOR_READ" => "Re-authenticate with a sufficiently privileged role and retry.",
+        "UNRECOGNIZED_ROLE" => "Ask an admin to assign a recognized role.",
+        "QUOTA_EXCEEDED" => "Wait for the quota window to reset, then retry.",
+        "MISSING_PREREQUISITE" => "Perform the prerequisite action first, then retry.",
+        "COLD_SESSION" => "Perform a routine action such as a read first, then retry.",
+        "ACTION_COOLDOWN" => "Wait for the cooldown to pass (see retry_after_ms), then retry.",
+        "METADATA_CONDITION_FAILED"
+        | "DISALLOWED_METADATA"
+        | "METADATA_REQUIRED"
+        | "INVALID_ARGUMENT" => "Correct the request metadata and retry.",
+        // HARD_BLOCK, NOT_IN_WHITELIST and permits: nothing to do
+        _ => "",
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Omitted fields take fail-closed defaults, so a partial context is
+/// evaluated conservatively instead of failing to parse. `user_role`
+/// stays required: the caller must always say who is acting.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "std", derive(JsonSchema))]
 pub struct SecurityContext {
     /// Current user's role
     pub user_role: String,
-    /// Whether MFA has been verified in this session
+    /// Whether MFA has been verified in this session.
+    /// Default `false`: unproven MFA is treated as absent.
+    #[serde(default)]
     pub mfa_verified: bool,
-    /// Session age in seconds
+    /// Session age in seconds.
+    /// Default `u64::MAX`: an unknown session age is treated as stale,
+    /// so write/system actions require re-authentication.
+    #[serde(default = "unknown_session_age")]
     pub session_age_seconds: u64,
-    /// Whether the request comes from a trusted network
+    /// Whether the request comes from a trusted network.
+    /// Default `false`: an unknown network is untrusted.
+    #[serde(default)]
     pub trusted_network: bool,
-    /// Number of failed attempts in last hour
+    /// Number of failed attempts in last hour.
+    /// Default `0`: lockout is driven by counters the caller reports;
+    /// with none reported the remaining rules still gate the action.
+    #[serde(default)]
     pub failed_attempts_last_hour: u32,
+    /// Age of the user's account in seconds, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account_age_seconds: Option<u64>,
+    /// Opaque session identifier, used by the stateful engine to track
+    /// per-session workflow state (falls back to the request's user_id)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// Edge-supplied reputation of the source IP, 0 (clean) to 100.
+    /// Absent means no signal and skips the threat rule.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "std", schemars(range(max = 100)))]
+    pub threat_score: Option<u8>,
+    /// Seconds since the oldest failed attempt still counted in
+    /// `failed_attempts_last_hour`; lets a lockout report `retry_after_ms`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oldest_failure_age_seconds: Option<u64>,
+    /// The subject the session authenticated as. User-scoped actions must
+    /// target this user; absent, they are denied.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authenticated_user_id: Option<String>,
+    /// Seconds since MFA was last verified, when known. Checked against
+    /// `max_mfa_age_seconds` for MFA-gated actions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mfa_age_seconds: Option<u64>,
+    /// Second approver of a dual-control action. The engine checks only that
+    /// it differs from `authenticated_user_id` and holds an eligible role;
+    /// the approval itself must be verified upstream.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub second_approver_id: Option<String>,
+    /// Role of `second_approver_id`, as verified upstream
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub second_approver_role: Option<String>,
+    /// The user's active sessions, this one included, as counted upstream
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub concurrent_sessions: Option<u32>,
+}
+
+fn unknown_session_age() -> u64 {
+    u64::MAX
 }
 
 // ============================================================
@@ -76,13 +447,25 @@ pub struct SecurityContext {
 // ============================================================
 
 /// Maximum risk score allowed for any AI action
-const MAX_RISK_SCORE: u8 = 50;
+pub(crate) const MAX_RISK_SCORE: u8 = 50;
 
 /// Maximum session age for sensitive operations (15 minutes)
-const MAX_SESSION_AGE_SENSITIVE: u64 = 900;
+pub(crate) const MAX_SESSION_AGE_SENSITIVE: u64 = 900;
 
 /// Maximum failed attempts before lockout
-const MAX_FAILED_ATTEMPTS: u32 = 5;
+pub(crate) const MAX_FAILED_ATTEMPTS: u32 = 5;
+
+/// Longest session a working client can report (one year)
+pub(crate) const MAX_PLAUSIBLE_SESSION_AGE: u64 = 365 * 24 * 3600;
+
+/// Most failed attempts in an hour a working client can report
+pub(crate) const MAX_PLAUSIBLE_FAILED_ATTEMPTS: u32 = 10_000;
+
+/// Version stamp of the built-in ruleset
+pub(crate) const RULESET_VERSION: &str = "1.0.0";
+
+/// Recognized roles, lowest privilege first
+pub(crate) const KNOWN_ROLES: &[&str] = &["user", "power_user", "org_admin", "super_admin"];
 
 // ============================================================
 // ALLOWED ACTIONS — Hardcoded whitelist
@@ -90,7 +473,7 @@ const MAX_FAILED_ATTEMPTS: u32 = 5;
 // Default deny: anything not listed is BLOCKED.
 // ============================================================
 
-const ALLOWED_READ_ACTIONS: &[&str] = &[
+pub(crate) const ALLOWED_READ_ACTIONS: &[&str] = &[
     "read_public_cache",
     "read_user_preferences",
     "read_module_config",
@@ -101,7 +484,7 @@ const ALLOWED_READ_ACTIONS: &[&str] = &[
     "read_system_metrics",
 ];
 
-const ALLOWED_WRITE_ACTIONS: &[&str] = &[
+pub(crate) const ALLOWED_WRITE_ACTIONS: &[&str] = &[
     "write_user_preferences",
     "write_notification",
     "write_search_index",
@@ -110,9 +493,14 @@ const ALLOWED_WRITE_ACTIONS: &[&str] = &[
     "adjust_ui_theme",
     "prefetch_module",
     "cache_file_metadata",
+    ELEVATION_ACTION,
 ];
 
-const ALLOWED_SYSTEM_ACTIONS: &[&str] = &[
+/// Write action through which the AI asks a human for a role elevation.
+/// Permitting it only records the request; it never changes a role.
+pub(crate) const ELEVATION_ACTION: &str = "request_role_elevation";
+
+pub(crate) const ALLOWED_SYSTEM_ACTIONS: &[&str] = &[
     "schedule_background_task",
     "clear_expired_cache",
     "compress_old_logs",
@@ -121,7 +509,7 @@ const ALLOWED_SYSTEM_ACTIONS: &[&str] = &[
 ];
 
 /// Actions that are ALWAYS blocked regardless of context
-const BLOCKED_ACTIONS: &[&str] = &[
+pub(crate) const BLOCKED_ACTIONS: &[&str] = &[
     "modify_kernel_scheduler",
     "modify_security_policy",
     "delete_user_data",
@@ -144,318 +532,6019 @@ const BLOCKED_ACTIONS: &[&str] = &[
 
 /// Main policy validation function
 /// Called by the AI orchestration layer before any action
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub fn validate_ai_action(
     request_json: &str,
     context_json: &str,
     timestamp_ms: u64,
 ) -> String {
-    // Parse request — fail closed on bad JSON
-    let request: AiRequest = match serde_json::from_str(request_json) {
-        Ok(r) => r,
-        Err(e) => {
-            return serde_json::to_string(&PolicyDecision {
-                permitted: false,
-                applied_rule: "PARSE_ERROR".to_string(),
-                reason: format!("Invalid request JSON: {}", e),
-                iso_control: "A.8.16".to_string(),
-                timestamp_ms,
-                audit_required: true,
-            }).unwrap_or_default();
+    let (config, index) = &default_policy();
+    let decision = validate_json_with_config(
+        config,
+        index,
+        request_json,
+        context_json,
+        timestamp_ms,
+    );
+    serialize_decision(&decision, DECISION_SCHEMA_VERSION)
+}
+
+/// `validate_ai_action` for Rust hosts that want a refused input as an
+/// error rather than as its fail-closed decision. An `Ok` decision is
+/// identical to what `validate_ai_action` returns for the same inputs.
+pub fn try_validate_ai_action(
+    request_json: &str,
+    context_json: &str,
+    timestamp_ms: u64,
+) -> Result<PolicyDecision, PolicyError> {
+    let (config, index) = &default_policy();
+    try_validate_json_with_config(config, index, request_json, context_json, timestamp_ms)
+}
+
+/// `validate_ai_action` with decision-shape negotiation: version 1 clients
+/// get the original six-field decision; any other version (including
+/// unknown or future ones) gets the latest shape
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn validate_ai_action_v(
+    request_json: &str,
+    context_json: &str,
+    timestamp_ms: u64,
+    schema_version: u32,
+) -> String {
+    let (config, index) = &default_policy();
+    let decision = validate_json_with_config(config, index, request_json, context_json, timestamp_ms);
+    serialize_decision(&decision, schema_version)
+}
+
+/// `validate_ai_action` with `redact_reasons` on: the request's
+/// `target_resource` and `user_id` never appear in the decision's `reason`.
+/// Parse errors carry no request to redact and are returned as-is.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn validate_ai_action_redacted(
+    request_json: &str,
+    context_json: &str,
+    timestamp_ms: u64,
+) -> String {
+    let (config, index) = &default_policy();
+    let decision = match parse_inputs(config, request_json, context_json) {
+        Ok((request, context)) => {
+            let mut decision = evaluate_indexed(config, index, &request, &context, timestamp_ms);
+            redact_reason(&mut decision, &request);
+            decision
         }
+        Err(error) => error.into_decision(timestamp_ms),
     };
+    serialize_decision(&decision, DECISION_SCHEMA_VERSION)
+}
 
-    // Parse security context — fail closed on bad JSON
-    let context: SecurityContext = match serde_json::from_str(context_json) {
-        Ok(c) => c,
+/// SHA-256 (hex) of the built-in blocklist, the baseline for
+/// `PolicyConfig.expected_blocklist_checksum`
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn blocklist_checksum() -> String {
+    default_policy().0.blocklist_checksum()
+}
+
+/// Static classification of one action name under the built-in policy, as
+/// `{"category": "blocked"|"read"|"write"|"system"|"unknown", "blocked": bool}`.
+/// A pure lookup: no request or context is involved, so a listed action may
+/// still be denied at evaluation time, but a blocked one never permitted.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn classify_action_name(action: &str) -> String {
+    let (config, index) = &default_policy();
+    let category = index.category(config.canonical_action(action));
+    serde_json::json!({
+        "category": category.name(),
+        "blocked": category == ActionCategory::Blocked,
+    })
+    .to_string()
+}
+
+/// Name fragments that mark an action as worth a second look before it is
+/// allowlisted, even when it is not itself blocked
+const SUSPICIOUS_ACTION_FRAGMENTS: &[&str] = &[
+    "delete",
+    "modify_security",
+    "vault",
+    "disable_",
+    "audit_log",
+    "encryption",
+    "permission",
+    "rbac",
+    "admin",
+];
+
+/// Lint a proposed allowlist (a JSON array of action names, `*` wildcards
+/// allowed) against the built-in blocked set, as `{safe, errors, warnings}`.
+/// An entry equal to, or a wildcard matching, a blocked action is an error;
+/// one whose name contains a suspicious fragment (`delete`, `vault`, ...)
+/// is a warning. `safe` means no errors. Bad JSON yields `{"error"}`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn check_allowlist_safety(allowlist_json: &str) -> String {
+    let allowlist: Vec<String> = match serde_json::from_str(allowlist_json) {
+        Ok(allowlist) => allowlist,
         Err(e) => {
-            return serde_json::to_string(&PolicyDecision {
-                permitted: false,
-                applied_rule: "PARSE_ERROR".to_string(),
-                reason: format!("Invalid context JSON: {}", e),
-                iso_control: "A.8.16".to_string(),
-                timestamp_ms,
-                audit_required: true,
-            }).unwrap_or_default();
+            let error = format!("Invalid allowlist JSON: {}", e);
+            return serde_json::json!({ "error": error }).to_string();
         }
     };
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+    for action in &allowlist {
+        let blocked: Vec<&str> =
+            BLOCKED_ACTIONS.iter().copied().filter(|b| glob_match(action, b)).collect();
+        if !blocked.is_empty() {
+            errors.push(serde_json::json!({
+                "action": action,
+                "message": format!("Allows blocked action(s): {}.", blocked.join(", ")),
+            }));
+            continue;
+        }
+        let name = action.to_lowercase();
+        let fragments: Vec<&str> = SUSPICIOUS_ACTION_FRAGMENTS
+            .iter()
+            .copied()
+            .filter(|fragment| name.contains(fragment))
+            .collect();
+        if !fragments.is_empty() {
+            let message =
+                format!("Name contains '{}'; confirm it is safe.", fragments.join("', '"));
+            warnings.push(serde_json::json!({ "action": action, "message": message }));
+        }
+    }
+    serde_json::json!({ "safe": errors.is_empty(), "errors": errors, "warnings": warnings })
+        .to_string()
+}
 
-    let decision = evaluate_policy(&request, &context, timestamp_ms);
-    serde_json::to_string(&decision).unwrap_or_default()
+/// JSON Schema (draft-07) documents for the wire types, keyed by type
+/// name, derived from the Rust structs so client codegen never drifts
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn json_schema() -> String {
+    serde_json::json!({
+        "AiRequest": schemars::schema_for!(AiRequest),
+        "SecurityContext": schemars::schema_for!(SecurityContext),
+        "PolicyDecision": schemars::schema_for!(PolicyDecision),
+        "GdprDecision": schemars::schema_for!(GdprDecision),
+        "AuditRecord": schemars::schema_for!(AuditRecord),
+    })
+    .to_string()
 }
 
-/// Evaluate the policy — pure deterministic logic
-fn evaluate_policy(
-    request: &AiRequest,
-    context: &SecurityContext,
+/// Batch validation over newline-delimited JSON: one `AiRequest` per
+/// input line, one `PolicyDecision` per output line, in order. All
+/// requests share one security context. Blank lines are skipped;
+/// malformed lines yield a fail-closed REQUEST_PARSE_ERROR line.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn validate_ai_action_batch_ndjson(
+    requests_ndjson: &str,
+    context_json: &str,
     timestamp_ms: u64,
-) -> PolicyDecision {
+) -> String {
+    let (config, index) = &default_policy();
+    let context = parse_context(config, context_json);
 
-    // --------------------------------------------------------
-    // RULE 1: Hard block — always denied actions
-    // --------------------------------------------------------
-    if BLOCKED_ACTIONS.contains(&request.action.as_str()) {
-        return PolicyDecision {
-            permitted: false,
-            applied_rule: "HARD_BLOCK".to_string(),
-            reason: format!(
-                "Action '{}' is permanently blocked. AI cannot modify security-critical resources.",
-                request.action
-            ),
-            iso_control: "A.9.4.1".to_string(),
-            timestamp_ms,
-            audit_required: true,
+    let mut output = String::new();
+    for line in requests_ndjson.lines().filter(|l| !l.trim().is_empty()) {
+        let decision = match &context {
+            Err(error) => error.clone().into_decision(timestamp_ms),
+            Ok(context) => match parse_request(config, line) {
+                Ok(request) => evaluate_indexed(config, index, &request, context, timestamp_ms),
+                Err(error) => error.into_decision(timestamp_ms),
+            },
         };
+        output.push_str(&serialize_decision(&decision, DECISION_SCHEMA_VERSION));
+        output.push('\n');
     }
+    output
+}
 
-    // --------------------------------------------------------
-    // RULE 2: Risk score threshold
-    // --------------------------------------------------------
-    if request.risk_score > MAX_RISK_SCORE {
-        return PolicyDecision {
-            permitted: false,
-            applied_rule: "RISK_SCORE_EXCEEDED".to_string(),
-            reason: format!(
-                "Risk score {} exceeds maximum allowed {}. Human review required.",
-                request.risk_score, MAX_RISK_SCORE
-            ),
-            iso_control: "A.8.16".to_string(),
-            timestamp_ms,
-            audit_required: true,
-        };
-    }
+/// Streaming form of `validate_ai_action_batch_ndjson`: requests are
+/// pushed one at a time against a security context fixed for the
+/// session's lifetime, and per-rule counts accumulate for `summary`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct BatchSession {
+    /// The shared context, or the error its JSON produced
+    context: Result<SecurityContext, PolicyError>,
+    permitted: u64,
+    by_rule: BTreeMap<String, u64>,
+}
 
-    // --------------------------------------------------------
-    // RULE 3: Account lockout check
-    // --------------------------------------------------------
-    if context.failed_attempts_last_hour >= MAX_FAILED_ATTEMPTS {
-        return PolicyDecision {
-            permitted: false,
-            applied_rule: "ACCOUNT_LOCKOUT".to_string(),
-            reason: format!(
-                "Too many failed attempts ({}/{}). Account temporarily locked.",
-                context.failed_attempts_last_hour, MAX_FAILED_ATTEMPTS
-            ),
-            iso_control: "A.9.4.3".to_string(),
-            timestamp_ms,
-            audit_required: true,
-        };
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl BatchSession {
+    /// Session over one context. A malformed context is not an error here:
+    /// every push then yields its fail-closed decision.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(context_json: &str) -> BatchSession {
+        let (config, _) = &default_policy();
+        BatchSession {
+            context: parse_context(config, context_json),
+            permitted: 0,
+            by_rule: BTreeMap::new(),
+        }
     }
 
-    // --------------------------------------------------------
-    // RULE 4: Session age check for sensitive operations
-    // --------------------------------------------------------
-    let is_write = ALLOWED_WRITE_ACTIONS.contains(&request.action.as_str())
-        || ALLOWED_SYSTEM_ACTIONS.contains(&request.action.as_str());
-
-    if is_write && context.session_age_seconds > MAX_SESSION_AGE_SENSITIVE {
-        return PolicyDecision {
-            permitted: false,
-            applied_rule: "SESSION_EXPIRED".to_string(),
-            reason: format!(
-                "Session age {}s exceeds {}s limit for write operations. Re-authentication required.",
-                context.session_age_seconds, MAX_SESSION_AGE_SENSITIVE
-            ),
-            iso_control: "A.9.4.2".to_string(),
-            timestamp_ms,
-            audit_required: false,
+    /// Evaluate one request against the session's context and return its
+    /// decision, exactly as `validate_ai_action` would
+    pub fn push(&mut self, request_json: &str, timestamp_ms: u64) -> String {
+        let (config, index) = &default_policy();
+        let decision = match &self.context {
+            Err(error) => error.clone().into_decision(timestamp_ms),
+            Ok(context) => match parse_request(config, request_json) {
+                Ok(request) => evaluate_indexed(config, index, &request, context, timestamp_ms),
+                Err(error) => error.into_decision(timestamp_ms),
+            },
         };
+        self.permitted += u64::from(decision.permitted);
+        *self.by_rule.entry(decision.applied_rule.clone()).or_insert(0) += 1;
+        serialize_decision(&decision, DECISION_SCHEMA_VERSION)
     }
 
-    // --------------------------------------------------------
-    // RULE 5: MFA required for system actions
-    // --------------------------------------------------------
-    if ALLOWED_SYSTEM_ACTIONS.contains(&request.action.as_str()) && !context.mfa_verified {
-        return PolicyDecision {
-            permitted: false,
-            applied_rule: "MFA_REQUIRED".to_string(),
-            reason: "System-level actions require MFA verification.".to_string(),
-            iso_control: "A.9.4.2".to_string(),
-            timestamp_ms,
-            audit_required: false,
-        };
+    /// Counts so far, as `{total, permitted, denied, by_rule}`
+    pub fn summary(&self) -> String {
+        let total: u64 = self.by_rule.values().sum();
+        serde_json::json!({
+            "total": total,
+            "permitted": self.permitted,
+            "denied": total - self.permitted,
+            "by_rule": self.by_rule,
+        })
+        .to_string()
     }
+}
 
-    // --------------------------------------------------------
-    // RULE 6: Role-based action restrictions
-    // --------------------------------------------------------
-    if context.user_role == "user" && ALLOWED_SYSTEM_ACTIONS.contains(&request.action.as_str()) {
-        return PolicyDecision {
-            permitted: false,
-            applied_rule: "INSUFFICIENT_ROLE".to_string(),
-            reason: format!(
-                "Role '{}' cannot perform system actions. Requires 'power_user' or higher.",
-                context.user_role
-            ),
-            iso_control: "A.9.2.3".to_string(),
-            timestamp_ms,
-            audit_required: false,
-        };
+/// Parse and evaluate raw JSON inputs under the given configuration.
+/// Every malformed or oversized input fails closed.
+fn validate_json_with_config(
+    config: &PolicyConfig,
+    index: &ActionIndex,
+    request_json: &str,
+    context_json: &str,
+    timestamp_ms: u64,
+) -> PolicyDecision {
+    try_validate_json_with_config(config, index, request_json, context_json, timestamp_ms)
+        .unwrap_or_else(|error| error.into_decision(timestamp_ms))
+}
+
+/// `validate_json_with_config`, reporting a refused input as its error
+fn try_validate_json_with_config(
+    config: &PolicyConfig,
+    index: &ActionIndex,
+    request_json: &str,
+    context_json: &str,
+    timestamp_ms: u64,
+) -> Result<PolicyDecision, PolicyError> {
+    let (request, context) = parse_inputs(config, request_json, context_json)?;
+    Ok(evaluate_indexed(config, index, &request, &context, timestamp_ms))
+}
+
+/// Parse both JSON inputs, enforcing the configured size limits
+fn parse_inputs(
+    config: &PolicyConfig,
+    request_json: &str,
+    context_json: &str,
+) -> Result<(AiRequest, SecurityContext), PolicyError> {
+    let request = parse_request(config, request_json)?;
+    let context = parse_context(config, context_json)?;
+    Ok((request, context))
+}
+
+fn parse_request(config: &PolicyConfig, request_json: &str) -> Result<AiRequest, PolicyError> {
+    // Size limit — checked before any parsing is attempted
+    if request_json.len() > config.max_input_bytes {
+        return Err(PolicyError::InputTooLarge(format!(
+            "Request JSON is {} bytes, exceeding the {} byte limit.",
+            request_json.len(), config.max_input_bytes
+        )));
     }
 
-    // --------------------------------------------------------
-    // RULE 7: Whitelist check — default deny
-    // --------------------------------------------------------
-    let is_allowed = ALLOWED_READ_ACTIONS.contains(&request.action.as_str())
-        || ALLOWED_WRITE_ACTIONS.contains(&request.action.as_str())
-        || ALLOWED_SYSTEM_ACTIONS.contains(&request.action.as_str());
+    // Parse request — fail closed on bad JSON
+    let request: AiRequest =
+        serde_json::from_str(request_json).map_err(|e| PolicyError::parse("request", &e))?;
 
-    if !is_allowed {
-        return PolicyDecision {
-            permitted: false,
-            applied_rule: "NOT_IN_WHITELIST".to_string(),
-            reason: format!(
-                "Action '{}' is not in the permitted actions whitelist. Default deny.",
-                request.action
-            ),
-            iso_control: "A.9.4.1".to_string(),
-            timestamp_ms,
-            audit_required: true,
-        };
+    if config.strict_fields {
+        if let Some(field) = unknown_field(request_json, REQUEST_FIELDS) {
+            return Err(PolicyError::UnknownField { input: "request", field });
+        }
     }
 
-    // --------------------------------------------------------
-    // PERMITTED — all rules passed
-    // --------------------------------------------------------
-    PolicyDecision {
-        permitted: true,
-        applied_rule: "WHITELIST_APPROVED".to_string(),
-        reason: format!(
-            "Action '{}' approved. Risk score: {}/{}.",
-            request.action, request.risk_score, MAX_RISK_SCORE
-        ),
-        iso_control: "A.9.4.1".to_string(),
-        timestamp_ms,
-        audit_required: request.risk_score > 30,   // Audit medium-risk actions
+    check_request_metadata(config, &request)?;
+    Ok(request)
+}
+
+/// Metadata shape limits — deeply nested or bloated payloads are refused.
+/// Applied to typed requests too, so they match the JSON path exactly.
+fn check_request_metadata(config: &PolicyConfig, request: &AiRequest) -> Result<(), PolicyError> {
+    match &request.metadata {
+        Some(metadata) => {
+            check_metadata_limits(metadata, config).map_err(PolicyError::InputTooLarge)
+        }
+        None => Ok(()),
     }
 }
 
-// ============================================================
-// CRYPTO-SHREDDING HELPERS
-// ============================================================
+fn parse_context(
+    config: &PolicyConfig,
+    context_json: &str,
+) -> Result<SecurityContext, PolicyError> {
+    // Size limit — checked before any parsing is attempted
+    if context_json.len() > config.max_input_bytes {
+        return Err(PolicyError::InputTooLarge(format!(
+            "Context JSON is {} bytes, exceeding the {} byte limit.",
+            context_json.len(), config.max_input_bytes
+        )));
+    }
 
-/// Validate a GDPR deletion request
-/// Returns true if the deletion is valid and should proceed
-#[wasm_bindgen]
-pub fn validate_gdpr_deletion(
-    user_id: &str,
-    requesting_user_id: &str,
-    requester_role: &str,
-    timestamp_ms: u64,
-) -> String {
-    #[derive(Serialize)]
-    struct DeletionDecision {
-        permitted: bool,
-        reason: String,
-        action: String,
-        iso_control: String,
-        gdpr_article: String,
-        timestamp_ms: u64,
+    // Parse security context — fail closed on bad JSON
+    let context =
+        serde_json::from_str(context_json).map_err(|e| PolicyError::parse("context", &e))?;
+
+    if config.strict_fields {
+        if let Some(field) = unknown_field(context_json, CONTEXT_FIELDS) {
+            return Err(PolicyError::UnknownField { input: "context", field });
+        }
     }
+    Ok(context)
+}
 
-    // Only the user themselves or an admin can request deletion
-    let permitted = user_id == requesting_user_id
-        || requester_role == "org_admin"
-        || requester_role == "super_admin";
+/// Wire field names of `AiRequest` and `SecurityContext`, for strict mode
+const REQUEST_FIELDS: &[&str] = &[
+    "action",
+    "target_resource",
+    "risk_score",
+    "requesting_module",
+    "user_id",
+    "organisation_id",
+    "metadata",
+    "approval_token",
+    "risk_components",
+];
+const CONTEXT_FIELDS: &[&str] = &[
+    "user_role",
+    "mfa_verified",
+    "session_age_seconds",
+    "trusted_network",
+    "failed_attempts_last_hour",
+    "account_age_seconds",
+    "session_id",
+    "threat_score",
+    "oldest_failure_age_seconds",
+    "authenticated_user_id",
+    "mfa_age_seconds",
+    "second_approver_id",
+    "second_approver_role",
+    "concurrent_sessions",
+];
 
-    let decision = DeletionDecision {
-        permitted,
-        reason: if permitted {
-            "GDPR deletion request validated. Proceed with crypto-shredding.".to_string()
-        } else {
-            "Deletion request denied: requester is not the data subject or an authorised admin.".to_string()
-        },
-        action: if permitted {
-            "DELETE_VAULT_KEY".to_string()
-        } else {
-            "DENY".to_string()
-        },
-        iso_control: "A.8.3".to_string(),
-        gdpr_article: "Article 17 — Right to erasure".to_string(),
-        timestamp_ms,
-    };
+/// First top-level key of an already-validated JSON object outside `known`
+fn unknown_field(json: &str, known: &[&str]) -> Option<String> {
+    let object: serde_json::Map<String, serde_json::Value> = serde_json::from_str(json).ok()?;
+    object.keys().find(|key| !known.contains(&key.as_str())).cloned()
+}
+
+/// Walk the metadata tree iteratively (no recursion, so no stack risk)
+/// and enforce the configured nesting depth and total node count
+fn check_metadata_limits(
+    metadata: &serde_json::Value,
+    config: &PolicyConfig,
+) -> Result<(), String> {
+    let mut stack = vec![(metadata, 1usize)];
+    let mut nodes = 0usize;
+    while let Some((value, depth)) = stack.pop() {
+        nodes += 1;
+        if depth > config.max_metadata_depth {
+            return Err(format!(
+                "Metadata nesting depth exceeds the limit of {}.",
+                config.max_metadata_depth
+            ));
+        }
+        if nodes > config.max_metadata_entries {
+            return Err(format!(
+                "Metadata contains more than {} entries.",
+                config.max_metadata_entries
+            ));
+        }
+        match value {
+            serde_json::Value::Array(items) => {
+                stack.extend(items.iter().map(|v| (v, depth + 1)));
+            }
+            serde_json::Value::Object(map) => {
+                stack.extend(map.values().map(|v| (v, depth + 1)));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
 
-    serde_json::to_string(&decision).unwrap_or_default()
+/// Built-in default configuration and its action index, built on first use
+#[cfg(feature = "std")]
+fn default_policy() -> &'static (PolicyConfig, ActionIndex) {
+    static DEFAULT: OnceLock<(PolicyConfig, ActionIndex)> = OnceLock::new();
+    DEFAULT.get_or_init(|| {
+        let config = PolicyConfig::default();
+        let index = ActionIndex::new(&config);
+        (config, index)
+    })
 }
 
-// ============================================================
-// TESTS
-// ============================================================
+/// Without `std` there is no lazy static, so the defaults are rebuilt per call
+#[cfg(not(feature = "std"))]
+fn default_policy() -> (PolicyConfig, ActionIndex) {
+    let config = PolicyConfig::default();
+    let index = ActionIndex::new(&config);
+    (config, index)
+}
+
+/// Evaluate already-typed values with the built-in default configuration.
+/// For Rust hosts: the decision is identical to `validate_ai_action` on
+/// the same values serialized, without the JSON round-trip.
+pub fn evaluate_policy(
+    request: &AiRequest,
+    context: &SecurityContext,
+    timestamp_ms: u64,
+) -> PolicyDecision {
+    let (config, index) = &default_policy();
+    if let Err(error) = check_request_metadata(config, request) {
+        return error.into_decision(timestamp_ms);
+    }
+    evaluate_indexed(config, index, request, context, timestamp_ms)
+}
 
+/// Evaluate the policy under an ad-hoc configuration. Builds the action
+/// index on every call; long-lived callers should hold an `ActionIndex`
+/// (as `PolicyEngine` does) and use `evaluate_indexed`, so only tests do.
 #[cfg(test)]
-mod tests {
-    use super::*;
+fn evaluate_policy_with_config(
+    config: &PolicyConfig,
+    request: &AiRequest,
+    context: &SecurityContext,
+    timestamp_ms: u64,
+) -> PolicyDecision {
+    evaluate_indexed(config, &ActionIndex::new(config), request, context, timestamp_ms)
+}
 
-    fn make_context(role: &str, mfa: bool, risk: u8) -> (AiRequest, SecurityContext) {
-        let req = AiRequest {
-            action: "read_public_cache".to_string(),
-            target_resource: "cache:public".to_string(),
-            risk_score: risk,
-            requesting_module: "com.infinity-os.shell".to_string(),
-            user_id: Some("user-123".to_string()),
-            organisation_id: Some("org-456".to_string()),
-            metadata: None,
-        };
-        let ctx = SecurityContext {
-            user_role: role.to_string(),
-            mfa_verified: mfa,
-            session_age_seconds: 300,
-            trusted_network: true,
-            failed_attempts_last_hour: 0,
-        };
-        (req, ctx)
+/// Evaluate the policy — pure deterministic logic
+fn evaluate_indexed(
+    config: &PolicyConfig,
+    index: &ActionIndex,
+    request: &AiRequest,
+    context: &SecurityContext,
+    timestamp_ms: u64,
+) -> PolicyDecision {
+    let risk = config.composite_risk(request.risk_score, request.risk_components.as_ref());
+    let mut decision = evaluate_at_risk(config, index, request, context, risk, timestamp_ms);
+    annotate_decision(config, index, request, context, timestamp_ms, &mut decision);
+    if config.redact_reasons {
+        redact_reason(&mut decision, request);
     }
+    decision
+}
 
-    #[test]
-    fn test_allowed_read_action() {
-        let (req, ctx) = make_context("user", false, 10);
-        let decision = evaluate_policy(&req, &ctx, 0);
-        assert!(decision.permitted);
+/// Mask the request's identifying values wherever a rule echoed them into
+/// the reason. Applied after evaluation, so no rule branch can bypass it.
+fn redact_reason(decision: &mut PolicyDecision, request: &AiRequest) {
+    let mut values = [request.target_resource.as_str(), request.user_id.as_deref().unwrap_or("")];
+    // Longest first, so a value containing the other is masked whole
+    values.sort_by_key(|value| core::cmp::Reverse(value.len()));
+    for value in values.into_iter().filter(|value| !value.is_empty()) {
+        decision.reason = decision.reason.replace(value, "[redacted]");
     }
+}
 
-    #[test]
-    fn test_blocked_action_always_denied() {
-        let (mut req, ctx) = make_context("super_admin", true, 0);
+/// Evaluate with `effective_risk` (composite risk, less any trust decay)
+/// standing in for the reported risk score in the threshold comparison
+/// only. Every other rule, and the decision itself, still sees the
+/// reported score. The result still needs `annotate_decision`.
+fn evaluate_at_risk(
+    config: &PolicyConfig,
+    index: &ActionIndex,
+    request: &AiRequest,
+    context: &SecurityContext,
+    effective_risk: u8,
+    timestamp_ms: u64,
+) -> PolicyDecision {
+    let base_risk = config.composite_risk(request.risk_score, request.risk_components.as_ref());
+    let composite =
+        request.risk_components.is_some() && !config.risk_component_weights.is_empty();
+    // Aliases resolve first, so the canonical action is what every rule sees
+    let canonical = config.canonical_action(&request.action);
+    let aliased = canonical != request.action;
+    let resolved;
+    let evaluated = if aliased {
+        resolved = AiRequest {
+            action: canonical.to_string(),
+            ..request.clone()
+        };
+        &resolved
+    } else {
+        request
+    };
+    let mut decision =
+        evaluate_approved(config, index, evaluated, context, effective_risk, timestamp_ms);
+    if aliased {
+        decision.reason.push_str(&format!(
+            " (Alias '{}' resolved to '{}'.)",
+            request.action, canonical
+        ));
+        decision.aliased_from = Some(request.action.clone());
+    }
+    if effective_risk != request.risk_score || composite {
+        if decision.permitted && effective_risk != base_risk {
+            decision
+                .reason
+                .push_str(&format!(" Effective risk {} after trust decay.", effective_risk));
+        }
+        decision.effective_risk_score = Some(effective_risk);
+    }
+    if composite {
+        decision.risk_components = request.risk_components.clone();
+    }
+    decision.finalize()
+}
+
+/// Per-request fields layered on a final decision, exactly once: controls
+/// and route, sensitive-target audit, deprecation, retention, the gate
+/// list of an audited permit, and IDs. Runs
+/// after everything that can change the applied rule or raise
+/// `audit_required`, so `PolicyEngine` calls it after its overlays.
+pub(crate) fn annotate_decision(
+    config: &PolicyConfig,
+    index: &ActionIndex,
+    request: &AiRequest,
+    context: &SecurityContext,
+    timestamp_ms: u64,
+    decision: &mut PolicyDecision,
+) {
+    let action = config.canonical_action(&request.action);
+    attach_controls(config, request, decision);
+    flag_sensitive_target(config, request, decision);
+    attach_deprecation(config, action, timestamp_ms, decision);
+    attach_retention(config, action, decision);
+    // Only audited permits carry the gate list; the common path stays allocation-light
+    if decision.permitted && decision.audit_required {
+        decision.cleared_gates = cleared_gates(config, index, request, context);
+    }
+    decision.decision_id = decision_id(request, timestamp_ms);
+    decision.trace_id = trace_id(request);
+}
+
+/// The rule cascade, then pre-approval: a configured approval token bound to
+/// this action and user lifts a risk or role denial. The cascade is re-run
+/// with only the risk ceiling and system role minimum lifted, so every
+/// other rule, the hard block above all, still has to pass.
+fn evaluate_approved(
+    config: &PolicyConfig,
+    index: &ActionIndex,
+    request: &AiRequest,
+    context: &SecurityContext,
+    effective_risk: u8,
+    timestamp_ms: u64,
+) -> PolicyDecision {
+    #[cfg(feature = "trace-timing")]
+    timing::begin();
+    let decision =
+        evaluate_rules(config, index, request, context, effective_risk, false, timestamp_ms);
+    #[cfg(feature = "trace-timing")]
+    let decision = PolicyDecision {
+        rule_timings: timing::end(),
+        ..decision
+    };
+    if !matches!(decision.applied_rule.as_str(), "RISK_SCORE_EXCEEDED" | "INSUFFICIENT_ROLE") {
+        return decision;
+    }
+    let approval = request.approval_token.as_deref().and_then(|token| {
+        config.approval_for(token, &request.action, request.user_id.as_deref(), timestamp_ms)
+    });
+    let Some(approval) = approval else {
+        return decision;
+    };
+    let lifted = evaluate_rules(config, index, request, context, effective_risk, true, timestamp_ms);
+    if !lifted.permitted {
+        return lifted;
+    }
+    PolicyDecision {
+        permitted: true,
+        applied_rule: "PRE_APPROVED".to_string(),
+        reason: format!(
+            "Action '{}' pre-approved for user '{}' until {}, overriding {}.",
+            approval.action, approval.user_id, approval.expires_at_ms, decision.applied_rule
+        ),
+        iso_control: "A.9.2.2".to_string(),
+        timestamp_ms,
+        audit_required: true,
+        ..Default::default()
+    }
+}
+
+/// One evaluation's inputs and the facts derived from them, shared by
+/// every built-in rule. `pre_approved` lifts the risk ceiling and system
+/// role minimum, and nothing else.
+struct Cascade<'a> {
+    config: &'a PolicyConfig,
+    index: &'a ActionIndex,
+    request: &'a AiRequest,
+    context: &'a SecurityContext,
+    effective_risk: u8,
+    pre_approved: bool,
+    timestamp_ms: u64,
+    /// A deprecated action past its sunset, no longer whitelisted
+    retired: bool,
+    category: ActionCategory,
+    is_system: bool,
+    is_write: bool,
+    mfa_required: bool,
+    /// The system action's minimum role, when the caller's falls short;
+    /// unranked roles never meet it, and an unranked minimum is never met
+    role_shortfall: Option<&'a str>,
+    role_recognized: bool,
+}
+
+impl<'a> Cascade<'a> {
+    fn new(
+        config: &'a PolicyConfig,
+        index: &'a ActionIndex,
+        request: &'a AiRequest,
+        context: &'a SecurityContext,
+        effective_risk: u8,
+        pre_approved: bool,
+        timestamp_ms: u64,
+    ) -> Self {
+        let retired = config.sunset_passed(&request.action, timestamp_ms);
+        let category = if retired {
+            ActionCategory::Unlisted
+        } else {
+            index.category(&request.action)
+        };
+        let is_system = category == ActionCategory::System;
+        let role_shortfall = (is_system && !pre_approved)
+            .then(|| config.system_min_role(&request.action))
+            .filter(|min| !config.role_at_least(&context.user_role, min));
+        Cascade {
+            config,
+            index,
+            request,
+            context,
+            effective_risk,
+            pre_approved,
+            timestamp_ms,
+            retired,
+            category,
+            is_system,
+            is_write: category == ActionCategory::Write || is_system,
+            mfa_required: config.requires_mfa(&request.action, is_system),
+            role_shortfall,
+            role_recognized: config.role_rank(&context.user_role).is_some(),
+        }
+    }
+
+    fn approve(&self) -> PolicyDecision {
+        approve(
+            self.config,
+            self.request,
+            self.context,
+            self.role_recognized,
+            self.is_write,
+            self.is_system,
+            self.timestamp_ms,
+        )
+    }
+}
+
+/// One stage of the built-in cascade. `check` returns the decision that
+/// ends the cascade, or `None` to fall through to the next stage.
+/// `stage` and `rules` serve `PolicyEngine` and rule timings.
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+pub(crate) struct BuiltinRule {
+    /// Banner label, as in `ruleset_graph` and rule timings
+    pub(crate) stage: &'static str,
+    /// Applied rules the stage can decide with
+    pub(crate) rules: &'static [&'static str],
+    check: fn(&Cascade) -> Option<PolicyDecision>,
+}
+
+/// Leading stages no custom rule can run ahead of: the panic switch, the
+/// integrity check guarding the blocklist, and the hard block itself
+#[cfg(feature = "std")]
+pub(crate) const PINNED_RULES: usize = 3;
+
+/// The built-in rules in evaluation order — first decision wins. The
+/// final stage always decides, so the cascade never falls off the end.
+pub(crate) const BUILTIN_RULES: &[BuiltinRule] = &[
+    BuiltinRule { stage: "PANIC MODE", rules: &["PANIC_MODE"], check: panic_mode },
+    BuiltinRule { stage: "RULE 0", rules: &["INTEGRITY_FAILURE"], check: blocklist_integrity },
+    BuiltinRule { stage: "RULE 1", rules: &["HARD_BLOCK"], check: hard_block },
+    BuiltinRule { stage: "RULE 1-SANE", rules: &["CONTEXT_IMPLAUSIBLE"], check: implausible },
+    BuiltinRule { stage: "RULE 1-FREEZE", rules: &["MAINTENANCE_FREEZE"], check: freeze },
+    BuiltinRule { stage: "RULE 1a", rules: &["RESOURCE_DENYLISTED"], check: resource_denied },
+    BuiltinRule { stage: "RULE 1a-MOD", rules: &["DEPRECATED_MODULE"], check: deprecated_module },
+    BuiltinRule { stage: "RULE 1b", rules: &["DISALLOWED_METADATA"], check: metadata_keys },
+    BuiltinRule {
+        stage: "RULE 1c",
+        rules: &["MISSING_ORG_CONTEXT", "MISSING_USER_CONTEXT"],
+        check: identity_context,
+    },
+    BuiltinRule { stage: "RULE 1c-META", rules: &["METADATA_REQUIRED"], check: metadata_present },
+    BuiltinRule { stage: "RULE 1c-ARGS", rules: &["INVALID_ARGUMENT"], check: argument_schema },
+    BuiltinRule { stage: "RULE 1c-TRACE", rules: &["MISSING_TRACE_ID"], check: traceable },
+    BuiltinRule { stage: "RULE 1d", rules: &["TRUSTED_NETWORK_REQUIRED"], check: network },
+    BuiltinRule { stage: "RULE 1e", rules: &["CROSS_USER_WRITE"], check: user_scoped },
+    BuiltinRule { stage: "RULE 2", rules: &["RISK_SCORE_EXCEEDED"], check: risk_ceiling },
+    BuiltinRule { stage: "RULE 2a", rules: &["RISK_UNDERREPORTED"], check: risk_floor },
+    BuiltinRule { stage: "RULE 3", rules: &["ACCOUNT_LOCKOUT"], check: lockout },
+    BuiltinRule { stage: "RULE 3b", rules: &["ELEVATED_THREAT"], check: threat_signal },
+    BuiltinRule {
+        stage: "RULE 3c",
+        rules: &["AUTH_REQUIRED_FOR_READ"],
+        check: authenticated_read,
+    },
+    BuiltinRule { stage: "FAST PATH", rules: &["WHITELIST_APPROVED"], check: read_fast_path },
+    BuiltinRule { stage: "RULE 3a", rules: &["UNRECOGNIZED_ROLE"], check: unrecognized_role },
+    BuiltinRule {
+        stage: "PRE-FLIGHT",
+        rules: &["AUTH_REQUIREMENTS_NOT_MET"],
+        check: auth_requirements,
+    },
+    BuiltinRule { stage: "RULE 4", rules: &["SESSION_EXPIRED"], check: session_age },
+    BuiltinRule { stage: "RULE 4a", rules: &["TOO_MANY_SESSIONS"], check: concurrent_sessions },
+    BuiltinRule { stage: "RULE 5", rules: &["MFA_REQUIRED"], check: mfa },
+    BuiltinRule { stage: "RULE 5a", rules: &["MFA_REAUTH_REQUIRED"], check: mfa_recency },
+    BuiltinRule { stage: "RULE 5b", rules: &["SENSITIVE_TARGET"], check: sensitive_target },
+    BuiltinRule { stage: "RULE 6", rules: &["INSUFFICIENT_ROLE"], check: system_role },
+    BuiltinRule { stage: "RULE 6a", rules: &["NEW_ACCOUNT_RESTRICTION"], check: new_account },
+    BuiltinRule { stage: "RULE 6b", rules: &["DUAL_CONTROL_REQUIRED"], check: dual_control },
+    BuiltinRule { stage: "RULE 7", rules: &["NOT_IN_WHITELIST"], check: whitelist },
+    BuiltinRule {
+        stage: "RULE 8",
+        rules: &["METADATA_CONDITION_FAILED"],
+        check: metadata_condition,
+    },
+    BuiltinRule {
+        stage: "RULE 9",
+        rules: &["ELEVATION_REQUESTED", "ELEVATION_DENIED"],
+        check: elevation,
+    },
+    BuiltinRule {
+        stage: "PERMITTED",
+        rules: &["WHITELIST_APPROVED"],
+        check: |cascade| Some(cascade.approve()),
+    },
+];
+
+/// Index in `BUILTIN_RULES` of the stage that denied with `applied_rule`,
+/// or `None` for denials from outside the cascade
+#[cfg(feature = "std")]
+pub(crate) fn denying_stage(applied_rule: &str) -> Option<usize> {
+    BUILTIN_RULES.iter().position(|rule| rule.rules.contains(&applied_rule))
+}
+
+/// The built-in rules, run in order until one decides
+fn evaluate_rules(
+    config: &PolicyConfig,
+    index: &ActionIndex,
+    request: &AiRequest,
+    context: &SecurityContext,
+    effective_risk: u8,
+    pre_approved: bool,
+    timestamp_ms: u64,
+) -> PolicyDecision {
+    let cascade =
+        Cascade::new(config, index, request, context, effective_risk, pre_approved, timestamp_ms);
+    for rule in BUILTIN_RULES {
+        trace_rule!(rule.stage);
+        if let Some(decision) = (rule.check)(&cascade) {
+            return decision;
+        }
+    }
+    unreachable!("the PERMITTED stage always decides")
+}
+
+/// PANIC MODE: incident switch — denies everything, reads included
+fn panic_mode(c: &Cascade) -> Option<PolicyDecision> {
+    c.config.panic_mode.then(|| PolicyDecision {
+        permitted: false,
+        applied_rule: "PANIC_MODE".to_string(),
+        reason: "Panic mode is active: every action is denied until an operator clears it."
+            .to_string(),
+        iso_control: "A.16.1".to_string(),
+        timestamp_ms: c.timestamp_ms,
+        audit_required: true,
+        ..Default::default()
+    })
+}
+
+/// RULE 0: Blocklist integrity — a tampered or corrupted blocklist fails
+/// closed for everything except reads
+fn blocklist_integrity(c: &Cascade) -> Option<PolicyDecision> {
+    if c.category == ActionCategory::Read {
+        return None;
+    }
+    let reason = c.config.verify_blocklist().err()?;
+    Some(PolicyDecision {
+        permitted: false,
+        applied_rule: "INTEGRITY_FAILURE".to_string(),
+        reason,
+        iso_control: "A.12.1.2".to_string(),
+        timestamp_ms: c.timestamp_ms,
+        audit_required: true,
+        ..Default::default()
+    })
+}
+
+/// RULE 1: Hard block — always denied actions
+fn hard_block(c: &Cascade) -> Option<PolicyDecision> {
+    (c.category == ActionCategory::Blocked).then(|| PolicyDecision {
+        permitted: false,
+        applied_rule: "HARD_BLOCK".to_string(),
+        reason: format!(
+            "Action '{}' is permanently blocked. AI cannot modify security-critical resources.",
+            c.request.action
+        ),
+        iso_control: "A.9.4.1".to_string(),
+        timestamp_ms: c.timestamp_ms,
+        audit_required: true,
+        ..Default::default()
+    })
+}
+
+/// RULE 1-SANE: Implausible context values point at a broken client,
+/// not a stale session or a real attack, and fail closed
+fn implausible(c: &Cascade) -> Option<PolicyDecision> {
+    let implausible = c.config.implausible_context(c.context);
+    (!implausible.is_empty()).then(|| PolicyDecision {
+        permitted: false,
+        applied_rule: "CONTEXT_IMPLAUSIBLE".to_string(),
+        reason: format!(
+            "Security context values are implausible ({}). \
+             The client's instrumentation may be broken.",
+            implausible.join(", ")
+        ),
+        iso_control: "A.8.16".to_string(),
+        timestamp_ms: c.timestamp_ms,
+        audit_required: true,
+        ..Default::default()
+    })
+}
+
+/// RULE 1-FREEZE: Maintenance freeze — only reads get through
+fn freeze(c: &Cascade) -> Option<PolicyDecision> {
+    (c.config.freeze_writes && c.category != ActionCategory::Read).then(|| PolicyDecision {
+        permitted: false,
+        applied_rule: "MAINTENANCE_FREEZE".to_string(),
+        reason: format!(
+            "Action '{}' denied: a maintenance freeze is in effect. Only reads are allowed.",
+            c.request.action
+        ),
+        iso_control: "A.12.1.2".to_string(),
+        timestamp_ms: c.timestamp_ms,
+        audit_required: false,
+        ..Default::default()
+    })
+}
+
+/// RULE 1a: Resource deny patterns — near-top priority
+fn resource_denied(c: &Cascade) -> Option<PolicyDecision> {
+    let pattern = c
+        .config
+        .resource_deny_patterns
+        .iter()
+        .find(|p| glob_match(p, &c.request.target_resource))?;
+    Some(PolicyDecision {
+        permitted: false,
+        applied_rule: "RESOURCE_DENYLISTED".to_string(),
+        reason: format!(
+            "Target resource '{}' matches deny pattern '{}'.",
+            c.request.target_resource, pattern
+        ),
+        iso_control: "A.9.4.1".to_string(),
+        timestamp_ms: c.timestamp_ms,
+        audit_required: true,
+        ..Default::default()
+    })
+}
+
+/// RULE 1a-MOD: Deprecated module builds — versioned modules only
+fn deprecated_module(c: &Cascade) -> Option<PolicyDecision> {
+    let (module, version) = module_version(&c.request.requesting_module)?;
+    let pattern = c
+        .config
+        .deprecated_module_versions
+        .iter()
+        .find(|p| glob_match(p, &c.request.requesting_module))?;
+    Some(PolicyDecision {
+        permitted: false,
+        applied_rule: "DEPRECATED_MODULE".to_string(),
+        reason: format!(
+            "Module '{}' version {} matches deprecated pattern '{}'.",
+            module, version, pattern
+        ),
+        iso_control: "A.12.6.1".to_string(),
+        timestamp_ms: c.timestamp_ms,
+        audit_required: true,
+        ..Default::default()
+    })
+}
+
+/// RULE 1b: Metadata key allowlist (only when configured)
+fn metadata_keys(c: &Cascade) -> Option<PolicyDecision> {
+    let allowed = c.config.allowed_metadata_keys.as_ref()?;
+    let reason = check_metadata_keys(c.request.metadata.as_ref(), allowed).err()?;
+    Some(PolicyDecision {
+        permitted: false,
+        applied_rule: "DISALLOWED_METADATA".to_string(),
+        reason,
+        iso_control: "A.8.16".to_string(),
+        timestamp_ms: c.timestamp_ms,
+        audit_required: true,
+        ..Default::default()
+    })
+}
+
+/// RULE 1c: Required identity context — empty IDs count as absent
+fn identity_context(c: &Cascade) -> Option<PolicyDecision> {
+    let has_id = |id: &Option<String>| id.as_deref().is_some_and(|id| !id.is_empty());
+    let scoped = [
+        (
+            "MISSING_ORG_CONTEXT",
+            "organisation_id",
+            &c.config.actions_requiring_org,
+            &c.request.organisation_id,
+        ),
+        (
+            "MISSING_USER_CONTEXT",
+            "user_id",
+            &c.config.actions_requiring_user,
+            &c.request.user_id,
+        ),
+    ];
+    let (rule, field, _, _) = scoped
+        .into_iter()
+        .find(|(_, _, actions, id)| actions.contains(&c.request.action) && !has_id(id))?;
+    Some(PolicyDecision {
+        permitted: false,
+        applied_rule: rule.to_string(),
+        reason: format!("Action '{}' requires a non-empty {}.", c.request.action, field),
+        iso_control: "A.9.4.1".to_string(),
+        timestamp_ms: c.timestamp_ms,
+        audit_required: false,
+        ..Default::default()
+    })
+}
+
+/// RULE 1c-META: Required metadata — JSON null counts as absent
+fn metadata_present(c: &Cascade) -> Option<PolicyDecision> {
+    let absent = c.request.metadata.as_ref().is_none_or(serde_json::Value::is_null);
+    (absent && c.config.actions_requiring_metadata.contains(&c.request.action)).then(|| {
+        PolicyDecision {
+            permitted: false,
+            applied_rule: "METADATA_REQUIRED".to_string(),
+            reason: format!("Action '{}' requires non-null metadata.", c.request.action),
+            iso_control: "A.8.16".to_string(),
+            timestamp_ms: c.timestamp_ms,
+            audit_required: false,
+            ..Default::default()
+        }
+    })
+}
+
+/// RULE 1c-ARGS: Argument schema — declared arguments, in name order
+fn argument_schema(c: &Cascade) -> Option<PolicyDecision> {
+    let arguments = c.config.action_arguments.get(&c.request.action)?;
+    arguments.iter().find_map(|(name, argument)| {
+        let why = argument.check(name, c.request.metadata.as_ref()).err()?;
+        Some(PolicyDecision {
+            permitted: false,
+            applied_rule: "INVALID_ARGUMENT".to_string(),
+            reason: format!("Action '{}' rejected: {}", c.request.action, why),
+            iso_control: "A.8.16".to_string(),
+            timestamp_ms: c.timestamp_ms,
+            audit_required: false,
+            invalid_argument: Some(name.clone()),
+            ..Default::default()
+        })
+    })
+}
+
+/// RULE 1c-TRACE: Writes and system actions must be traceable
+fn traceable(c: &Cascade) -> Option<PolicyDecision> {
+    (c.config.require_trace_id && c.is_write && trace_id(c.request).is_none()).then(|| {
+        PolicyDecision {
+            permitted: false,
+            applied_rule: "MISSING_TRACE_ID".to_string(),
+            reason: format!(
+                "Action '{}' requires a non-empty metadata.trace_id.",
+                c.request.action
+            ),
+            iso_control: "A.12.4.1".to_string(),
+            timestamp_ms: c.timestamp_ms,
+            audit_required: false,
+            ..Default::default()
+        }
+    })
+}
+
+/// RULE 1d: Network-restricted actions — independent of risk
+fn network(c: &Cascade) -> Option<PolicyDecision> {
+    let restricted = c.config.trusted_network_required.contains(&c.request.action);
+    (restricted && !c.context.trusted_network).then(|| PolicyDecision {
+        permitted: false,
+        applied_rule: "TRUSTED_NETWORK_REQUIRED".to_string(),
+        reason: format!("Action '{}' may only run from a trusted network.", c.request.action),
+        iso_control: "A.13.1.1".to_string(),
+        timestamp_ms: c.timestamp_ms,
+        audit_required: true,
+        ..Default::default()
+    })
+}
+
+/// RULE 1e: User-scoped actions — only on the caller's own data.
+/// Fails closed when the context names no authenticated subject.
+fn user_scoped(c: &Cascade) -> Option<PolicyDecision> {
+    if !c.config.user_scoped_actions.contains(&c.request.action)
+        || c.config.cross_user_exempt(&c.context.user_role)
+    {
+        return None;
+    }
+    let reason = match (&c.context.authenticated_user_id, &c.request.user_id) {
+        (None, _) => format!(
+            "Action '{}' requires an authenticated_user_id in the context.",
+            c.request.action
+        ),
+        (Some(subject), target) if target.as_ref() != Some(subject) => format!(
+            "Action '{}' may only target the authenticated user '{}'.",
+            c.request.action, subject
+        ),
+        _ => return None,
+    };
+    Some(PolicyDecision {
+        permitted: false,
+        applied_rule: "CROSS_USER_WRITE".to_string(),
+        reason,
+        iso_control: "A.9.4.1".to_string(),
+        timestamp_ms: c.timestamp_ms,
+        audit_required: true,
+        ..Default::default()
+    })
+}
+
+/// RULE 2: Risk score threshold
+fn risk_ceiling(c: &Cascade) -> Option<PolicyDecision> {
+    let ceiling = c.config.risk_ceiling(&c.context.user_role);
+    if c.effective_risk <= ceiling || c.pre_approved {
+        return None;
+    }
+    let scope = if ceiling == c.config.max_risk_score {
+        String::new()
+    } else {
+        format!(" for role '{}'", c.context.user_role)
+    };
+    Some(PolicyDecision {
+        permitted: false,
+        applied_rule: "RISK_SCORE_EXCEEDED".to_string(),
+        reason: format!(
+            "Risk score {} exceeds maximum allowed {}{}. Human review required.",
+            c.effective_risk, ceiling, scope
+        ),
+        iso_control: "A.8.16".to_string(),
+        timestamp_ms: c.timestamp_ms,
+        audit_required: true,
+        ..Default::default()
+    })
+}
+
+/// RULE 2a: Risk floor — sensitive actions reported as low-risk
+fn risk_floor(c: &Cascade) -> Option<PolicyDecision> {
+    let floor = *c.config.min_risk_for_action.get(&c.request.action)?;
+    (c.request.risk_score < floor).then(|| PolicyDecision {
+        permitted: false,
+        applied_rule: "RISK_UNDERREPORTED".to_string(),
+        reason: format!(
+            "Risk score {} is below the minimum {} declared for action '{}'. The caller may be under-reporting risk.",
+            c.request.risk_score, floor, c.request.action
+        ),
+        iso_control: "A.8.16".to_string(),
+        timestamp_ms: c.timestamp_ms,
+        audit_required: true,
+        ..Default::default()
+    })
+}
+
+/// RULE 3: Account lockout check (configured reads stay available so
+/// the lockout can be explained)
+fn lockout(c: &Cascade) -> Option<PolicyDecision> {
+    let locked = c.context.failed_attempts_last_hour >= c.config.max_failed_attempts;
+    (locked && !c.config.lockout_exempt_reads.contains(&c.request.action)).then(|| {
+        PolicyDecision {
+            permitted: false,
+            applied_rule: "ACCOUNT_LOCKOUT".to_string(),
+            reason: format!(
+                "Too many failed attempts ({}/{}). Account temporarily locked.",
+                c.context.failed_attempts_last_hour, c.config.max_failed_attempts
+            ),
+            iso_control: "A.9.4.3".to_string(),
+            timestamp_ms: c.timestamp_ms,
+            audit_required: true,
+            retry_after_ms: lockout_retry_after_ms(c.config, c.context),
+            ..Default::default()
+        }
+    })
+}
+
+/// RULE 3b: Threat signal — only ever adds restrictions. Above the write
+/// threshold write/system actions are denied; above the read band every
+/// action also needs MFA.
+fn threat_signal(c: &Cascade) -> Option<PolicyDecision> {
+    let threat = c.context.threat_score?;
+    if c.is_write && threat > c.config.threat_write_deny_above {
+        return Some(PolicyDecision {
+            permitted: false,
+            applied_rule: "ELEVATED_THREAT".to_string(),
+            reason: format!(
+                "Source threat score {} exceeds {}. Write and system actions are denied.",
+                threat, c.config.threat_write_deny_above
+            ),
+            iso_control: "A.13.1.1".to_string(),
+            timestamp_ms: c.timestamp_ms,
+            audit_required: true,
+            ..Default::default()
+        });
+    }
+    (threat > c.config.threat_mfa_required_above && !c.context.mfa_verified).then(|| {
+        PolicyDecision {
+            permitted: false,
+            applied_rule: "ELEVATED_THREAT".to_string(),
+            reason: format!(
+                "Source threat score {} exceeds {}. MFA verification is required.",
+                threat, c.config.threat_mfa_required_above
+            ),
+            iso_control: "A.13.1.1".to_string(),
+            timestamp_ms: c.timestamp_ms,
+            audit_required: true,
+            missing: vec!["MFA_REQUIRED".to_string()],
+            ..Default::default()
+        }
+    })
+}
+
+/// RULE 3c: Authenticated reads — sensitive reads need a sufficiently
+/// privileged role and (optionally) a fresh session
+fn authenticated_read(c: &Cascade) -> Option<PolicyDecision> {
+    let config = c.config;
+    let action = &c.request.action;
+    if c.category != ActionCategory::Read || !config.authenticated_reads.contains(action) {
+        return None;
+    }
+    let mut missing = Vec::new();
+    let min_rank = config.role_rank(&config.authenticated_read_min_role);
+    let rank = config.role_rank(&c.context.user_role);
+    if rank.is_none() || rank < min_rank {
+        missing.push("INSUFFICIENT_ROLE".to_string());
+    }
+    if config.authenticated_reads_check_session
+        && config.session_expired(c.context.session_age_seconds, false)
+    {
+        missing.push("SESSION_EXPIRED".to_string());
+    }
+    (!missing.is_empty()).then(|| PolicyDecision {
+        permitted: false,
+        applied_rule: "AUTH_REQUIRED_FOR_READ".to_string(),
+        reason: format!(
+            "Action '{}' reads sensitive data and requires role '{}' or higher with a valid session. Unmet: {}.",
+            action,
+            config.authenticated_read_min_role,
+            missing.join(", ")
+        ),
+        iso_control: "A.9.4.1".to_string(),
+        timestamp_ms: c.timestamp_ms,
+        audit_required: false,
+        missing,
+        ..Default::default()
+    })
+}
+
+/// FAST PATH: Rules 3a-6a only gate write/system actions, so a
+/// whitelisted read without a metadata condition or two-person rule is
+/// decided here
+fn read_fast_path(c: &Cascade) -> Option<PolicyDecision> {
+    let action = &c.request.action;
+    (c.index.read_fast_path
+        && c.category == ActionCategory::Read
+        && !c.config.metadata_conditions.contains_key(action)
+        && !c.config.dual_control_actions.contains(action)
+        && action != ELEVATION_ACTION
+        && !c.mfa_required)
+        .then(|| c.approve())
+}
+
+/// RULE 3a: Unrecognized role — surfaces role-config drift. Reads stay
+/// available so anonymous read flows keep working.
+fn unrecognized_role(c: &Cascade) -> Option<PolicyDecision> {
+    (c.is_write && !c.role_recognized).then(|| PolicyDecision {
+        permitted: false,
+        applied_rule: "UNRECOGNIZED_ROLE".to_string(),
+        reason: format!(
+            "Role '{}' is not a recognized role. Write and system actions are denied.",
+            c.context.user_role
+        ),
+        iso_control: "A.9.2.3".to_string(),
+        timestamp_ms: c.timestamp_ms,
+        audit_required: true,
+        ..Default::default()
+    })
+}
+
+/// PRE-FLIGHT: Consolidated authentication requirements. Reports every
+/// unmet requirement at once so the client can resolve them in a single
+/// round-trip. A lone deficiency falls through to its dedicated rule.
+fn auth_requirements(c: &Cascade) -> Option<PolicyDecision> {
+    if !c.config.consolidate_auth_requirements || !c.is_write {
+        return None;
+    }
+    let missing = auth_deficiencies(
+        c.config,
+        &c.request.action,
+        c.context,
+        c.is_write,
+        c.is_system,
+        c.mfa_required,
+        c.role_shortfall.is_some(),
+    );
+    (missing.len() > 1).then(|| PolicyDecision {
+        permitted: false,
+        applied_rule: "AUTH_REQUIREMENTS_NOT_MET".to_string(),
+        reason: format!("Multiple authentication requirements not met: {}.", missing.join(", ")),
+        iso_control: "A.9.4.2".to_string(),
+        timestamp_ms: c.timestamp_ms,
+        audit_required: false,
+        missing,
+        ..Default::default()
+    })
+}
+
+/// RULE 4: Session age check for sensitive operations
+fn session_age(c: &Cascade) -> Option<PolicyDecision> {
+    let expired = c.config.session_expired(c.context.session_age_seconds, c.is_system);
+    (c.is_write && expired).then(|| PolicyDecision {
+        permitted: false,
+        applied_rule: "SESSION_EXPIRED".to_string(),
+        reason: format!(
+            "Session age {}s exceeds {}s limit for {} operations. Re-authentication required.",
+            c.context.session_age_seconds,
+            c.config.session_age_limit(c.is_system),
+            if c.is_system { "system" } else { "write" }
+        ),
+        iso_control: "A.9.4.2".to_string(),
+        timestamp_ms: c.timestamp_ms,
+        audit_required: false,
+        ..Default::default()
+    })
+}
+
+/// RULE 4a: Concurrent sessions — possible sharing or hijacking
+fn concurrent_sessions(c: &Cascade) -> Option<PolicyDecision> {
+    let max = c.config.max_concurrent_sessions?;
+    let sessions = c.context.concurrent_sessions?;
+    (c.is_write && sessions > max).then(|| PolicyDecision {
+        permitted: false,
+        applied_rule: "TOO_MANY_SESSIONS".to_string(),
+        reason: format!(
+            "User has {} concurrent sessions; at most {} are allowed for {} operations.",
+            sessions,
+            max,
+            if c.is_system { "system" } else { "write" }
+        ),
+        iso_control: "A.9.4.2".to_string(),
+        timestamp_ms: c.timestamp_ms,
+        audit_required: true,
+        ..Default::default()
+    })
+}
+
+/// RULE 5: MFA required (system actions unless `mfa_required` is set)
+fn mfa(c: &Cascade) -> Option<PolicyDecision> {
+    (c.mfa_required && !c.context.mfa_verified).then(|| PolicyDecision {
+        permitted: false,
+        applied_rule: "MFA_REQUIRED".to_string(),
+        reason: if c.is_system {
+            "System-level actions require MFA verification.".to_string()
+        } else {
+            format!("Action '{}' requires MFA verification.", c.request.action)
+        },
+        iso_control: "A.9.4.2".to_string(),
+        timestamp_ms: c.timestamp_ms,
+        audit_required: false,
+        ..Default::default()
+    })
+}
+
+/// RULE 5a: MFA recency — verified, but too long ago for this action
+fn mfa_recency(c: &Cascade) -> Option<PolicyDecision> {
+    let stale = c.config.mfa_stale(&c.request.action, c.context.mfa_age_seconds);
+    (c.mfa_required && stale).then(|| PolicyDecision {
+        permitted: false,
+        applied_rule: "MFA_REAUTH_REQUIRED".to_string(),
+        reason: match c.context.mfa_age_seconds {
+            Some(age) => format!(
+                "MFA verified {}s ago is too old for action '{}'. Verify MFA again.",
+                age, c.request.action
+            ),
+            None => format!(
+                "Action '{}' requires a recent MFA verification of known age.",
+                c.request.action
+            ),
+        },
+        iso_control: "A.9.4.2".to_string(),
+        timestamp_ms: c.timestamp_ms,
+        audit_required: false,
+        ..Default::default()
+    })
+}
+
+/// RULE 5b: Sensitive targets — writes need MFA whatever the action
+fn sensitive_target(c: &Cascade) -> Option<PolicyDecision> {
+    let sensitive = c.config.sensitive_target(&c.request.target_resource);
+    (c.is_write && !c.context.mfa_verified && sensitive).then(|| PolicyDecision {
+        permitted: false,
+        applied_rule: "SENSITIVE_TARGET".to_string(),
+        reason: format!(
+            "Resource '{}' is sensitive. Writes to it require MFA verification.",
+            c.request.target_resource
+        ),
+        iso_control: "A.8.2.3".to_string(),
+        timestamp_ms: c.timestamp_ms,
+        audit_required: true,
+        ..Default::default()
+    })
+}
+
+/// RULE 6: Role-based action restrictions
+fn system_role(c: &Cascade) -> Option<PolicyDecision> {
+    let min_role = c.role_shortfall?;
+    Some(PolicyDecision {
+        permitted: false,
+        applied_rule: "INSUFFICIENT_ROLE".to_string(),
+        reason: format!(
+            "Role '{}' cannot perform system action '{}'. Requires '{}' or higher.",
+            c.context.user_role, c.request.action, min_role
+        ),
+        iso_control: "A.9.2.3".to_string(),
+        timestamp_ms: c.timestamp_ms,
+        audit_required: false,
+        ..Default::default()
+    })
+}
+
+/// RULE 6a: Brand-new accounts need MFA for any write/system action
+fn new_account(c: &Cascade) -> Option<PolicyDecision> {
+    let account_age = c.context.account_age_seconds?;
+    let young = account_age < c.config.new_account_age_seconds;
+    (c.is_write && young && !c.context.mfa_verified).then(|| PolicyDecision {
+        permitted: false,
+        applied_rule: "NEW_ACCOUNT_RESTRICTION".to_string(),
+        reason: format!(
+            "Account is {}s old (under {}s). MFA is required for write and system actions on new accounts.",
+            account_age, c.config.new_account_age_seconds
+        ),
+        iso_control: "A.9.4.2".to_string(),
+        timestamp_ms: c.timestamp_ms,
+        audit_required: true,
+        ..Default::default()
+    })
+}
+
+/// RULE 6b: Two-person rule — approval evidence is verified upstream;
+/// only distinctness and both parties' roles are checked here
+fn dual_control(c: &Cascade) -> Option<PolicyDecision> {
+    let (config, context) = (c.config, c.context);
+    if !config.dual_control_actions.contains(&c.request.action) {
+        return None;
+    }
+    let acting = context.authenticated_user_id.as_deref();
+    let approver = context.second_approver_id.as_deref();
+    let reason = if acting.is_none() {
+        "the context names no authenticated_user_id".to_string()
+    } else if !config.dual_control_eligible(Some(&context.user_role)) {
+        format!("role '{}' may not initiate it", context.user_role)
+    } else if approver.is_none() {
+        "no second approver is present".to_string()
+    } else if approver == acting {
+        "the second approver is the acting user".to_string()
+    } else if !config.dual_control_eligible(context.second_approver_role.as_deref()) {
+        format!("the second approver lacks role '{}' or higher", config.dual_control_min_role)
+    } else {
+        return None;
+    };
+    Some(PolicyDecision {
+        permitted: false,
+        applied_rule: "DUAL_CONTROL_REQUIRED".to_string(),
+        reason: format!(
+            "Action '{}' requires two distinct '{}'-or-higher approvers, but {}.",
+            c.request.action, config.dual_control_min_role, reason
+        ),
+        iso_control: "A.6.1.2".to_string(),
+        timestamp_ms: c.timestamp_ms,
+        audit_required: true,
+        ..Default::default()
+    })
+}
+
+/// RULE 7: Whitelist check — default deny
+fn whitelist(c: &Cascade) -> Option<PolicyDecision> {
+    if c.category == ActionCategory::Read || c.is_write {
+        return None;
+    }
+    let reason = if c.retired {
+        format!(
+            "Action '{}' was retired from the whitelist at {} ms. Default deny.",
+            c.request.action,
+            c.config.sunset_ms(&c.request.action).unwrap_or_default()
+        )
+    } else {
+        format!(
+            "Action '{}' is not in the permitted actions whitelist. Default deny.",
+            c.request.action
+        )
+    };
+    Some(PolicyDecision {
+        permitted: false,
+        applied_rule: "NOT_IN_WHITELIST".to_string(),
+        reason,
+        iso_control: "A.9.4.1".to_string(),
+        timestamp_ms: c.timestamp_ms,
+        audit_required: true,
+        ..Default::default()
+    })
+}
+
+/// RULE 8: Metadata conditions — single numeric ceiling per action
+fn metadata_condition(c: &Cascade) -> Option<PolicyDecision> {
+    let condition = c.config.metadata_conditions.get(&c.request.action)?;
+    let reason = condition.check(c.request.metadata.as_ref()).err()?;
+    Some(PolicyDecision {
+        permitted: false,
+        applied_rule: "METADATA_CONDITION_FAILED".to_string(),
+        reason: format!("Action '{}' denied: {}", c.request.action, reason),
+        iso_control: "A.9.4.1".to_string(),
+        timestamp_ms: c.timestamp_ms,
+        audit_required: false,
+        ..Default::default()
+    })
+}
+
+/// RULE 9: Elevation requests — recorded for human approval
+fn elevation(c: &Cascade) -> Option<PolicyDecision> {
+    (c.request.action == ELEVATION_ACTION)
+        .then(|| elevation_request(c.config, c.request, c.context, c.timestamp_ms))
+}
+
+/// Record (never grant) a request to elevate to `metadata.target_role`.
+/// The target must be a known role above the caller's and no higher
+/// than `max_elevation_role`.
+fn elevation_request(
+    config: &PolicyConfig,
+    request: &AiRequest,
+    context: &SecurityContext,
+    timestamp_ms: u64,
+) -> PolicyDecision {
+    let target = request
+        .metadata
+        .as_ref()
+        .and_then(|m| m.get("target_role"))
+        .and_then(|r| r.as_str());
+    let denial = |reason: String| PolicyDecision {
+        permitted: false,
+        applied_rule: "ELEVATION_DENIED".to_string(),
+        reason,
+        iso_control: "A.9.2.2".to_string(),
+        timestamp_ms,
+        audit_required: true,
+        ..Default::default()
+    };
+    let Some(target) = target else {
+        return denial("Elevation requests must name a metadata 'target_role'.".to_string());
+    };
+    let Some(rank) = config.role_rank(target) else {
+        return denial(format!("Requested role '{}' is not a recognized role.", target));
+    };
+    if config.role_rank(&config.max_elevation_role).is_some_and(|max| rank > max) {
+        return denial(format!(
+            "Requested role '{}' is above the maximum elevation '{}'.",
+            target, config.max_elevation_role
+        ));
+    }
+    if config.role_rank(&context.user_role).is_some_and(|current| rank <= current) {
+        return denial(format!(
+            "Role '{}' already meets or exceeds requested role '{}'.",
+            context.user_role, target
+        ));
+    }
+    PolicyDecision {
+        permitted: true,
+        applied_rule: "ELEVATION_REQUESTED".to_string(),
+        reason: format!(
+            "Elevation from '{}' to '{}' recorded for human approval. No role has changed.",
+            context.user_role, target
+        ),
+        iso_control: "A.9.2.2".to_string(),
+        timestamp_ms,
+        audit_required: true,
+        requires_human_review: true,
+        requested_role: Some(target.to_string()),
+        ..Default::default()
+    }
+}
+
+/// The WHITELIST_APPROVED decision for an action that cleared every rule
+fn approve(
+    config: &PolicyConfig,
+    request: &AiRequest,
+    context: &SecurityContext,
+    role_recognized: bool,
+    is_write: bool,
+    is_system: bool,
+    timestamp_ms: u64,
+) -> PolicyDecision {
+    let mut reason = format!(
+        "Action '{}' approved. Risk score: {}/{}.",
+        request.action,
+        request.risk_score,
+        config.risk_ceiling(&context.user_role)
+    );
+    if !role_recognized {
+        reason.push_str(&format!(" Note: role '{}' is not recognized.", context.user_role));
+    }
+    let session_checked = is_write
+        || (config.authenticated_reads_check_session
+            && config.authenticated_reads.contains(&request.action));
+    if session_checked && config.session_in_grace(context.session_age_seconds, is_system) {
+        reason.push_str(&format!(
+            " Note: session age {}s is past the {}s limit but within the {}s clock-skew tolerance.",
+            context.session_age_seconds,
+            config.session_age_limit(is_system),
+            config.session_age_tolerance_seconds
+        ));
+    }
+    let mut audit_required = request.risk_score > 30;   // Audit medium-risk actions
+    if !audit_required && audit_sampled(config, request, timestamp_ms) {
+        audit_required = true;
+        reason.push_str(&format!(
+            " Sampled for audit ({}% of low-risk permits).",
+            config.audit_sample_percent
+        ));
+    }
+    let margin = permit_margin(config, request, context, session_checked, is_system);
+    PolicyDecision {
+        permitted: true,
+        applied_rule: "WHITELIST_APPROVED".to_string(),
+        reason,
+        iso_control: "A.9.4.1".to_string(),
+        timestamp_ms,
+        audit_required,
+        margin: Some(margin),
+        ..Default::default()
+    }
+}
+
+/// Minimum headroom, in percent of each threshold, across the gates a
+/// permit passed: risk score, failed attempts, and session age when the
+/// action was session-checked. Negative inside the session grace band.
+fn permit_margin(
+    config: &PolicyConfig,
+    request: &AiRequest,
+    context: &SecurityContext,
+    session_checked: bool,
+    is_system: bool,
+) -> i32 {
+    let headroom = |limit: u64, value: u64| -> i32 {
+        if limit == 0 {
+            return 0;
+        }
+        let percent = (limit as i128 - value as i128) * 100 / limit as i128;
+        percent.clamp(i32::MIN as i128, i32::MAX as i128) as i32
+    };
+    let risk_ceiling = config.risk_ceiling(&context.user_role);
+    let mut margin = headroom(risk_ceiling.into(), request.risk_score.into()).min(headroom(
+        config.max_failed_attempts.into(),
+        context.failed_attempts_last_hour.into(),
+    ));
+    if session_checked {
+        let limit = config.session_age_limit(is_system);
+        margin = margin.min(headroom(limit, context.session_age_seconds));
+    }
+    margin
+}
+
+/// Deterministic audit sampling: the same `(user_id, action, timestamp_ms)`
+/// always lands in or out of the sample, so audits are reproducible
+fn audit_sampled(config: &PolicyConfig, request: &AiRequest, timestamp_ms: u64) -> bool {
+    if config.audit_sample_percent == 0 {
+        return false;
+    }
+    let input = format!(
+        "{}\n{}\n{}",
+        request.user_id.as_deref().unwrap_or(""),
+        request.action,
+        timestamp_ms
+    );
+    let digest = Sha256::digest(input.as_bytes());
+    let mut head = [0u8; 8];
+    head.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(head) % 100 < u64::from(config.audit_sample_percent)
+}
+
+/// Every gate a permitted action went through, in cascade order, as
+/// `"<gate>: cleared"` or `"<gate>: n/a"` when it does not apply to
+/// this action (e.g. MFA for a read)
+fn cleared_gates(
+    config: &PolicyConfig,
+    index: &ActionIndex,
+    request: &AiRequest,
+    context: &SecurityContext,
+) -> Vec<String> {
+    let action = &config.canonical_action(&request.action).to_string();
+    let category = index.category(action);
+    let is_system = category == ActionCategory::System;
+    let is_write = category == ActionCategory::Write || is_system;
+    let gates = [
+        ("hard_block", true),
+        (
+            "context_plausible",
+            config.max_plausible_session_age_seconds.is_some()
+                || config.max_plausible_failed_attempts.is_some(),
+        ),
+        ("resource_denylist", !config.resource_deny_patterns.is_empty()),
+        (
+            "module_version",
+            !config.deprecated_module_versions.is_empty()
+                && module_version(&request.requesting_module).is_some(),
+        ),
+        ("metadata_allowlist", config.allowed_metadata_keys.is_some()),
+        ("org_context", config.actions_requiring_org.contains(action)),
+        ("user_context", config.actions_requiring_user.contains(action)),
+        ("arguments", config.action_arguments.contains_key(action)),
+        ("trace_id", config.require_trace_id && is_write),
+        ("trusted_network", config.trusted_network_required.contains(action)),
+        ("risk_score", true),
+        ("risk_floor", config.min_risk_for_action.contains_key(action)),
+        ("lockout", true),
+        ("threat", context.threat_score.is_some()),
+        ("authenticated_read", config.authenticated_reads.contains(action)),
+        ("recognized_role", is_write),
+        ("session_age", is_write),
+        (
+            "concurrent_sessions",
+            is_write
+                && config.max_concurrent_sessions.is_some()
+                && context.concurrent_sessions.is_some(),
+        ),
+        ("mfa", config.requires_mfa(action, is_system)),
+        (
+            "mfa_recency",
+            config.requires_mfa(action, is_system)
+                && (config.max_mfa_age_seconds.is_some()
+                    || config.max_mfa_age_for_action.contains_key(action)),
+        ),
+        (
+            "sensitive_target",
+            is_write && config.sensitive_target(&request.target_resource),
+        ),
+        ("role", is_system),
+        ("new_account", is_write && context.account_age_seconds.is_some()),
+        ("dual_control", config.dual_control_actions.contains(action)),
+        ("whitelist", true),
+        ("metadata_condition", config.metadata_conditions.contains_key(action)),
+    ];
+    gates
+        .iter()
+        .map(|(gate, applies)| {
+            format!("{}: {}", gate, if *applies { "cleared" } else { "n/a" })
+        })
+        .collect()
+}
+
+/// Split `name@version` into its parts; `None` when there is no
+/// non-empty version suffix
+fn module_version(requesting_module: &str) -> Option<(&str, &str)> {
+    requesting_module
+        .rsplit_once('@')
+        .filter(|(module, version)| !module.is_empty() && !version.is_empty())
+}
+
+/// Anchored glob match: `*` matches any run of characters (including
+/// none); everything else is literal. The whole input must match, so
+/// `vault` does not match `my-vaulted-cache`.
+fn glob_match(pattern: &str, input: &str) -> bool {
+    let pattern = pattern.as_bytes();
+    let input = input.as_bytes();
+    let (mut p, mut i) = (0, 0);
+    // Position of the last `*` seen and the input index it was tried at
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while i < input.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            backtrack = Some((p, i));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == input[i] {
+            p += 1;
+            i += 1;
+        } else if let Some((star, tried)) = backtrack {
+            // Let the last `*` swallow one more character
+            p = star + 1;
+            i = tried + 1;
+            backtrack = Some((star, tried + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// Metadata must be absent, null, or an object whose keys are all allowlisted
+fn check_metadata_keys(
+    metadata: Option<&serde_json::Value>,
+    allowed: &[String],
+) -> Result<(), String> {
+    match metadata {
+        None | Some(serde_json::Value::Null) => Ok(()),
+        Some(serde_json::Value::Object(map)) => {
+            let mut disallowed: Vec<&str> = map
+                .keys()
+                .filter(|k| !allowed.contains(k))
+                .map(|k| k.as_str())
+                .collect();
+            if disallowed.is_empty() {
+                return Ok(());
+            }
+            disallowed.sort_unstable();
+            Err(format!(
+                "Metadata keys not permitted: {}.",
+                disallowed.join(", ")
+            ))
+        }
+        Some(_) => Err("Metadata must be a JSON object.".to_string()),
+    }
+}
+
+/// Failures fall out of the one-hour window oldest first. When dropping
+/// the oldest alone ends the lockout, that is the wait; otherwise the
+/// later failures' times are unknown, so the whole window is the bound.
+fn lockout_retry_after_ms(config: &PolicyConfig, context: &SecurityContext) -> Option<u64> {
+    const WINDOW_SECONDS: u64 = 3600;
+    let oldest_age = context.oldest_failure_age_seconds?;
+    let excess = context.failed_attempts_last_hour - config.max_failed_attempts;
+    let wait_seconds = if excess == 0 {
+        WINDOW_SECONDS.saturating_sub(oldest_age)
+    } else {
+        WINDOW_SECONDS
+    };
+    Some(wait_seconds * 1000)
+}
+
+/// Collect the authentication rules (RULES 4-6) this context fails,
+/// in cascade order
+fn auth_deficiencies(
+    config: &PolicyConfig,
+    action: &str,
+    context: &SecurityContext,
+    is_write: bool,
+    is_system: bool,
+    mfa_required: bool,
+    role_insufficient: bool,
+) -> Vec<String> {
+    let mut missing = Vec::new();
+    if is_write && config.session_expired(context.session_age_seconds, is_system) {
+        missing.push("SESSION_EXPIRED".to_string());
+    }
+    if mfa_required && !context.mfa_verified {
+        missing.push("MFA_REQUIRED".to_string());
+    } else if mfa_required && config.mfa_stale(action, context.mfa_age_seconds) {
+        missing.push("MFA_REAUTH_REQUIRED".to_string());
+    }
+    if role_insufficient {
+        missing.push("INSUFFICIENT_ROLE".to_string());
+    }
+    missing
+}
+
+// ============================================================
+// CANONICAL JSON
+// Stable serialization for anything hashed or diffed: object keys
+// sorted, no insignificant whitespace, integral floats written as
+// integers. Equal values always yield identical bytes.
+// ============================================================
+
+/// Serialize `value` to canonical JSON
+pub fn to_canonical_json<T: Serialize>(value: &T) -> String {
+    let mut out = String::new();
+    match serde_json::to_value(value) {
+        Ok(v) => write_canonical(&v, &mut out),
+        Err(_) => out.push_str("null"),
+    }
+    out
+}
+
+fn write_canonical(value: &serde_json::Value, out: &mut String) {
+    use serde_json::Value;
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => out.push_str(&canonical_number(n)),
+        Value::String(s) => out.push_str(&serde_json::to_string(s).unwrap_or_default()),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+            out.push('{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::to_string(key).unwrap_or_default());
+                out.push(':');
+                write_canonical(item, out);
+            }
+            out.push('}');
+        }
+    }
+}
+
+/// `1.0` and `1` are the same number: integral floats are written as integers
+fn canonical_number(n: &serde_json::Number) -> String {
+    if n.is_f64() {
+        if let Some(f) = n.as_f64() {
+            // `fract` needs std; the round-trip through i64 is the core equivalent
+            if f.abs() < 9_007_199_254_740_992.0 && f == (f as i64) as f64 {
+                return format!("{}", f as i64);
+            }
+        }
+    }
+    n.to_string()
+}
+
+// ============================================================
+// COMPLIANCE MAPPING — one rule, many frameworks
+// ============================================================
+
+/// Every rule name the engine can emit
+pub(crate) const APPLIED_RULES: &[&str] = &[
+    "REQUEST_PARSE_ERROR",
+    "CONTEXT_PARSE_ERROR",
+    "INPUT_TOO_LARGE",
+    "UNKNOWN_FIELD",
+    "PANIC_MODE",
+    "INTEGRITY_FAILURE",
+    "HARD_BLOCK",
+    "CONTEXT_IMPLAUSIBLE",
+    "MAINTENANCE_FREEZE",
+    "RESOURCE_DENYLISTED",
+    "DEPRECATED_MODULE",
+    "DISALLOWED_METADATA",
+    "MISSING_ORG_CONTEXT",
+    "MISSING_USER_CONTEXT",
+    "METADATA_REQUIRED",
+    "INVALID_ARGUMENT",
+    "MISSING_TRACE_ID",
+    "TRUSTED_NETWORK_REQUIRED",
+    "CROSS_USER_WRITE",
+    "RISK_SCORE_EXCEEDED",
+    "RISK_UNDERREPORTED",
+    "ACCOUNT_LOCKOUT",
+    "ELEVATED_THREAT",
+    "AUTH_REQUIRED_FOR_READ",
+    "UNRECOGNIZED_ROLE",
+    "AUTH_REQUIREMENTS_NOT_MET",
+    "SESSION_EXPIRED",
+    "TOO_MANY_SESSIONS",
+    "MFA_REQUIRED",
+    "MFA_REAUTH_REQUIRED",
+    "SENSITIVE_TARGET",
+    "INSUFFICIENT_ROLE",
+    "NEW_ACCOUNT_RESTRICTION",
+    "DUAL_CONTROL_REQUIRED",
+    "NOT_IN_WHITELIST",
+    "METADATA_CONDITION_FAILED",
+    "ELEVATION_REQUESTED",
+    "ELEVATION_DENIED",
+    "WHITELIST_APPROVED",
+    "ENGINE_DEGRADED",
+    "REPEATED_DENIAL_BLOCK",
+    "TEMPORARY_GRANT",
+    "PRE_APPROVED",
+    "MISSING_PREREQUISITE",
+    "COLD_SESSION",
+    "MUTUALLY_EXCLUSIVE_ACTION",
+    "ACTION_COOLDOWN",
+    "QUOTA_EXCEEDED",
+    "COMBINE_ERROR",
+    "ESCALATE_ERROR",
+    "SERIALIZE_ERROR",
+];
+
+/// Denials of the rule cascade in precedence order. When a request trips
+/// several rules, the earliest here is the one reported. Engine overlays
+/// (grants, prerequisites, quotas) apply after the cascade; custom rules
+/// slot in among the built-ins by priority.
+pub const RULE_ORDER: &[&str] = &[
+    "PANIC_MODE",
+    "INTEGRITY_FAILURE",
+    "HARD_BLOCK",
+    "CONTEXT_IMPLAUSIBLE",
+    "MAINTENANCE_FREEZE",
+    "RESOURCE_DENYLISTED",
+    "DEPRECATED_MODULE",
+    "DISALLOWED_METADATA",
+    "MISSING_ORG_CONTEXT",
+    "MISSING_USER_CONTEXT",
+    "METADATA_REQUIRED",
+    "INVALID_ARGUMENT",
+    "MISSING_TRACE_ID",
+    "TRUSTED_NETWORK_REQUIRED",
+    "CROSS_USER_WRITE",
+    "RISK_SCORE_EXCEEDED",
+    "RISK_UNDERREPORTED",
+    "ACCOUNT_LOCKOUT",
+    "ELEVATED_THREAT",
+    "AUTH_REQUIRED_FOR_READ",
+    "UNRECOGNIZED_ROLE",
+    "AUTH_REQUIREMENTS_NOT_MET",
+    "SESSION_EXPIRED",
+    "TOO_MANY_SESSIONS",
+    "MFA_REQUIRED",
+    "MFA_REAUTH_REQUIRED",
+    "SENSITIVE_TARGET",
+    "INSUFFICIENT_ROLE",
+    "NEW_ACCOUNT_RESTRICTION",
+    "DUAL_CONTROL_REQUIRED",
+    "NOT_IN_WHITELIST",
+    "METADATA_CONDITION_FAILED",
+    "ELEVATION_DENIED",
+];
+
+/// Control references for an applied rule across ISO 27001, SOC 2 and
+/// NIST 800-53, as `FRAMEWORK:control`. The first ISO entry always matches
+/// the decision's `iso_control`; any further ISO entries are secondary and
+/// fill out `iso_controls`. Combinator-only rules (e.g.
+/// UNANIMITY_NOT_REACHED) inherit the underlying decision's controls.
+fn controls_for(applied_rule: &str) -> &'static [&'static str] {
+    match applied_rule {
+        "REQUEST_PARSE_ERROR" => &["ISO27001:A.8.16", "SOC2:CC7.2", "NIST800-53:SI-10"],
+        "CONTEXT_PARSE_ERROR" => &["ISO27001:A.8.16", "SOC2:CC7.2", "NIST800-53:SI-10"],
+        "INPUT_TOO_LARGE" => &["ISO27001:A.8.16", "SOC2:CC7.2", "NIST800-53:SI-10", "NIST800-53:SC-5"],
+        "UNKNOWN_FIELD" => &["ISO27001:A.8.16", "SOC2:CC7.2", "NIST800-53:SI-10"],
+        "INTEGRITY_FAILURE" => &["ISO27001:A.12.1.2", "SOC2:CC8.1", "NIST800-53:SI-7", "NIST800-53:CM-3"],
+        "HARD_BLOCK" => &[
+            "ISO27001:A.9.4.1",
+            "ISO27001:A.9.2.3",
+            "SOC2:CC6.1",
+            "NIST800-53:AC-3",
+            "NIST800-53:AC-6",
+        ],
+        "PANIC_MODE" => &["ISO27001:A.16.1", "SOC2:CC7.4", "NIST800-53:IR-4"],
+        "ENGINE_DEGRADED" => &["ISO27001:A.17.1.1", "SOC2:A1.2", "NIST800-53:CP-10"],
+        "DUAL_CONTROL_REQUIRED" => &[
+            "ISO27001:A.6.1.2",
+            "ISO27001:A.9.2.3",
+            "SOC2:CC6.1",
+            "NIST800-53:AC-5",
+        ],
+        "REPEATED_DENIAL_BLOCK" => &["ISO27001:A.9.4.3", "SOC2:CC6.1", "NIST800-53:AC-7"],
+        "MAINTENANCE_FREEZE" => &["ISO27001:A.12.1.2", "SOC2:CC8.1", "NIST800-53:CM-3"],
+        "RESOURCE_DENYLISTED" => &["ISO27001:A.9.4.1", "SOC2:CC6.1", "NIST800-53:AC-3"],
+        "DEPRECATED_MODULE" => &["ISO27001:A.12.6.1", "SOC2:CC7.1", "NIST800-53:SI-2"],
+        "DISALLOWED_METADATA" => &["ISO27001:A.8.16", "SOC2:CC7.2", "NIST800-53:SI-10"],
+        "METADATA_REQUIRED" => &["ISO27001:A.8.16", "SOC2:CC7.2", "NIST800-53:SI-10"],
+        "INVALID_ARGUMENT" => &["ISO27001:A.8.16", "SOC2:CC7.2", "NIST800-53:SI-10"],
+        "CONTEXT_IMPLAUSIBLE" => &["ISO27001:A.8.16", "SOC2:CC7.2", "NIST800-53:SI-10"],
+        "MISSING_TRACE_ID" => &["ISO27001:A.12.4.1", "SOC2:CC7.2", "NIST800-53:AU-3"],
+        "MISSING_ORG_CONTEXT" => &["ISO27001:A.9.4.1", "SOC2:CC6.1", "NIST800-53:AC-3"],
+        "MISSING_USER_CONTEXT" => &["ISO27001:A.9.4.1", "SOC2:CC6.1", "NIST800-53:AC-3", "NIST800-53:IA-2"],
+        "TRUSTED_NETWORK_REQUIRED" => &["ISO27001:A.13.1.1", "SOC2:CC6.6", "NIST800-53:AC-17"],
+        "CROSS_USER_WRITE" => &["ISO27001:A.9.4.1", "SOC2:CC6.1", "NIST800-53:AC-3", "NIST800-53:AC-6"],
+        "RISK_SCORE_EXCEEDED" => &["ISO27001:A.8.16", "SOC2:CC7.2", "NIST800-53:SI-4"],
+        "RISK_UNDERREPORTED" => &["ISO27001:A.8.16", "SOC2:CC7.2", "NIST800-53:SI-4"],
+        "ACCOUNT_LOCKOUT" => &[
+            "ISO27001:A.9.4.3",
+            "ISO27001:A.9.4.2",
+            "SOC2:CC6.1",
+            "NIST800-53:AC-7",
+        ],
+        "ELEVATED_THREAT" => &["ISO27001:A.13.1.1", "SOC2:CC6.6", "NIST800-53:SC-7", "NIST800-53:SI-4"],
+        "AUTH_REQUIRED_FOR_READ" => &["ISO27001:A.9.4.1", "SOC2:CC6.1", "NIST800-53:AC-3", "NIST800-53:AC-6"],
+        "UNRECOGNIZED_ROLE" => &["ISO27001:A.9.2.3", "SOC2:CC6.3", "NIST800-53:AC-2", "NIST800-53:AC-6"],
+        "AUTH_REQUIREMENTS_NOT_MET" => &["ISO27001:A.9.4.2", "SOC2:CC6.1", "NIST800-53:IA-2", "NIST800-53:AC-12"],
+        "SESSION_EXPIRED" => &["ISO27001:A.9.4.2", "SOC2:CC6.1", "NIST800-53:AC-12", "NIST800-53:IA-11"],
+        "TOO_MANY_SESSIONS" => &["ISO27001:A.9.4.2", "SOC2:CC6.1", "NIST800-53:AC-10"],
+        "MFA_REQUIRED" => &[
+            "ISO27001:A.9.4.2",
+            "ISO27001:A.9.4.3",
+            "SOC2:CC6.1",
+            "NIST800-53:IA-2(1)",
+        ],
+        "MFA_REAUTH_REQUIRED" => &["ISO27001:A.9.4.2", "SOC2:CC6.1", "NIST800-53:IA-11"],
+        "SENSITIVE_TARGET" => &["ISO27001:A.8.2.3", "SOC2:CC6.1", "NIST800-53:IA-2(1)"],
+        "INSUFFICIENT_ROLE" => &[
+            "ISO27001:A.9.2.3",
+            "ISO27001:A.9.4.1",
+            "SOC2:CC6.3",
+            "NIST800-53:AC-6",
+        ],
+        "NEW_ACCOUNT_RESTRICTION" => &["ISO27001:A.9.4.2", "SOC2:CC6.1", "NIST800-53:IA-2(1)"],
+        "NOT_IN_WHITELIST" => &["ISO27001:A.9.4.1", "SOC2:CC6.1", "NIST800-53:AC-3", "NIST800-53:CM-7"],
+        "METADATA_CONDITION_FAILED" => &["ISO27001:A.9.4.1", "SOC2:CC6.1", "NIST800-53:AC-3"],
+        "ELEVATION_REQUESTED" => &["ISO27001:A.9.2.2", "SOC2:CC6.2", "NIST800-53:AC-2"],
+        "ELEVATION_DENIED" => &["ISO27001:A.9.2.2", "SOC2:CC6.2", "NIST800-53:AC-2", "NIST800-53:AC-6"],
+        "WHITELIST_APPROVED" => &["ISO27001:A.9.4.1", "SOC2:CC6.1", "NIST800-53:AC-3"],
+        "TEMPORARY_GRANT" => &["ISO27001:A.9.2.2", "SOC2:CC6.2", "NIST800-53:AC-2(2)"],
+        "PRE_APPROVED" => &["ISO27001:A.9.2.2", "SOC2:CC6.2", "NIST800-53:AC-6"],
+        "MISSING_PREREQUISITE" => &["ISO27001:A.12.1.1", "SOC2:CC8.1", "NIST800-53:AC-3(2)"],
+        "COLD_SESSION" => &["ISO27001:A.9.4.2", "SOC2:CC6.1", "NIST800-53:AC-12"],
+        "ACTION_COOLDOWN" => &["ISO27001:A.12.1.3", "SOC2:CC7.2", "NIST800-53:SC-5"],
+        "MUTUALLY_EXCLUSIVE_ACTION" => &["ISO27001:A.6.1.2", "SOC2:CC5.1", "NIST800-53:AC-5"],
+        "QUOTA_EXCEEDED" => &["ISO27001:A.12.1.3", "SOC2:A1.1", "NIST800-53:SC-5"],
+        "COMBINE_ERROR" => &["ISO27001:A.8.16", "SOC2:CC7.2", "NIST800-53:SI-10"],
+        "ESCALATE_ERROR" => &["ISO27001:A.8.16", "SOC2:CC7.2", "NIST800-53:SI-10"],
+        "SERIALIZE_ERROR" => &["ISO27001:A.12.1.2", "SOC2:CC7.2", "NIST800-53:SI-11"],
+        _ => &[],
+    }
+}
+
+/// Full rule → controls matrix as a JSON array, one entry per rule
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn control_mappings() -> String {
+    let matrix: Vec<serde_json::Value> = APPLIED_RULES
+        .iter()
+        .map(|rule| serde_json::json!({ "rule": rule, "controls": controls_for(rule) }))
+        .collect();
+    serde_json::to_string(&matrix).unwrap_or_default()
+}
+
+/// Fill `controls` when the config asks for multi-framework references,
+/// swap in the requesting tenant's own `iso_control` where it has one, and
+/// set the rule's `iso_controls` and `route_hint`
+fn attach_controls(config: &PolicyConfig, request: &AiRequest, decision: &mut PolicyDecision) {
+    let tenant = request.organisation_id.as_deref();
+    if let Some(control) = config.tenant_control(tenant, &decision.applied_rule) {
+        decision.iso_control = control.to_string();
+    }
+    decision.route_hint = Some(config.route_hint(&decision.applied_rule).to_string());
+    let secondary = controls_for(&decision.applied_rule)
+        .iter()
+        .filter_map(|c| c.strip_prefix("ISO27001:"))
+        .filter(|c| *c != decision.iso_control)
+        .map(|c| c.to_string());
+    decision.iso_controls =
+        core::iter::once(decision.iso_control.clone()).chain(secondary).collect();
+    if config.include_all_controls {
+        decision.controls = controls_for(&decision.applied_rule)
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+    }
+}
+
+/// Audit every decision on a sensitive resource, reads and permits included
+fn flag_sensitive_target(
+    config: &PolicyConfig,
+    request: &AiRequest,
+    decision: &mut PolicyDecision,
+) {
+    if config.sensitive_target(&request.target_resource) {
+        merge_audit(decision, true, false);
+    }
+}
+
+/// Warn about a deprecated action still short of its sunset
+fn attach_deprecation(
+    config: &PolicyConfig,
+    action: &str,
+    timestamp_ms: u64,
+    decision: &mut PolicyDecision,
+) {
+    let sunset = config.sunset_ms(action).filter(|&sunset| timestamp_ms < sunset);
+    decision.deprecation_warning = sunset.map(|sunset| {
+        format!(
+            "Action '{}' is deprecated and will be denied from {} ms.",
+            action, sunset
+        )
+    });
+    decision.sunset_timestamp_ms = sunset;
+}
+
+/// Set `audit_retention_days` from the config once `audit_required` is final
+fn attach_retention(config: &PolicyConfig, action: &str, decision: &mut PolicyDecision) {
+    let retention = &config.audit_retention_days;
+    decision.audit_retention_days = decision
+        .audit_required
+        .then(|| retention.get(&decision.applied_rule).or_else(|| retention.get(action)))
+        .flatten()
+        .copied();
+}
+
+// ============================================================
+// RULESET GRAPH
+// The evaluation order as data, for rendering as a flowchart.
+// Stages use the cascade's own banner labels (see `trace_rule!`),
+// and `enabled` reflects the configuration, not just the source.
+// ============================================================
+
+/// One flowchart node: a stage, the rules it can apply, and whether
+/// the configuration leaves it able to fire
+pub(crate) fn graph_node(
+    stage: &str,
+    rules: &[&str],
+    condition: String,
+    outcome: &str,
+    iso_control: &str,
+    enabled: bool,
+) -> serde_json::Value {
+    serde_json::json!({
+        "stage": stage,
+        "rules": rules,
+        "condition": condition,
+        "outcome": outcome,
+        "iso_control": iso_control,
+        "enabled": enabled,
+    })
+}
+
+/// Input checks, the rule cascade and pre-approval, in evaluation order.
+/// Each node falls through to the next when its condition does not hold.
+pub(crate) fn ruleset_nodes(config: &PolicyConfig) -> Vec<serde_json::Value> {
+    let mfa_condition = match &config.mfa_required {
+        Some(actions) => format!("Action is one of {:?} and MFA is not verified", actions),
+        None => "System action and MFA is not verified".to_string(),
+    };
+    vec![
+        graph_node(
+            "INPUT",
+            &["REQUEST_PARSE_ERROR", "CONTEXT_PARSE_ERROR", "INPUT_TOO_LARGE", "UNKNOWN_FIELD"],
+            format!(
+                "Input is malformed, over {} bytes, or (strict_fields) carries unknown fields",
+                config.max_input_bytes
+            ),
+            "deny",
+            "A.8.16",
+            true,
+        ),
+        graph_node(
+            "PANIC MODE",
+            &["PANIC_MODE"],
+            "Panic mode is on (every action, reads included)".to_string(),
+            "deny",
+            "A.16.1",
+            config.panic_mode,
+        ),
+        graph_node(
+            "RULE 0",
+            &["INTEGRITY_FAILURE"],
+            "Non-read action and the blocklist checksum does not match".to_string(),
+            "deny",
+            "A.12.1.2",
+            config.expected_blocklist_checksum.is_some(),
+        ),
+        graph_node(
+            "RULE 1",
+            &["HARD_BLOCK"],
+            format!("Action is one of {} blocked actions", config.blocked_actions.len()),
+            "deny",
+            "A.9.4.1",
+            true,
+        ),
+        graph_node(
+            "RULE 1-SANE",
+            &["CONTEXT_IMPLAUSIBLE"],
+            "Session age or failed-attempt count beyond its plausibility limit".to_string(),
+            "deny",
+            "A.8.16",
+            config.max_plausible_session_age_seconds.is_some()
+                || config.max_plausible_failed_attempts.is_some(),
+        ),
+        graph_node(
+            "RULE 1-FREEZE",
+            &["MAINTENANCE_FREEZE"],
+            "Maintenance freeze is on and the action is not a read".to_string(),
+            "deny",
+            "A.12.1.2",
+            config.freeze_writes,
+        ),
+        graph_node(
+            "RULE 1a",
+            &["RESOURCE_DENYLISTED"],
+            "Target resource matches a deny pattern".to_string(),
+            "deny",
+            "A.9.4.1",
+            !config.resource_deny_patterns.is_empty(),
+        ),
+        graph_node(
+            "RULE 1a-MOD",
+            &["DEPRECATED_MODULE"],
+            "Versioned requesting module matches a deprecated-build pattern".to_string(),
+            "deny",
+            "A.12.6.1",
+            !config.deprecated_module_versions.is_empty(),
+        ),
+        graph_node(
+            "RULE 1b",
+            &["DISALLOWED_METADATA"],
+            "Metadata carries a key outside the allowlist".to_string(),
+            "deny",
+            "A.8.16",
+            config.allowed_metadata_keys.is_some(),
+        ),
+        graph_node(
+            "RULE 1c",
+            &["MISSING_ORG_CONTEXT", "MISSING_USER_CONTEXT"],
+            "Action requires an organisation or user id the request lacks".to_string(),
+            "deny",
+            "A.9.4.1",
+            !config.actions_requiring_org.is_empty() || !config.actions_requiring_user.is_empty(),
+        ),
+        graph_node(
+            "RULE 1c-META",
+            &["METADATA_REQUIRED"],
+            "Action requires metadata and the request's is absent or null".to_string(),
+            "deny",
+            "A.8.16",
+            !config.actions_requiring_metadata.is_empty(),
+        ),
+        graph_node(
+            "RULE 1c-ARGS",
+            &["INVALID_ARGUMENT"],
+            "An argument in metadata is missing or outside the action's schema".to_string(),
+            "deny",
+            "A.8.16",
+            !config.action_arguments.is_empty(),
+        ),
+        graph_node(
+            "RULE 1c-TRACE",
+            &["MISSING_TRACE_ID"],
+            "Write or system action without a non-empty metadata.trace_id".to_string(),
+            "deny",
+            "A.12.4.1",
+            config.require_trace_id,
+        ),
+        graph_node(
+            "RULE 1d",
+            &["TRUSTED_NETWORK_REQUIRED"],
+            "Network-restricted action from an untrusted network".to_string(),
+            "deny",
+            "A.13.1.1",
+            !config.trusted_network_required.is_empty(),
+        ),
+        graph_node(
+            "RULE 1e",
+            &["CROSS_USER_WRITE"],
+            match &config.cross_user_exempt_role {
+                Some(role) => format!(
+                    "User-scoped action targeting someone other than the authenticated user, \
+                     by a role below '{}'",
+                    role
+                ),
+                None => "User-scoped action targeting someone other than the authenticated user"
+                    .to_string(),
+            },
+            "deny",
+            "A.9.4.1",
+            !config.user_scoped_actions.is_empty(),
+        ),
+        graph_node(
+            "RULE 2",
+            &["RISK_SCORE_EXCEEDED"],
+            if config.role_max_risk.is_empty() {
+                format!("Risk score above {}", config.max_risk_score)
+            } else {
+                let ceilings: Vec<String> = config
+                    .role_max_risk
+                    .iter()
+                    .map(|(role, ceiling)| format!("{} {}", role, ceiling))
+                    .collect();
+                format!(
+                    "Risk score above {}, or the role's ceiling ({})",
+                    config.max_risk_score,
+                    ceilings.join(", ")
+                )
+            },
+            "deny",
+            "A.8.16",
+            true,
+        ),
+        graph_node(
+            "RULE 2a",
+            &["RISK_UNDERREPORTED"],
+            "Risk score below the action's configured floor".to_string(),
+            "deny",
+            "A.8.16",
+            !config.min_risk_for_action.is_empty(),
+        ),
+        graph_node(
+            "RULE 3",
+            &["ACCOUNT_LOCKOUT"],
+            if config.lockout_exempt_reads.is_empty() {
+                format!("{} or more failed attempts in the last hour", config.max_failed_attempts)
+            } else {
+                format!(
+                    "{} or more failed attempts in the last hour, except reads of {}",
+                    config.max_failed_attempts,
+                    config.lockout_exempt_reads.join(", ")
+                )
+            },
+            "deny",
+            "A.9.4.3",
+            true,
+        ),
+        graph_node(
+            "RULE 3b",
+            &["ELEVATED_THREAT"],
+            format!(
+                "Threat score above {} for writes, or above {} without MFA",
+                config.threat_write_deny_above, config.threat_mfa_required_above
+            ),
+            "deny",
+            "A.13.1.1",
+            true,
+        ),
+        graph_node(
+            "RULE 3c",
+            &["AUTH_REQUIRED_FOR_READ"],
+            format!(
+                "Sensitive read by a role below '{}' or with an expired session",
+                config.authenticated_read_min_role
+            ),
+            "deny",
+            "A.9.4.1",
+            !config.authenticated_reads.is_empty(),
+        ),
+        graph_node(
+            "FAST PATH",
+            &["WHITELIST_APPROVED"],
+            "Whitelisted read with no metadata condition, MFA requirement or two-person rule"
+                .to_string(),
+            "permit",
+            "A.9.4.1",
+            true,
+        ),
+        graph_node(
+            "RULE 3a",
+            &["UNRECOGNIZED_ROLE"],
+            "Write or system action by a role outside the hierarchy".to_string(),
+            "deny",
+            "A.9.2.3",
+            true,
+        ),
+        graph_node(
+            "PRE-FLIGHT",
+            &["AUTH_REQUIREMENTS_NOT_MET"],
+            "Write or system action failing more than one of rules 4-6".to_string(),
+            "deny",
+            "A.9.4.2",
+            config.consolidate_auth_requirements,
+        ),
+        graph_node(
+            "RULE 4",
+            &["SESSION_EXPIRED"],
+            format!(
+                "Write or system action with a session older than {}s (+{}s tolerance)",
+                config.session_age_limit(false),
+                config.session_age_tolerance_seconds
+            ),
+            "deny",
+            "A.9.4.2",
+            true,
+        ),
+        graph_node(
+            "RULE 4a",
+            &["TOO_MANY_SESSIONS"],
+            format!(
+                "Write or system action by a user with more than {} concurrent sessions",
+                config.max_concurrent_sessions.unwrap_or_default()
+            ),
+            "deny",
+            "A.9.4.2",
+            config.max_concurrent_sessions.is_some(),
+        ),
+        graph_node("RULE 5", &["MFA_REQUIRED"], mfa_condition, "deny", "A.9.4.2", true),
+        graph_node(
+            "RULE 5a",
+            &["MFA_REAUTH_REQUIRED"],
+            format!(
+                "MFA-gated action whose MFA is older than its limit{}",
+                if config.require_mfa_age { ", or of unknown age" } else { "" }
+            ),
+            "deny",
+            "A.9.4.2",
+            config.max_mfa_age_seconds.is_some() || !config.max_mfa_age_for_action.is_empty(),
+        ),
+        graph_node(
+            "RULE 5b",
+            &["SENSITIVE_TARGET"],
+            format!(
+                "Write or system action without MFA on a resource under {:?}",
+                config.sensitive_resource_prefixes
+            ),
+            "deny",
+            "A.8.2.3",
+            !config.sensitive_resource_prefixes.is_empty(),
+        ),
+        graph_node(
+            "RULE 6",
+            &["INSUFFICIENT_ROLE"],
+            "System action by a role below its minimum (default 'power_user')".to_string(),
+            "deny",
+            "A.9.2.3",
+            true,
+        ),
+        graph_node(
+            "RULE 6a",
+            &["NEW_ACCOUNT_RESTRICTION"],
+            format!(
+                "Write or system action without MFA from an account younger than {}s",
+                config.new_account_age_seconds
+            ),
+            "deny",
+            "A.9.4.2",
+            true,
+        ),
+        graph_node(
+            "RULE 6b",
+            &["DUAL_CONTROL_REQUIRED"],
+            format!(
+                "Dual-control action without two distinct '{}'-or-higher approvers",
+                config.dual_control_min_role
+            ),
+            "deny",
+            "A.6.1.2",
+            !config.dual_control_actions.is_empty(),
+        ),
+        graph_node(
+            "RULE 7",
+            &["NOT_IN_WHITELIST"],
+            "Action is on no whitelist (default deny)".to_string(),
+            "deny",
+            "A.9.4.1",
+            true,
+        ),
+        graph_node(
+            "RULE 8",
+            &["METADATA_CONDITION_FAILED"],
+            "Metadata value above the action's configured ceiling".to_string(),
+            "deny",
+            "A.9.4.1",
+            !config.metadata_conditions.is_empty(),
+        ),
+        graph_node(
+            "RULE 9",
+            &["ELEVATION_REQUESTED", "ELEVATION_DENIED"],
+            format!(
+                "Action is '{}': recorded for review if the target role is valid and at most '{}'",
+                ELEVATION_ACTION, config.max_elevation_role
+            ),
+            "permit_or_deny",
+            "A.9.2.2",
+            true,
+        ),
+        graph_node(
+            "PERMITTED",
+            &["WHITELIST_APPROVED"],
+            "Every rule above passed".to_string(),
+            "permit",
+            "A.9.4.1",
+            true,
+        ),
+        graph_node(
+            "PRE-APPROVAL",
+            &["PRE_APPROVED"],
+            "A risk or role denial carrying a matching, unexpired approval token, when every other rule passes".to_string(),
+            "permit",
+            "A.9.2.2",
+            !config.approval_tokens.is_empty(),
+        ),
+    ]
+}
+
+pub(crate) fn ruleset_graph_json(config: &PolicyConfig, nodes: Vec<serde_json::Value>) -> String {
+    serde_json::json!({
+        "ruleset_version": config.ruleset_version,
+        "nodes": nodes,
+    })
+    .to_string()
+}
+
+/// The built-in ruleset as `{ruleset_version, nodes}`: one node per stage
+/// in evaluation order, with its condition, outcome and ISO control
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn ruleset_graph() -> String {
+    let (config, _) = &default_policy();
+    ruleset_graph_json(config, ruleset_nodes(config))
+}
+
+// ============================================================
+// DECISION COMBINATORS
+// Compose verdicts from several engines (e.g. global + per-module)
+// ============================================================
+
+/// Combine an array of `PolicyDecision`s under a strategy:
+/// - `deny_overrides`: the first deny wins; permit only if none deny
+/// - `permit_overrides`: the first permit wins; otherwise the first deny
+/// - `unanimous`: permit only if every decision permits
+///
+/// `audit_required` is the OR of all inputs. Empty input, bad JSON or
+/// an unknown strategy fail closed with `COMBINE_ERROR`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn combine_decisions(decisions_json: &str, strategy: &str) -> String {
+    let combined = match serde_json::from_str::<Vec<PolicyDecision>>(decisions_json) {
+        Ok(decisions) => combine(decisions, strategy),
+        Err(e) => combine_error(format!("Invalid decisions JSON: {}", e)),
+    };
+    serialize_decision(&combined, DECISION_SCHEMA_VERSION)
+}
+
+fn combine(decisions: Vec<PolicyDecision>, strategy: &str) -> PolicyDecision {
+    if decisions.is_empty() {
+        return combine_error("No decisions to combine.".to_string());
+    }
+    let audit_required = decisions.iter().any(|d| d.audit_required);
+    let requires_human_review = decisions.iter().any(|d| d.requires_human_review);
+    let total = decisions.len();
+    let denied = decisions.iter().filter(|d| !d.permitted).count();
+
+    let mut combined = match strategy {
+        "deny_overrides" => pick(decisions, |d| !d.permitted),
+        "permit_overrides" => pick(decisions, |d| d.permitted),
+        "unanimous" => {
+            if denied == 0 {
+                pick(decisions, |d| d.permitted)
+            } else {
+                let first_deny = pick(decisions, |d| !d.permitted);
+                PolicyDecision {
+                    permitted: false,
+                    applied_rule: "UNANIMITY_NOT_REACHED".to_string(),
+                    reason: format!(
+                        "{} of {} decisions denied. First denial: {} — {}",
+                        denied, total, first_deny.applied_rule, first_deny.reason
+                    ),
+                    iso_control: first_deny.iso_control,
+                    timestamp_ms: first_deny.timestamp_ms,
+                    audit_required,
+                    ..Default::default()
+                }
+                .finalize()
+            }
+        }
+        other => return combine_error(format!("Unknown combination strategy '{}'.", other)),
+    };
+    combined.audit_required = audit_required;
+    combined.requires_human_review = requires_human_review;
+    combined
+}
+
+/// First decision matching `preferred`, else the first decision
+fn pick(decisions: Vec<PolicyDecision>, preferred: impl Fn(&PolicyDecision) -> bool) -> PolicyDecision {
+    let index = decisions.iter().position(preferred).unwrap_or(0);
+    decisions.into_iter().nth(index).unwrap_or_default()
+}
+
+fn combine_error(reason: String) -> PolicyDecision {
+    PolicyDecision {
+        permitted: false,
+        applied_rule: "COMBINE_ERROR".to_string(),
+        reason,
+        iso_control: "A.8.16".to_string(),
+        timestamp_ms: 0,
+        audit_required: true,
+        ..Default::default()
+    }
+}
+
+// ============================================================
+// DECISION DIFF — regression tooling for rule changes
+// ============================================================
+
+/// Indices of decisions that changed in one particular way
+#[derive(Serialize, Debug, Default)]
+struct DiffBucket {
+    count: usize,
+    indices: Vec<usize>,
+}
+
+impl DiffBucket {
+    fn record(&mut self, index: usize) {
+        self.count += 1;
+        self.indices.push(index);
+    }
+}
+
+/// Compare two same-order arrays of decisions over one request corpus.
+/// Reports permit→deny and deny→permit flips, and decisions whose rule
+/// changed but whose outcome did not. Arrays of differing length are
+/// compared over their common prefix and flagged as mismatched.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn diff_decisions(before_json: &str, after_json: &str) -> String {
+    #[derive(Serialize)]
+    struct DecisionDiff {
+        compared: usize,
+        length_mismatch: bool,
+        before_len: usize,
+        after_len: usize,
+        permit_to_deny: DiffBucket,
+        deny_to_permit: DiffBucket,
+        rule_changed: DiffBucket,
+    }
+
+    let parse = |json: &str, side: &str| {
+        serde_json::from_str::<Vec<PolicyDecision>>(json)
+            .map_err(|e| format!("Invalid {} decisions JSON: {}", side, e))
+    };
+    let (before, after) = match (parse(before_json, "before"), parse(after_json, "after")) {
+        (Ok(before), Ok(after)) => (before, after),
+        (Err(error), _) | (_, Err(error)) => {
+            return serde_json::json!({ "error": error }).to_string();
+        }
+    };
+
+    let mut diff = DecisionDiff {
+        compared: before.len().min(after.len()),
+        length_mismatch: before.len() != after.len(),
+        before_len: before.len(),
+        after_len: after.len(),
+        permit_to_deny: DiffBucket::default(),
+        deny_to_permit: DiffBucket::default(),
+        rule_changed: DiffBucket::default(),
+    };
+    for (index, (old, new)) in before.iter().zip(&after).enumerate() {
+        match (old.permitted, new.permitted) {
+            (true, false) => diff.permit_to_deny.record(index),
+            (false, true) => diff.deny_to_permit.record(index),
+            _ if old.applied_rule != new.applied_rule => diff.rule_changed.record(index),
+            _ => {}
+        }
+    }
+    serde_json::to_string(&diff).unwrap_or_default()
+}
+
+// ============================================================
+// DECISION ESCALATION — host-side post-processing
+// ============================================================
+
+/// Raise audit and/or review flags on an already-evaluated decision,
+/// e.g. on an external threat signal. Flags can only go up: passing
+/// `false` leaves the existing value untouched.
+pub fn merge_audit(decision: &mut PolicyDecision, force_audit: bool, force_review: bool) {
+    decision.audit_required |= force_audit;
+    decision.requires_human_review |= force_review;
+}
+
+/// WASM wrapper for `merge_audit` over a serialized decision.
+/// Unparseable input yields a fail-closed ESCALATE_ERROR denial.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn escalate_decision(decision_json: &str, force_audit: bool, force_review: bool) -> String {
+    let decision = match serde_json::from_str::<PolicyDecision>(decision_json) {
+        Ok(mut decision) => {
+            merge_audit(&mut decision, force_audit, force_review);
+            decision
+        }
+        Err(e) => PolicyDecision {
+            permitted: false,
+            applied_rule: "ESCALATE_ERROR".to_string(),
+            reason: format!("Invalid decision JSON: {}", e),
+            iso_control: "A.8.16".to_string(),
+            timestamp_ms: 0,
+            audit_required: true,
+            requires_human_review: force_review,
+            ..Default::default()
+        },
+    };
+    serialize_decision(&decision, DECISION_SCHEMA_VERSION)
+}
+
+// ============================================================
+// AUDIT RECORDS — what the host persists for audited decisions
+// ============================================================
+
+/// An audit-trail entry for one audited decision
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "std", derive(JsonSchema))]
+pub struct AuditRecord {
+    /// Decision timestamp (Unix ms)
+    pub timestamp_ms: u64,
+    pub applied_rule: String,
+    pub permitted: bool,
+    pub iso_control: String,
+    /// Every framework control mapped to `applied_rule`
+    pub controls: Vec<String>,
+    /// The evaluated request, without its `approval_token`; `None` when
+    /// the request could not be parsed
+    pub request: Option<AiRequest>,
+    /// SHA-256 (hex) of the serialized decision, binding the record to it
+    pub decision_sha256: String,
+    /// The decision's `decision_id`, for matching the client's request
+    #[serde(default)]
+    pub decision_id: String,
+    /// The decision's `trace_id`, for joining with traces
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    /// `this_hash` of the previous record in the engine's chain, or
+    /// `AUDIT_CHAIN_GENESIS` for the first. Empty when unchained.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub prev_hash: String,
+    /// `audit_chain_hash` of this record. Empty when unchained.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub this_hash: String,
+}
+
+/// `prev_hash` of the first record in an audit chain
+pub const AUDIT_CHAIN_GENESIS: &str =
+    "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Chain hash of an audit record: SHA-256 (hex) of its canonical JSON with
+/// `this_hash` left out. `prev_hash` is covered, which links the chain.
+pub fn audit_chain_hash(record: &AuditRecord) -> String {
+    let unsealed = AuditRecord { this_hash: String::new(), ..record.clone() };
+    config::sha256_hex(to_canonical_json(&unsealed).as_bytes())
+}
+
+/// Walk a JSON array of chained audit records, oldest first, as
+/// `{"valid", "records", "first_break": {"index", "reason"}|null}`.
+/// The first record must follow `AUDIT_CHAIN_GENESIS`, so a truncated
+/// head is a break too. Bad JSON yields `{"error"}`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn verify_audit_chain(records_json: &str) -> String {
+    let records: Vec<AuditRecord> = match serde_json::from_str(records_json) {
+        Ok(records) => records,
+        Err(e) => {
+            let error = format!("Invalid audit records JSON: {}", e);
+            return serde_json::json!({ "error": error }).to_string();
+        }
+    };
+    let mut expected_prev = AUDIT_CHAIN_GENESIS;
+    let mut first_break = serde_json::Value::Null;
+    for (index, record) in records.iter().enumerate() {
+        let reason = if record.prev_hash != expected_prev {
+            "prev_hash does not match the preceding record"
+        } else if record.this_hash != audit_chain_hash(record) {
+            "this_hash does not match the record's contents"
+        } else {
+            expected_prev = &record.this_hash;
+            continue;
+        };
+        first_break = serde_json::json!({ "index": index, "reason": reason });
+        break;
+    }
+    serde_json::json!({
+        "valid": first_break.is_null(),
+        "records": records.len(),
+        "first_break": first_break,
+    })
+    .to_string()
+}
+
+/// Correlation ID for a request at `timestamp_ms`: the first 128 bits
+/// (hex, so URL-safe) of SHA-256 over `<canonical request>\n<timestamp>`.
+/// Computable before evaluation, unlike the decision itself. The request
+/// is hashed without its `approval_token`, a bearer secret.
+pub fn decision_id(request: &AiRequest, timestamp_ms: u64) -> String {
+    let request = AiRequest { approval_token: None, ..request.clone() };
+    let input = format!("{}\n{}", to_canonical_json(&request), timestamp_ms);
+    let mut id = config::sha256_hex(input.as_bytes());
+    id.truncate(32);
+    id
+}
+
+/// The request's `metadata.trace_id`, if it is a non-empty string
+pub(crate) fn trace_id(request: &AiRequest) -> Option<String> {
+    let trace_id = request.metadata.as_ref()?.get("trace_id")?.as_str()?;
+    (!trace_id.is_empty()).then(|| trace_id.to_string())
+}
+
+/// `decision_id` for a request JSON, as `{"decision_id"}` or `{"error"}`
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn predict_decision_id(request_json: &str, timestamp_ms: u64) -> String {
+    match serde_json::from_str::<AiRequest>(request_json) {
+        Ok(request) => {
+            serde_json::json!({ "decision_id": decision_id(&request, timestamp_ms) }).to_string()
+        }
+        Err(e) => {
+            let error = format!("Invalid request JSON: {}", e);
+            serde_json::json!({ "error": error }).to_string()
+        }
+    }
+}
+
+/// The risk ceiling RULE 2 would hold a request for `action` to under
+/// this context, as `{"action", "ceiling", "default"}` or `{"error"}`.
+/// Only the context's role moves it off the global default today; the
+/// action is resolved through aliases and echoed for the caller's
+/// bookkeeping. Trust decay and pre-approval are not reflected.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn effective_risk_ceiling(action: &str, context_json: &str) -> String {
+    let (config, _) = &default_policy();
+    match parse_context(config, context_json) {
+        Ok(context) => serde_json::json!({
+            "action": config.canonical_action(action),
+            "ceiling": config.risk_ceiling(&context.user_role),
+            "default": config.max_risk_score,
+        })
+        .to_string(),
+        Err(error) => serde_json::json!({ "error": error.to_string() }).to_string(),
+    }
+}
+
+pub(crate) fn audit_record(
+    request: Option<&AiRequest>,
+    decision: &PolicyDecision,
+    decision_json: &str,
+) -> AuditRecord {
+    AuditRecord {
+        timestamp_ms: decision.timestamp_ms,
+        applied_rule: decision.applied_rule.clone(),
+        permitted: decision.permitted,
+        iso_control: decision.iso_control.clone(),
+        controls: controls_for(&decision.applied_rule).iter().map(|c| c.to_string()).collect(),
+        // The token is a bearer secret and has no place in a log
+        request: request.map(|request| AiRequest { approval_token: None, ..request.clone() }),
+        decision_sha256: config::sha256_hex(decision_json.as_bytes()),
+        decision_id: decision.decision_id.clone(),
+        trace_id: decision.trace_id.clone(),
+        prev_hash: String::new(),
+        this_hash: String::new(),
+    }
+}
+
+/// `validate_ai_action` plus its audit record in one call, as
+/// `{"decision": {...}, "audit_record": {...}|null}`. The record is present
+/// only when `audit_required`; the decision is byte-identical to
+/// `validate_ai_action`'s output.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn validate_and_audit(request_json: &str, context_json: &str, timestamp_ms: u64) -> String {
+    let (config, index) = &default_policy();
+    let (decision, request) = match parse_inputs(config, request_json, context_json)
+    {
+        Ok((request, context)) => {
+            (evaluate_indexed(config, index, &request, &context, timestamp_ms), Some(request))
+        }
+        Err(error) => (error.into_decision(timestamp_ms), None),
+    };
+    let decision_json = serialize_decision(&decision, DECISION_SCHEMA_VERSION);
+    let record_json = if decision.audit_required {
+        let record = audit_record(request.as_ref(), &decision, &decision_json);
+        serde_json::to_string(&record).unwrap_or_default()
+    } else {
+        "null".to_string()
+    };
+    // Spliced rather than re-serialized, so the decision bytes are exact
+    format!(r#"{{"decision":{},"audit_record":{}}}"#, decision_json, record_json)
+}
+
+// ============================================================
+// SELF-TEST — run at WASM load to confirm the build is intact
+// ============================================================
+
+/// Run a fixed battery of golden cases through the built-in rules.
+/// Deliberately ignores any loaded config: it checks the compiled
+/// logic itself. Returns `{"ok":true}` or the list of failures.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn self_test() -> String {
+    #[derive(Serialize)]
+    struct Failure {
+        case: &'static str,
+        expected_permitted: bool,
+        expected_rule: &'static str,
+        actual_permitted: bool,
+        actual_rule: String,
+    }
+
+    #[derive(Serialize)]
+    struct SelfTestReport {
+        ok: bool,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        failures: Vec<Failure>,
+    }
+
+    struct Golden {
+        case: &'static str,
+        action: &'static str,
+        risk: u8,
+        role: &'static str,
+        mfa: bool,
+        session_age: u64,
+        failed_attempts: u32,
+        rule: &'static str,
+    }
+
+    // Only WHITELIST_APPROVED cases expect a permit
+    const GOLDEN: &[Golden] = &[
+        Golden { case: "read_permitted", action: "read_public_cache", risk: 10, role: "user", mfa: false, session_age: 300, failed_attempts: 0, rule: "WHITELIST_APPROVED" },
+        Golden { case: "hard_block", action: "modify_audit_logs", risk: 0, role: "super_admin", mfa: true, session_age: 0, failed_attempts: 0, rule: "HARD_BLOCK" },
+        Golden { case: "risk_exceeded", action: "read_public_cache", risk: 51, role: "user", mfa: false, session_age: 300, failed_attempts: 0, rule: "RISK_SCORE_EXCEEDED" },
+        Golden { case: "lockout", action: "read_public_cache", risk: 10, role: "user", mfa: false, session_age: 300, failed_attempts: 5, rule: "ACCOUNT_LOCKOUT" },
+        Golden { case: "session_expired", action: "write_search_index", risk: 10, role: "user", mfa: false, session_age: 901, failed_attempts: 0, rule: "SESSION_EXPIRED" },
+        Golden { case: "mfa_required", action: "clear_expired_cache", risk: 10, role: "power_user", mfa: false, session_age: 300, failed_attempts: 0, rule: "MFA_REQUIRED" },
+        Golden { case: "insufficient_role", action: "clear_expired_cache", risk: 10, role: "user", mfa: true, session_age: 300, failed_attempts: 0, rule: "INSUFFICIENT_ROLE" },
+        Golden { case: "system_permitted", action: "clear_expired_cache", risk: 10, role: "power_user", mfa: true, session_age: 300, failed_attempts: 0, rule: "WHITELIST_APPROVED" },
+        Golden { case: "default_deny", action: "format_disk", risk: 0, role: "super_admin", mfa: true, session_age: 0, failed_attempts: 0, rule: "NOT_IN_WHITELIST" },
+    ];
+
+    let mut failures = Vec::new();
+    for golden in GOLDEN {
+        let request = AiRequest {
+            action: golden.action.to_string(),
+            target_resource: "self-test".to_string(),
+            risk_score: golden.risk,
+            requesting_module: "self-test".to_string(),
+            user_id: Some("self-test".to_string()),
+            organisation_id: Some("self-test".to_string()),
+            metadata: None,
+            approval_token: None,
+            risk_components: None,
+        };
+        let context = SecurityContext {
+            user_role: golden.role.to_string(),
+            mfa_verified: golden.mfa,
+            session_age_seconds: golden.session_age,
+            trusted_network: true,
+            failed_attempts_last_hour: golden.failed_attempts,
+            account_age_seconds: None,
+            session_id: None,
+            threat_score: None,
+            oldest_failure_age_seconds: None,
+            authenticated_user_id: Some("self-test".to_string()),
+            mfa_age_seconds: None,
+            second_approver_id: None,
+            second_approver_role: None,
+            concurrent_sessions: None,
+        };
+        let expected_permitted = golden.rule == "WHITELIST_APPROVED";
+        let decision = evaluate_policy(&request, &context, 0);
+        if decision.permitted != expected_permitted || decision.applied_rule != golden.rule {
+            failures.push(Failure {
+                case: golden.case,
+                expected_permitted,
+                expected_rule: golden.rule,
+                actual_permitted: decision.permitted,
+                actual_rule: decision.applied_rule,
+            });
+        }
+    }
+
+    let report = SelfTestReport {
+        ok: failures.is_empty(),
+        failures,
+    };
+    serde_json::to_string(&report).unwrap_or_default()
+}
+
+// ============================================================
+// FIXTURES — golden-file regression corpora for CI
+// ============================================================
+
+/// Run a corpus of expected decisions through the built-in policy.
+/// `fixtures_json` is an array of `{request, context, timestamp_ms,
+/// expected_permitted, expected_rule}`; request and context go through
+/// the same JSON path as `validate_ai_action`. Returns `{total, passed,
+/// failed, failing_cases, cases}`, where each case lists its diffs, or
+/// `{"error"}` when the corpus itself is not a JSON array.
+#[cfg(feature = "fixtures")]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn run_fixtures(fixtures_json: &str) -> String {
+    #[derive(Deserialize)]
+    struct Fixture {
+        request: serde_json::Value,
+        context: serde_json::Value,
+        timestamp_ms: u64,
+        expected_permitted: bool,
+        expected_rule: String,
+    }
+
+    #[derive(Serialize)]
+    struct Diff {
+        field: &'static str,
+        expected: serde_json::Value,
+        actual: serde_json::Value,
+    }
+
+    #[derive(Serialize)]
+    struct CaseResult {
+        index: usize,
+        passed: bool,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        diffs: Vec<Diff>,
+    }
+
+    let entries: Vec<serde_json::Value> = match serde_json::from_str(fixtures_json) {
+        Ok(entries) => entries,
+        Err(e) => {
+            let error = format!("Invalid fixtures JSON: {}", e);
+            return serde_json::json!({ "error": error }).to_string();
+        }
+    };
+    let (config, index) = &default_policy();
+    let cases: Vec<CaseResult> = entries
+        .into_iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let diffs = match serde_json::from_value::<Fixture>(entry) {
+                Ok(fixture) => {
+                    let decision = validate_json_with_config(
+                        config,
+                        index,
+                        &fixture.request.to_string(),
+                        &fixture.context.to_string(),
+                        fixture.timestamp_ms,
+                    );
+                    let mut diffs = Vec::new();
+                    if decision.permitted != fixture.expected_permitted {
+                        diffs.push(Diff {
+                            field: "permitted",
+                            expected: fixture.expected_permitted.into(),
+                            actual: decision.permitted.into(),
+                        });
+                    }
+                    if decision.applied_rule != fixture.expected_rule {
+                        diffs.push(Diff {
+                            field: "applied_rule",
+                            expected: fixture.expected_rule.into(),
+                            actual: decision.applied_rule.into(),
+                        });
+                    }
+                    diffs
+                }
+                // A malformed case fails rather than aborting the corpus
+                Err(e) => vec![Diff {
+                    field: "fixture",
+                    expected: "a well-formed fixture".into(),
+                    actual: e.to_string().into(),
+                }],
+            };
+            CaseResult { index: i, passed: diffs.is_empty(), diffs }
+        })
+        .collect();
+
+    let failing_cases: Vec<usize> = cases.iter().filter(|c| !c.passed).map(|c| c.index).collect();
+    serde_json::json!({
+        "total": cases.len(),
+        "passed": cases.len() - failing_cases.len(),
+        "failed": failing_cases.len(),
+        "failing_cases": failing_cases,
+        "cases": cases,
+    })
+    .to_string()
+}
+
+// ============================================================
+// DIAGNOSTICS — explain decisions, never enforce them
+// ============================================================
+
+/// One context adjustment suggested by `min_context_for_permit`
+#[derive(Serialize, Debug)]
+struct ContextChange {
+    field: &'static str,
+    value: serde_json::Value,
+}
+
+/// Find the smallest set of context changes (MFA, fresh session,
+/// trusted network) that would flip a denied request to permitted.
+/// Purely diagnostic: the result grants nothing.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn min_context_for_permit(
+    request_json: &str,
+    base_context_json: &str,
+    timestamp_ms: u64,
+) -> String {
+    #[derive(Serialize)]
+    struct MinimalContext {
+        permitted_now: bool,
+        base_rule: String,
+        achievable: bool,
+        changes: Vec<ContextChange>,
+        resulting_rule: Option<String>,
+    }
+
+    let (config, index) = &default_policy();
+    let (request, context) =
+        match parse_inputs(config, request_json, base_context_json) {
+            Ok(inputs) => inputs,
+            Err(error) => {
+                let decision = error.into_decision(timestamp_ms);
+                return serialize_decision(&decision, DECISION_SCHEMA_VERSION);
+            }
+        };
+
+    let base = evaluate_indexed(config, index, &request, &context, timestamp_ms);
+    let mut result = MinimalContext {
+        permitted_now: base.permitted,
+        base_rule: base.applied_rule.clone(),
+        achievable: base.permitted,
+        changes: Vec::new(),
+        resulting_rule: None,
+    };
+
+    if !base.permitted {
+        // Subsets ordered by size, so the first permit is a minimal change
+        const CANDIDATES: usize = 3;
+        let mut masks: Vec<u8> = (1..(1u8 << CANDIDATES)).collect();
+        masks.sort_by_key(|m| m.count_ones());
+
+        for mask in masks {
+            let mut candidate = context.clone();
+            let mut changes = Vec::new();
+            if mask & 0b001 != 0 {
+                candidate.mfa_verified = true;
+                changes.push(ContextChange { field: "mfa_verified", value: true.into() });
+            }
+            if mask & 0b010 != 0 {
+                candidate.session_age_seconds = 0;
+                changes.push(ContextChange { field: "session_age_seconds", value: 0.into() });
+            }
+            if mask & 0b100 != 0 {
+                candidate.trusted_network = true;
+                changes.push(ContextChange { field: "trusted_network", value: true.into() });
+            }
+            let decision = evaluate_indexed(config, index, &request, &candidate, timestamp_ms);
+            if decision.permitted {
+                result.achievable = true;
+                result.changes = changes;
+                result.resulting_rule = Some(decision.applied_rule);
+                break;
+            }
+        }
+    }
+
+    serde_json::to_string(&result).unwrap_or_default()
+}
+
+/// Which whitelisted actions this context could perform right now,
+/// grouped by category. Each action is evaluated as a benign request:
+/// risk score 0 (or the action's configured floor), no target resource,
+/// no organisation ID and the context's authenticated user as its target.
+/// A capability hint for UIs only — it is no substitute for evaluating
+/// each real request.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn permitted_actions_for_context(context_json: &str, timestamp_ms: u64) -> String {
+    let (config, index) = &default_policy();
+    match parse_context(config, context_json) {
+        Ok(context) => permitted_actions(config, index, &context, timestamp_ms).to_string(),
+        Err(error) => {
+            serialize_decision(&error.into_decision(timestamp_ms), DECISION_SCHEMA_VERSION)
+        }
+    }
+}
+
+/// What the request's decision would be were the context's user holding
+/// `hypothetical_role`, to preview an elevation. Same output as
+/// `validate_ai_action`; analytical only, so nothing is recorded.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn preview_with_role(
+    request_json: &str,
+    context_json: &str,
+    hypothetical_role: &str,
+    timestamp_ms: u64,
+) -> String {
+    let (config, index) = &default_policy();
+    let decision = preview_decision(
+        config,
+        index,
+        request_json,
+        context_json,
+        hypothetical_role,
+        timestamp_ms,
+    );
+    serialize_decision(&decision, DECISION_SCHEMA_VERSION)
+}
+
+/// Shared by the free function and `PolicyEngine`: the pure cascade under
+/// a substituted role. Input errors fail closed as in `validate_ai_action`.
+fn preview_decision(
+    config: &PolicyConfig,
+    index: &ActionIndex,
+    request_json: &str,
+    context_json: &str,
+    hypothetical_role: &str,
+    timestamp_ms: u64,
+) -> PolicyDecision {
+    match parse_inputs(config, request_json, context_json) {
+        Ok((request, context)) => {
+            let context = SecurityContext { user_role: hypothetical_role.to_string(), ..context };
+            evaluate_indexed(config, index, &request, &context, timestamp_ms)
+        }
+        Err(error) => error.into_decision(timestamp_ms),
+    }
+}
+
+/// Shared by the free function and `PolicyEngine`: the pure cascade only,
+/// so engine-side grants, prerequisites and quotas are not consulted
+fn permitted_actions(
+    config: &PolicyConfig,
+    index: &ActionIndex,
+    context: &SecurityContext,
+    timestamp_ms: u64,
+) -> serde_json::Value {
+    let categories = [
+        ("read", &config.read_actions),
+        ("write", &config.write_actions),
+        ("system", &config.system_actions),
+    ];
+    let mut grouped = serde_json::Map::new();
+    for (name, actions) in categories {
+        let permitted: Vec<&String> = actions
+            .iter()
+            .filter(|action| {
+                let request = AiRequest {
+                    action: action.to_string(),
+                    target_resource: String::new(),
+                    risk_score: config.min_risk_for_action.get(*action).copied().unwrap_or(0),
+                    requesting_module: "capability-discovery".to_string(),
+                    user_id: context.authenticated_user_id.clone(),
+                    organisation_id: None,
+                    metadata: None,
+                    approval_token: None,
+                    risk_components: None,
+                };
+                evaluate_indexed(config, index, &request, context, timestamp_ms).permitted
+            })
+            .collect();
+        grouped.insert(name.to_string(), serde_json::json!(permitted));
+    }
+    serde_json::Value::Object(grouped)
+}
+
+// ============================================================
+// CRYPTO-SHREDDING HELPERS
+// ============================================================
+
+/// Outcome of a GDPR data-subject request check (erasure, portability)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(JsonSchema))]
+pub struct GdprDecision {
+    /// Whether the request may proceed
+    pub permitted: bool,
+    /// Human-readable reason
+    pub reason: String,
+    /// What to do when permitted (`DELETE_VAULT_KEY`, `EXPORT_USER_DATA`),
+    /// else `DENY`
+    pub action: String,
+    /// ISO 27001 control reference
+    pub iso_control: String,
+    pub gdpr_article: String,
+    /// Unix timestamp (ms) of the decision
+    pub timestamp_ms: u64,
+    /// Set when someone other than the data subject filed the request
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub audit_required: bool,
+    /// The least role that could have filed it, when the requester's fell short
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required_role: Option<String>,
+}
+
+/// One kind of GDPR data-subject request
+pub(crate) struct GdprRequestType {
+    pub(crate) name: &'static str,
+    article: &'static str,
+    /// `GdprDecision::action` when permitted
+    action: &'static str,
+    iso_control: &'static str,
+    validated: &'static str,
+}
+
+/// Every GDPR request type the validators accept; any other fails closed
+pub(crate) const GDPR_REQUEST_TYPES: &[GdprRequestType] = &[
+    GdprRequestType {
+        name: "erasure",
+        article: "Article 17 — Right to erasure",
+        action: "DELETE_VAULT_KEY",
+        iso_control: "A.8.3",
+        validated: "GDPR deletion request validated. Proceed with crypto-shredding.",
+    },
+    GdprRequestType {
+        name: "portability",
+        article: "Article 20 — Right to data portability",
+        action: "EXPORT_USER_DATA",
+        iso_control: "A.18.1.4",
+        validated: "GDPR portability request validated. Proceed with the export.",
+    },
+];
+
+/// Erasure under the built-in defaults: the data subject or an admin
+fn gdpr_decision(
+    user_id: &str,
+    requesting_user_id: &str,
+    requester_role: &str,
+    timestamp_ms: u64,
+) -> GdprDecision {
+    let (config, _) = &default_policy();
+    gdpr_request_decision(
+        config,
+        "erasure",
+        user_id,
+        requesting_user_id,
+        requester_role,
+        timestamp_ms,
+    )
+}
+
+/// The data subject may always file a request about themselves; anyone
+/// else needs the type's `gdpr_min_role` or higher. Unknown types are denied.
+pub(crate) fn gdpr_request_decision(
+    config: &PolicyConfig,
+    request_type: &str,
+    user_id: &str,
+    requesting_user_id: &str,
+    requester_role: &str,
+    timestamp_ms: u64,
+) -> GdprDecision {
+    let is_subject = user_id == requesting_user_id;
+    let Some(kind) = GDPR_REQUEST_TYPES.iter().find(|t| t.name == request_type) else {
+        return GdprDecision {
+            permitted: false,
+            reason: format!("Unknown GDPR request type '{}'. Denied.", request_type),
+            action: "DENY".to_string(),
+            iso_control: "A.18.1.4".to_string(),
+            gdpr_article: String::new(),
+            timestamp_ms,
+            audit_required: true,
+            required_role: None,
+        };
+    };
+    let min_role = config.gdpr_min_role(request_type);
+    let privileged = config.role_at_least(requester_role, min_role);
+    let permitted = is_subject || privileged;
+
+    GdprDecision {
+        permitted,
+        reason: if permitted {
+            kind.validated.to_string()
+        } else {
+            format!(
+                "GDPR {} request denied: requester is not the data subject and role '{}' \
+                 is below the required '{}'.",
+                kind.name, requester_role, min_role
+            )
+        },
+        action: if permitted {
+            kind.action.to_string()
+        } else {
+            "DENY".to_string()
+        },
+        iso_control: kind.iso_control.to_string(),
+        gdpr_article: kind.article.to_string(),
+        timestamp_ms,
+        audit_required: !is_subject,
+        required_role: (!permitted).then(|| min_role.to_string()),
+    }
+}
+
+/// Serialize a GDPR decision, or its fail-closed denial when that fails,
+/// as `serialize_decision` does for policy decisions
+pub(crate) fn serialize_gdpr_decision(decision: &GdprDecision) -> String {
+    serde_json::to_string(decision).unwrap_or_else(|e| {
+        let denial = GdprDecision {
+            permitted: false,
+            reason: PolicyError::Serialize(e.to_string()).to_string(),
+            action: "DENY".to_string(),
+            iso_control: decision.iso_control.clone(),
+            gdpr_article: decision.gdpr_article.clone(),
+            timestamp_ms: decision.timestamp_ms,
+            audit_required: true,
+            required_role: None,
+        };
+        serde_json::to_string(&denial).unwrap_or_default()
+    })
+}
+
+/// Validate a GDPR request of `request_type` (`erasure` or `portability`)
+/// under the built-in defaults, where either needs `org_admin` to file for
+/// someone else. `PolicyEngine::validate_gdpr_request` applies a config's
+/// `gdpr_min_roles`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn validate_gdpr_request(
+    request_type: &str,
+    user_id: &str,
+    requesting_user_id: &str,
+    requester_role: &str,
+    timestamp_ms: u64,
+) -> String {
+    let (config, _) = &default_policy();
+    let decision = gdpr_request_decision(
+        config,
+        request_type,
+        user_id,
+        requesting_user_id,
+        requester_role,
+        timestamp_ms,
+    );
+    serialize_gdpr_decision(&decision)
+}
+
+/// Validate a GDPR deletion request
+/// Returns true if the deletion is valid and should proceed
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn validate_gdpr_deletion(
+    user_id: &str,
+    requesting_user_id: &str,
+    requester_role: &str,
+    timestamp_ms: u64,
+) -> String {
+    let decision = gdpr_decision(user_id, requesting_user_id, requester_role, timestamp_ms);
+    serialize_gdpr_decision(&decision)
+}
+
+/// Batch form of `validate_gdpr_deletion` for bulk erasure jobs: a JSON
+/// array of `{user_id, requesting_user_id, requester_role}` in, an array
+/// of decisions out, in order. A malformed entry yields a fail-closed
+/// denial in its place; input that is not an array yields `{"error"}`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn validate_gdpr_deletion_batch(requests_json: &str, timestamp_ms: u64) -> String {
+    #[derive(Deserialize)]
+    struct DeletionRequest {
+        user_id: String,
+        requesting_user_id: String,
+        requester_role: String,
+    }
+
+    let entries: Vec<serde_json::Value> = match serde_json::from_str(requests_json) {
+        Ok(entries) => entries,
+        Err(e) => {
+            let error = format!("Invalid GDPR batch JSON: {}", e);
+            return serde_json::json!({ "error": error }).to_string();
+        }
+    };
+    let decisions: Vec<GdprDecision> = entries
+        .into_iter()
+        .map(|entry| match serde_json::from_value::<DeletionRequest>(entry) {
+            Ok(request) => gdpr_decision(
+                &request.user_id,
+                &request.requesting_user_id,
+                &request.requester_role,
+                timestamp_ms,
+            ),
+            Err(e) => GdprDecision {
+                permitted: false,
+                reason: format!("Invalid GDPR deletion entry: {}", e),
+                action: "DENY".to_string(),
+                iso_control: "A.8.3".to_string(),
+                gdpr_article: "Article 17 — Right to erasure".to_string(),
+                timestamp_ms,
+                audit_required: true,
+                required_role: None,
+            },
+        })
+        .collect();
+    // Spliced per decision so one that fails to serialize fails closed alone
+    let decisions: Vec<String> = decisions.iter().map(serialize_gdpr_decision).collect();
+    format!("[{}]", decisions.join(","))
+}
+
+// ============================================================
+// TESTS
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_context(role: &str, mfa: bool, risk: u8) -> (AiRequest, SecurityContext) {
+        let req = AiRequest {
+            action: "read_public_cache".to_string(),
+            target_resource: "cache:public".to_string(),
+            risk_score: risk,
+            requesting_module: "com.infinity-os.shell".to_string(),
+            user_id: Some("user-123".to_string()),
+            organisation_id: Some("org-456".to_string()),
+            metadata: None,
+            approval_token: None,
+            risk_components: None,
+        };
+        let ctx = SecurityContext {
+            user_role: role.to_string(),
+            mfa_verified: mfa,
+            session_age_seconds: 300,
+            trusted_network: true,
+            failed_attempts_last_hour: 0,
+            account_age_seconds: None,
+            session_id: None,
+            threat_score: None,
+            oldest_failure_age_seconds: None,
+            authenticated_user_id: Some("user-123".to_string()),
+            mfa_age_seconds: None,
+            second_approver_id: None,
+            second_approver_role: None,
+            concurrent_sessions: None,
+        };
+        (req, ctx)
+    }
+
+    #[test]
+    fn test_allowed_read_action() {
+        let (req, ctx) = make_context("user", false, 10);
+        let decision = evaluate_policy(&req, &ctx, 0);
+        assert!(decision.permitted);
+    }
+
+    #[test]
+    fn test_blocked_action_always_denied() {
+        let (mut req, ctx) = make_context("super_admin", true, 0);
         req.action = "modify_kernel_scheduler".to_string();
         let decision = evaluate_policy(&req, &ctx, 0);
-        assert!(!decision.permitted);
-        assert_eq!(decision.applied_rule, "HARD_BLOCK");
+        assert!(!decision.permitted);
+        assert_eq!(decision.applied_rule, "HARD_BLOCK");
+    }
+
+    #[test]
+    fn test_high_risk_score_denied() {
+        let (mut req, ctx) = make_context("user", false, 75);
+        req.action = "read_public_cache".to_string();
+        let decision = evaluate_policy(&req, &ctx, 0);
+        assert!(!decision.permitted);
+        assert_eq!(decision.applied_rule, "RISK_SCORE_EXCEEDED");
+    }
+
+    #[test]
+    fn test_unknown_action_denied() {
+        let (mut req, ctx) = make_context("super_admin", true, 0);
+        req.action = "some_unknown_action".to_string();
+        let decision = evaluate_policy(&req, &ctx, 0);
+        assert!(!decision.permitted);
+        assert_eq!(decision.applied_rule, "NOT_IN_WHITELIST");
+    }
+
+    #[test]
+    fn test_system_action_requires_mfa() {
+        let (mut req, ctx) = make_context("power_user", false, 10);
+        req.action = "schedule_background_task".to_string();
+        let decision = evaluate_policy(&req, &ctx, 0);
+        assert!(!decision.permitted);
+        assert_eq!(decision.applied_rule, "MFA_REQUIRED");
+    }
+
+    #[test]
+    fn test_account_lockout() {
+        let (req, mut ctx) = make_context("user", false, 10);
+        ctx.failed_attempts_last_hour = 10;
+        let decision = evaluate_policy(&req, &ctx, 0);
+        assert!(!decision.permitted);
+        assert_eq!(decision.applied_rule, "ACCOUNT_LOCKOUT");
+    }
+
+    #[test]
+    fn test_auth_requirements_consolidated() {
+        let (mut req, mut ctx) = make_context("user", false, 10);
+        req.action = "schedule_background_task".to_string();
+        ctx.session_age_seconds = 3600;
+        let decision = evaluate_policy(&req, &ctx, 0);
+        assert!(!decision.permitted);
+        assert_eq!(decision.applied_rule, "AUTH_REQUIREMENTS_NOT_MET");
+        assert_eq!(
+            decision.missing,
+            vec!["SESSION_EXPIRED", "MFA_REQUIRED", "INSUFFICIENT_ROLE"]
+        );
+    }
+
+    #[test]
+    fn test_auth_requirements_one_at_a_time() {
+        let (mut req, mut ctx) = make_context("user", false, 10);
+        req.action = "schedule_background_task".to_string();
+        ctx.session_age_seconds = 3600;
+        let config = PolicyConfig {
+            consolidate_auth_requirements: false,
+            ..PolicyConfig::default()
+        };
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "SESSION_EXPIRED");
+        assert!(decision.missing.is_empty());
+    }
+
+    #[test]
+    fn test_oversized_request_denied_before_parse() {
+        // Not valid JSON: a PARSE_ERROR would mean parsing was attempted
+        let payload = "x".repeat(PolicyConfig::default().max_input_bytes + 1);
+        let result = validate_ai_action(&payload, "{}", 0);
+        let decision: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(decision["permitted"], false);
+        assert_eq!(decision["applied_rule"], "INPUT_TOO_LARGE");
+        assert_eq!(decision["audit_required"], true);
+    }
+
+    #[test]
+    fn test_deeply_nested_metadata_denied() {
+        let (mut req, ctx) = make_context("user", false, 10);
+        let mut nested = serde_json::json!(1);
+        for _ in 0..64 {
+            nested = serde_json::json!({ "n": nested });
+        }
+        req.metadata = Some(nested);
+        let result = validate_ai_action(
+            &serde_json::to_string(&req).unwrap(),
+            &serde_json::to_string(&ctx).unwrap(),
+            0,
+        );
+        let decision: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(decision["permitted"], false);
+        assert_eq!(decision["applied_rule"], "INPUT_TOO_LARGE");
+    }
+
+    fn prefetch_size_limit() -> PolicyConfig {
+        let mut config = PolicyConfig::default();
+        config.metadata_conditions.insert(
+            "prefetch_module".to_string(),
+            MetadataCondition {
+                key: "module_size_mb".to_string(),
+                max_value: 10.0,
+            },
+        );
+        config
+    }
+
+    #[test]
+    fn test_metadata_condition_within_limit() {
+        let (mut req, ctx) = make_context("user", false, 10);
+        req.action = "prefetch_module".to_string();
+        req.metadata = Some(serde_json::json!({ "module_size_mb": 4 }));
+        let decision = evaluate_policy_with_config(&prefetch_size_limit(), &req, &ctx, 0);
+        assert!(decision.permitted);
+    }
+
+    #[test]
+    fn test_metadata_condition_exceeded_or_missing() {
+        let config = prefetch_size_limit();
+        let (mut req, ctx) = make_context("user", false, 10);
+        req.action = "prefetch_module".to_string();
+
+        for metadata in [
+            Some(serde_json::json!({ "module_size_mb": 25 })),
+            Some(serde_json::json!({ "module_size_mb": "small" })),
+            Some(serde_json::json!({ "other": 1 })),
+            None,
+        ] {
+            req.metadata = metadata;
+            let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+            assert!(!decision.permitted);
+            assert_eq!(decision.applied_rule, "METADATA_CONDITION_FAILED");
+        }
+    }
+
+    #[test]
+    fn test_remediation_derived_from_rule() {
+        let (mut req, ctx) = make_context("power_user", false, 10);
+        req.action = "schedule_background_task".to_string();
+        let decision = evaluate_policy(&req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "MFA_REQUIRED");
+        assert_eq!(decision.remediation, "Verify MFA and retry.");
+
+        req.action = "modify_audit_logs".to_string();
+        let decision = evaluate_policy(&req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "HARD_BLOCK");
+        assert_eq!(decision.remediation, "");
+
+        let result = validate_ai_action("{", "{}", 0);
+        let decision: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(decision["remediation"], "Fix the malformed input and retry.");
+    }
+
+    fn min_context(req: &AiRequest, ctx: &SecurityContext) -> serde_json::Value {
+        let result = min_context_for_permit(
+            &serde_json::to_string(req).unwrap(),
+            &serde_json::to_string(ctx).unwrap(),
+            0,
+        );
+        serde_json::from_str(&result).unwrap()
+    }
+
+    #[test]
+    fn test_min_context_suggests_mfa() {
+        let (mut req, ctx) = make_context("power_user", false, 10);
+        req.action = "schedule_background_task".to_string();
+        let result = min_context(&req, &ctx);
+        assert_eq!(result["permitted_now"], false);
+        assert_eq!(result["achievable"], true);
+        assert_eq!(
+            result["changes"],
+            serde_json::json!([{ "field": "mfa_verified", "value": true }])
+        );
+    }
+
+    #[test]
+    fn test_min_context_combines_changes() {
+        let (mut req, mut ctx) = make_context("power_user", false, 10);
+        req.action = "schedule_background_task".to_string();
+        ctx.session_age_seconds = 3600;
+        let result = min_context(&req, &ctx);
+        assert_eq!(result["achievable"], true);
+        assert_eq!(result["changes"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_min_context_hard_block_unachievable() {
+        let (mut req, ctx) = make_context("super_admin", false, 10);
+        req.action = "disable_mfa".to_string();
+        let result = min_context(&req, &ctx);
+        assert_eq!(result["achievable"], false);
+        assert_eq!(result["base_rule"], "HARD_BLOCK");
+        assert_eq!(result["changes"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_parse_errors_identify_input() {
+        let (req, ctx) = make_context("user", false, 10);
+        let req_json = serde_json::to_string(&req).unwrap();
+        let ctx_json = serde_json::to_string(&ctx).unwrap();
+
+        let result = validate_ai_action("{\n  \"action\": }", &ctx_json, 0);
+        let decision: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(decision["permitted"], false);
+        assert_eq!(decision["applied_rule"], "REQUEST_PARSE_ERROR");
+        assert_eq!(decision["audit_required"], true);
+        assert_eq!(decision["error_line"], 2);
+        assert_eq!(decision["error_column"], 13);
+
+        let result = validate_ai_action(&req_json, "not json", 0);
+        let decision: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(decision["applied_rule"], "CONTEXT_PARSE_ERROR");
+        assert_eq!(decision["audit_required"], true);
+        assert_eq!(decision["error_line"], 1);
+    }
+
+    fn combine_json(decisions: &[(&str, bool, bool)], strategy: &str) -> serde_json::Value {
+        let decisions: Vec<PolicyDecision> = decisions
+            .iter()
+            .map(|(rule, permitted, audit)| PolicyDecision {
+                permitted: *permitted,
+                applied_rule: rule.to_string(),
+                reason: format!("{} reason", rule),
+                iso_control: "A.9.4.1".to_string(),
+                audit_required: *audit,
+                ..Default::default()
+            })
+            .collect();
+        let result = combine_decisions(&serde_json::to_string(&decisions).unwrap(), strategy);
+        serde_json::from_str(&result).unwrap()
+    }
+
+    #[test]
+    fn test_combine_deny_overrides() {
+        let inputs = [
+            ("WHITELIST_APPROVED", true, true),
+            ("MFA_REQUIRED", false, false),
+            ("HARD_BLOCK", false, false),
+        ];
+        let combined = combine_json(&inputs, "deny_overrides");
+        assert_eq!(combined["permitted"], false);
+        assert_eq!(combined["applied_rule"], "MFA_REQUIRED");
+        assert_eq!(combined["reason"], "MFA_REQUIRED reason");
+        assert_eq!(combined["audit_required"], true);
+    }
+
+    #[test]
+    fn test_combine_permit_overrides_and_unanimous() {
+        let inputs = [("MFA_REQUIRED", false, false), ("WHITELIST_APPROVED", true, false)];
+        let combined = combine_json(&inputs, "permit_overrides");
+        assert_eq!(combined["permitted"], true);
+        assert_eq!(combined["applied_rule"], "WHITELIST_APPROVED");
+
+        let combined = combine_json(&inputs, "unanimous");
+        assert_eq!(combined["permitted"], false);
+        assert_eq!(combined["applied_rule"], "UNANIMITY_NOT_REACHED");
+
+        let all_permit = [("WHITELIST_APPROVED", true, false), ("WHITELIST_APPROVED", true, false)];
+        assert_eq!(combine_json(&all_permit, "unanimous")["permitted"], true);
+    }
+
+    #[test]
+    fn test_combine_fails_closed() {
+        assert_eq!(combine_json(&[], "deny_overrides")["applied_rule"], "COMBINE_ERROR");
+        let inputs = [("WHITELIST_APPROVED", true, false)];
+        let combined = combine_json(&inputs, "majority");
+        assert_eq!(combined["permitted"], false);
+        assert_eq!(combined["applied_rule"], "COMBINE_ERROR");
+        let combined: serde_json::Value =
+            serde_json::from_str(&combine_decisions("{", "deny_overrides")).unwrap();
+        assert_eq!(combined["applied_rule"], "COMBINE_ERROR");
+    }
+
+    #[test]
+    fn test_metadata_allowlist() {
+        let config = PolicyConfig {
+            allowed_metadata_keys: Some(vec!["trace_id".to_string()]),
+            ..PolicyConfig::default()
+        };
+        let (mut req, ctx) = make_context("user", false, 10);
+
+        req.metadata = Some(serde_json::json!({ "trace_id": "abc" }));
+        assert!(evaluate_policy_with_config(&config, &req, &ctx, 0).permitted);
+
+        req.metadata = None;
+        assert!(evaluate_policy_with_config(&config, &req, &ctx, 0).permitted);
+
+        for metadata in [
+            serde_json::json!({ "trace_id": "abc", "smuggled": "x" }),
+            serde_json::json!(["trace_id"]),
+            serde_json::json!("trace_id"),
+        ] {
+            req.metadata = Some(metadata);
+            let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+            assert!(!decision.permitted);
+            assert_eq!(decision.applied_rule, "DISALLOWED_METADATA");
+            assert_eq!(decision.iso_control, "A.8.16");
+        }
+    }
+
+    #[test]
+    fn test_metadata_allowlist_unset_skips_check() {
+        let (mut req, ctx) = make_context("user", false, 10);
+        req.metadata = Some(serde_json::json!([1, 2, 3]));
+        assert!(evaluate_policy(&req, &ctx, 0).permitted);
+    }
+
+    #[test]
+    fn test_new_account_write_requires_mfa() {
+        let (mut req, mut ctx) = make_context("user", false, 10);
+        req.action = "write_user_preferences".to_string();
+        ctx.account_age_seconds = Some(120);
+        let decision = evaluate_policy(&req, &ctx, 0);
+        assert!(!decision.permitted);
+        assert_eq!(decision.applied_rule, "NEW_ACCOUNT_RESTRICTION");
+        assert!(decision.audit_required);
+
+        ctx.mfa_verified = true;
+        assert!(evaluate_policy(&req, &ctx, 0).permitted);
+    }
+
+    #[test]
+    fn test_new_account_reads_and_unknown_age_unaffected() {
+        let (mut req, mut ctx) = make_context("user", false, 10);
+        ctx.account_age_seconds = Some(120);
+        assert!(evaluate_policy(&req, &ctx, 0).permitted);
+
+        req.action = "write_user_preferences".to_string();
+        ctx.account_age_seconds = None;
+        assert!(evaluate_policy(&req, &ctx, 0).permitted);
+
+        ctx.account_age_seconds = Some(7200);
+        assert!(evaluate_policy(&req, &ctx, 0).permitted);
+    }
+
+    #[test]
+    fn test_glob_match_is_anchored() {
+        assert!(glob_match("vault:*", "vault:root-token"));
+        assert!(glob_match("*:secrets:*", "org-1:secrets:db"));
+        assert!(glob_match("vault", "vault"));
+        assert!(!glob_match("vault", "my-vaulted-cache"));
+        assert!(!glob_match("vault:*", "my-vault:x"));
+        assert!(!glob_match("*:secrets:*", "org-1:secrets"));
+        assert!(glob_match("a*b*c", "axxbyyc"));
+        assert!(!glob_match("a*b*c", "axxbyy"));
+    }
+
+    #[test]
+    fn test_resource_denylist() {
+        let config = PolicyConfig {
+            resource_deny_patterns: vec!["vault:*".to_string(), "*:secrets:*".to_string()],
+            ..PolicyConfig::default()
+        };
+        let (mut req, ctx) = make_context("super_admin", true, 10);
+
+        req.target_resource = "org-1:secrets:db".to_string();
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert!(!decision.permitted);
+        assert_eq!(decision.applied_rule, "RESOURCE_DENYLISTED");
+        assert!(decision.audit_required);
+
+        req.target_resource = "my-vaulted-cache".to_string();
+        assert!(evaluate_policy_with_config(&config, &req, &ctx, 0).permitted);
+    }
+
+    #[test]
+    fn test_self_test_passes() {
+        assert_eq!(self_test(), r#"{"ok":true}"#);
+    }
+
+    #[test]
+    fn test_session_age_limits_per_category() {
+        let config = PolicyConfig {
+            max_session_age_write: Some(1800),
+            max_session_age_system: Some(300),
+            ..PolicyConfig::default()
+        };
+        let (mut req, mut ctx) = make_context("power_user", true, 10);
+        ctx.session_age_seconds = 600;
+
+        req.action = "write_search_index".to_string();
+        assert!(evaluate_policy_with_config(&config, &req, &ctx, 0).permitted);
+
+        req.action = "clear_expired_cache".to_string();
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "SESSION_EXPIRED");
+        assert!(decision.reason.contains("300s limit for system operations"));
+    }
+
+    #[test]
+    fn test_session_age_system_never_looser_than_write() {
+        // Only the write limit is set: system falls back to the global 900s,
+        // but the stricter write limit still applies to system actions
+        let config = PolicyConfig {
+            max_session_age_write: Some(120),
+            ..PolicyConfig::default()
+        };
+        assert_eq!(config.session_age_limit(false), 120);
+        assert_eq!(config.session_age_limit(true), 120);
+
+        let config = PolicyConfig {
+            max_session_age_system: Some(60),
+            ..PolicyConfig::default()
+        };
+        assert_eq!(config.session_age_limit(false), 900);
+        assert_eq!(config.session_age_limit(true), 60);
+    }
+
+    #[test]
+    fn test_batch_ndjson_preserves_order_and_skips_blank_lines() {
+        let (req, ctx) = make_context("user", false, 10);
+        let mut blocked = req.clone();
+        blocked.action = "disable_mfa".to_string();
+        let input = format!(
+            "{}\n\n{{not json}}\n   \n{}\n",
+            serde_json::to_string(&req).unwrap(),
+            serde_json::to_string(&blocked).unwrap()
+        );
+        let output = validate_ai_action_batch_ndjson(&input, &serde_json::to_string(&ctx).unwrap(), 7);
+
+        let rules: Vec<String> = output
+            .lines()
+            .map(|l| {
+                let d: serde_json::Value = serde_json::from_str(l).unwrap();
+                assert_eq!(d["timestamp_ms"], 7);
+                d["applied_rule"].as_str().unwrap().to_string()
+            })
+            .collect();
+        assert_eq!(rules, vec!["WHITELIST_APPROVED", "REQUEST_PARSE_ERROR", "HARD_BLOCK"]);
+    }
+
+    #[test]
+    fn test_risk_floor_catches_underreported_actions() {
+        let config = PolicyConfig {
+            min_risk_for_action: [("schedule_background_task".to_string(), 20)]
+                .into_iter()
+                .collect(),
+            ..PolicyConfig::default()
+        };
+        let (mut req, ctx) = make_context("power_user", true, 5);
+        req.action = "schedule_background_task".to_string();
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert!(!decision.permitted);
+        assert_eq!(decision.applied_rule, "RISK_UNDERREPORTED");
+        assert!(decision.audit_required);
+
+        req.risk_score = 20;
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert!(decision.permitted);
+    }
+
+    #[test]
+    fn test_partial_context_fails_closed() {
+        let decide = |req: &AiRequest| -> serde_json::Value {
+            let result = validate_ai_action(
+                &serde_json::to_string(req).unwrap(),
+                r#"{"user_role":"power_user"}"#,
+                0,
+            );
+            serde_json::from_str(&result).unwrap()
+        };
+        let (mut req, _) = make_context("power_user", true, 10);
+        assert_eq!(decide(&req)["applied_rule"], "WHITELIST_APPROVED");
+
+        // Unknown session age is stale
+        req.action = "write_notification".to_string();
+        assert_eq!(decide(&req)["applied_rule"], "SESSION_EXPIRED");
+
+        // ...and unproven MFA is absent
+        req.action = "clear_expired_cache".to_string();
+        let system = decide(&req);
+        assert_eq!(system["applied_rule"], "AUTH_REQUIREMENTS_NOT_MET");
+        assert_eq!(system["missing"], serde_json::json!(["SESSION_EXPIRED", "MFA_REQUIRED"]));
+    }
+
+    #[test]
+    fn test_batch_ndjson_bad_context_fails_every_line() {
+        let (req, _) = make_context("user", false, 10);
+        let line = serde_json::to_string(&req).unwrap();
+        let output = validate_ai_action_batch_ndjson(&format!("{}\n{}", line, line), "{", 0);
+        assert_eq!(output.lines().count(), 2);
+        assert!(output.lines().all(|l| l.contains("CONTEXT_PARSE_ERROR")));
+    }
+
+    #[test]
+    fn test_unrecognized_role_denied_for_writes() {
+        let (mut req, ctx) = make_context("contractor", true, 10);
+        req.action = "write_user_preferences".to_string();
+        let decision = evaluate_policy(&req, &ctx, 0);
+        assert!(!decision.permitted);
+        assert_eq!(decision.applied_rule, "UNRECOGNIZED_ROLE");
+        assert!(decision.audit_required);
+    }
+
+    #[test]
+    fn test_unrecognized_role_read_permitted_with_note() {
+        let (req, ctx) = make_context("anonymous", false, 10);
+        let decision = evaluate_policy(&req, &ctx, 0);
+        assert!(decision.permitted);
+        assert!(decision.reason.contains("role 'anonymous' is not recognized"));
+    }
+
+    fn renamed_theme_config() -> PolicyConfig {
+        let mut config = PolicyConfig::default();
+        config.write_actions.retain(|a| a != "adjust_ui_theme");
+        config.write_actions.push("set_ui_theme".to_string());
+        config
+            .action_aliases
+            .insert("adjust_ui_theme".to_string(), "set_ui_theme".to_string());
+        config
+    }
+
+    #[test]
+    fn test_action_alias_resolves_to_canonical() {
+        let config = renamed_theme_config();
+        assert!(config.validate().is_ok());
+        let (mut req, ctx) = make_context("user", false, 10);
+        req.action = "adjust_ui_theme".to_string();
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert!(decision.permitted);
+        assert_eq!(decision.aliased_from.as_deref(), Some("adjust_ui_theme"));
+        assert!(decision.reason.contains("'set_ui_theme'"));
+    }
+
+    #[test]
+    fn test_action_alias_cannot_change_block_status() {
+        let mut config = renamed_theme_config();
+        config
+            .action_aliases
+            .insert("read_vault".to_string(), "access_vault_secrets".to_string());
+        assert!(config.validate().is_err());
+
+        let mut config = renamed_theme_config();
+        config
+            .action_aliases
+            .insert("disable_mfa".to_string(), "read_public_cache".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_canonical_json_ignores_metadata_key_order() {
+        let (mut a, _) = make_context("user", false, 10);
+        let mut b = a.clone();
+        a.metadata = serde_json::from_str(r#"{"z": 1, "a": {"y": 2.0, "b": [3, 4.5]}}"#).unwrap();
+        b.metadata = serde_json::from_str(r#"{"a": {"b": [3, 4.5], "y": 2}, "z": 1.0}"#).unwrap();
+        assert_eq!(to_canonical_json(&a), to_canonical_json(&b));
+        assert!(to_canonical_json(&a).contains(r#""metadata":{"a":{"b":[3,4.5],"y":2},"z":1}"#));
+    }
+
+    #[test]
+    fn test_audited_permit_lists_cleared_gates() {
+        let (mut req, ctx) = make_context("power_user", true, 40);
+        req.action = "clear_expired_cache".to_string();
+        let decision = evaluate_policy(&req, &ctx, 0);
+        assert!(decision.permitted && decision.audit_required);
+        assert!(decision.cleared_gates.contains(&"mfa: cleared".to_string()));
+        assert!(decision.cleared_gates.contains(&"whitelist: cleared".to_string()));
+
+        req.action = "read_public_cache".to_string();
+        let decision = evaluate_policy(&req, &ctx, 0);
+        assert!(decision.cleared_gates.contains(&"mfa: n/a".to_string()));
+        assert!(decision.cleared_gates.contains(&"session_age: n/a".to_string()));
+        assert!(decision.cleared_gates.contains(&"risk_score: cleared".to_string()));
+    }
+
+    #[test]
+    fn test_sensitive_target_permit_lists_cleared_gates() {
+        let config = PolicyConfig {
+            sensitive_resource_prefixes: vec!["pii:".to_string()],
+            ..PolicyConfig::default()
+        };
+        let (mut req, ctx) = make_context("user", false, 5);
+        req.target_resource = "pii:x".to_string();
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert!(decision.permitted && decision.audit_required);
+        assert!(decision.cleared_gates.contains(&"whitelist: cleared".to_string()));
+    }
+
+    #[test]
+    fn test_unaudited_permit_has_no_gate_list() {
+        let (req, ctx) = make_context("user", false, 10);
+        let decision = evaluate_policy(&req, &ctx, 0);
+        assert!(decision.permitted && !decision.audit_required);
+        assert!(decision.cleared_gates.is_empty());
+    }
+
+    #[test]
+    fn test_step_up_signalled_for_mfa() {
+        let (mut req, ctx) = make_context("power_user", false, 10);
+        req.action = "schedule_background_task".to_string();
+        let decision = evaluate_policy(&req, &ctx, 0);
+        assert!(!decision.permitted);
+        let step_up = decision.step_up_required.expect("step-up expected");
+        assert_eq!(step_up.method, "mfa");
+    }
+
+    #[test]
+    fn test_no_step_up_when_auth_cannot_help() {
+        let (mut req, ctx) = make_context("power_user", false, 10);
+        for action in ["disable_mfa", "unknown_action"] {
+            req.action = action.to_string();
+            let decision = evaluate_policy(&req, &ctx, 0);
+            assert!(!decision.permitted);
+            assert!(decision.step_up_required.is_none());
+        }
+    }
+
+    #[test]
+    fn test_indexed_fast_path_matches_naive_cascade() {
+        let mut config = PolicyConfig {
+            metadata_conditions: [(
+                "read_public_cache".to_string(),
+                MetadataCondition { key: "rows".to_string(), max_value: 10.0 },
+            )]
+            .into_iter()
+            .collect(),
+            authenticated_reads: vec!["read_system_metrics".to_string()],
+            ..PolicyConfig::default()
+        };
+        // Overlapping lists exercise the most-restrictive-wins ordering
+        config.read_actions.push("write_notification".to_string());
+        let fast = ActionIndex::new(&config);
+        let mut naive = ActionIndex::new(&config);
+        naive.read_fast_path = false;
+
+        let actions: Vec<String> = config
+            .read_actions
+            .iter()
+            .chain(&config.write_actions)
+            .chain(&config.system_actions)
+            .chain(&config.blocked_actions)
+            .cloned()
+            .collect();
+        let known = actions.len();
+        let roles = ["user", "power_user", "org_admin", "super_admin", "guest"];
+
+        // xorshift64: deterministic, dependency-free pseudo-randomness
+        let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for i in 0..5_000 {
+            let roll = next();
+            let action = match roll % 3 {
+                0 => actions[(roll >> 8) as usize % known].clone(),
+                1 => format!("{}_x", actions[(roll >> 8) as usize % known]),
+                _ => format!("action_{}", roll % 1000),
+            };
+            assert_eq!(fast.category(&action), config.action_category(&action), "{}", action);
+
+            let role = roles[(roll >> 16) as usize % roles.len()];
+            let (mut req, mut ctx) = make_context(role, roll & 1 == 0, (roll >> 24) as u8 % 60);
+            req.action = action;
+            req.metadata = (i % 2 == 0).then(|| serde_json::json!({ "rows": (roll >> 32) % 20 }));
+            ctx.session_age_seconds = (roll >> 40) % 1800;
+            ctx.failed_attempts_last_hour = ((roll >> 48) % 7) as u32;
+            ctx.account_age_seconds = (roll & 2 == 0).then_some((roll >> 52) % 7200);
+
+            let optimized = evaluate_indexed(&config, &fast, &req, &ctx, 0);
+            let reference = evaluate_indexed(&config, &naive, &req, &ctx, 0);
+            // The fast path skips stages by design; only the outcome must match
+            #[cfg(feature = "trace-timing")]
+            let (optimized, reference) = (
+                PolicyDecision { rule_timings: Vec::new(), ..optimized },
+                PolicyDecision { rule_timings: Vec::new(), ..reference },
+            );
+            assert_eq!(
+                serde_json::to_value(&optimized).unwrap(),
+                serde_json::to_value(&reference).unwrap(),
+                "{:?} / {:?}",
+                req,
+                ctx
+            );
+        }
+    }
+
+    #[test]
+    fn test_threat_score_adds_restrictions() {
+        let (mut req, mut ctx) = make_context("power_user", false, 10);
+        ctx.threat_score = Some(50);
+        let decision = evaluate_policy(&req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "ELEVATED_THREAT");
+        assert_eq!(decision.step_up_required.unwrap().method, "mfa");
+
+        ctx.mfa_verified = true;
+        assert!(evaluate_policy(&req, &ctx, 0).permitted);
+
+        req.action = "write_notification".to_string();
+        assert!(evaluate_policy(&req, &ctx, 0).permitted);
+        ctx.threat_score = Some(71);
+        let decision = evaluate_policy(&req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "ELEVATED_THREAT");
+        assert!(decision.step_up_required.is_none());
+
+        // No signal, no rule
+        ctx.threat_score = None;
+        ctx.mfa_verified = false;
+        assert!(evaluate_policy(&req, &ctx, 0).permitted);
+    }
+
+    // Compares whole decisions, and timings differ run to run
+    #[cfg(not(feature = "trace-timing"))]
+    #[test]
+    fn test_schema_version_negotiation() {
+        let (req, ctx) = make_context("user", false, 10);
+        let req_json = serde_json::to_string(&req).unwrap();
+        let ctx_json = serde_json::to_string(&ctx).unwrap();
+
+        let v1: serde_json::Value =
+            serde_json::from_str(&validate_ai_action_v(&req_json, &ctx_json, 0, 1)).unwrap();
+        let keys: Vec<&String> = v1.as_object().unwrap().keys().collect();
+        assert_eq!(
+            keys,
+            ["applied_rule", "audit_required", "iso_control", "permitted", "reason", "timestamp_ms"]
+        );
+
+        let latest = validate_ai_action(&req_json, &ctx_json, 0);
+        assert_eq!(validate_ai_action_v(&req_json, &ctx_json, 0, DECISION_SCHEMA_VERSION), latest);
+        assert_eq!(validate_ai_action_v(&req_json, &ctx_json, 0, 99), latest);
+    }
+
+    #[test]
+    fn test_authenticated_read_requires_role() {
+        let config = PolicyConfig {
+            authenticated_reads: vec!["read_system_metrics".to_string()],
+            ..PolicyConfig::default()
+        };
+        let (mut req, mut ctx) = make_context("user", false, 10);
+        req.action = "read_system_metrics".to_string();
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert!(!decision.permitted);
+        assert_eq!(decision.applied_rule, "AUTH_REQUIRED_FOR_READ");
+        assert_eq!(decision.missing, vec!["INSUFFICIENT_ROLE"]);
+
+        ctx.user_role = "power_user".to_string();
+        assert!(evaluate_policy_with_config(&config, &req, &ctx, 0).permitted);
+
+        ctx.session_age_seconds = 3600;
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "AUTH_REQUIRED_FOR_READ");
+        assert_eq!(decision.step_up_required.unwrap().method, "reauthenticate");
+
+        // Reads outside the set stay open
+        req.action = "read_public_cache".to_string();
+        ctx.user_role = "user".to_string();
+        assert!(evaluate_policy_with_config(&config, &req, &ctx, 0).permitted);
+    }
+
+    #[test]
+    fn test_diff_decisions_buckets_changes() {
+        let (req, ctx) = make_context("user", false, 10);
+        let permit = evaluate_policy(&req, &ctx, 0);
+        let risk = evaluate_policy(&AiRequest { risk_score: 90, ..req.clone() }, &ctx, 0);
+        let blocked = evaluate_policy(
+            &AiRequest { action: "disable_mfa".to_string(), ..req.clone() },
+            &ctx,
+            0,
+        );
+        let before = serde_json::to_string(&[&permit, &risk, &risk, &permit]).unwrap();
+        let after = serde_json::to_string(&[&risk, &permit, &blocked]).unwrap();
+
+        let diff: serde_json::Value =
+            serde_json::from_str(&diff_decisions(&before, &after)).unwrap();
+        assert_eq!(diff["compared"], 3);
+        assert_eq!(diff["length_mismatch"], true);
+        assert_eq!(diff["permit_to_deny"]["indices"], serde_json::json!([0]));
+        assert_eq!(diff["deny_to_permit"]["indices"], serde_json::json!([1]));
+        assert_eq!(diff["rule_changed"]["indices"], serde_json::json!([2]));
+
+        let error: serde_json::Value =
+            serde_json::from_str(&diff_decisions("[]", "nope")).unwrap();
+        assert!(error["error"].as_str().unwrap().contains("after"));
+    }
+
+    #[test]
+    fn test_required_identity_context() {
+        let config = PolicyConfig {
+            actions_requiring_org: vec!["write_notification".to_string()],
+            actions_requiring_user: vec!["write_user_preferences".to_string()],
+            ..PolicyConfig::default()
+        };
+        let (mut req, ctx) = make_context("power_user", true, 10);
+        req.action = "write_notification".to_string();
+        assert!(evaluate_policy_with_config(&config, &req, &ctx, 0).permitted);
+
+        for org in [None, Some(String::new())] {
+            req.organisation_id = org;
+            let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+            assert_eq!(decision.applied_rule, "MISSING_ORG_CONTEXT");
+            assert_eq!(decision.iso_control, "A.9.4.1");
+        }
+
+        req.action = "write_user_preferences".to_string();
+        req.user_id = Some(String::new());
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "MISSING_USER_CONTEXT");
+
+        // Unlisted actions keep working without either ID
+        req.action = "write_search_index".to_string();
+        assert!(evaluate_policy_with_config(&config, &req, &ctx, 0).permitted);
+    }
+
+    #[test]
+    fn test_permitted_actions_for_context_groups_by_category() {
+        let (_, ctx) = make_context("user", false, 0);
+        let result: serde_json::Value = serde_json::from_str(&permitted_actions_for_context(
+            &serde_json::to_string(&ctx).unwrap(),
+            0,
+        ))
+        .unwrap();
+        assert_eq!(result["read"].as_array().unwrap().len(), ALLOWED_READ_ACTIONS.len());
+        // All writes except elevation, which needs a target role in metadata
+        assert_eq!(result["write"].as_array().unwrap().len(), ALLOWED_WRITE_ACTIONS.len() - 1);
+        // Plain users without MFA get no system actions
+        assert_eq!(result["system"], serde_json::json!([]));
+
+        let error: serde_json::Value =
+            serde_json::from_str(&permitted_actions_for_context("{", 0)).unwrap();
+        assert_eq!(error["applied_rule"], "CONTEXT_PARSE_ERROR");
+    }
+
+    #[test]
+    fn test_blocklist_integrity_failure_fails_closed() {
+        let baseline = blocklist_checksum();
+        let mut config = PolicyConfig {
+            expected_blocklist_checksum: Some(baseline.clone()),
+            ..PolicyConfig::default()
+        };
+        config.blocked_actions.reverse();
+        assert_eq!(config.blocklist_checksum(), baseline);
+
+        let (mut req, ctx) = make_context("power_user", true, 10);
+        req.action = "write_notification".to_string();
+        assert!(evaluate_policy_with_config(&config, &req, &ctx, 0).permitted);
+
+        config.blocked_actions.pop();
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "INTEGRITY_FAILURE");
+        assert!(decision.audit_required);
+
+        // Reads stay available while the blocklist is reconciled
+        req.action = "read_public_cache".to_string();
+        assert!(evaluate_policy_with_config(&config, &req, &ctx, 0).permitted);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_json_schema_reflects_structs() {
+        let schema: serde_json::Value = serde_json::from_str(&json_schema()).unwrap();
+        let context = &schema["SecurityContext"];
+        assert_eq!(context["required"], serde_json::json!(["user_role"]));
+        assert_eq!(context["properties"]["session_age_seconds"]["default"], u64::MAX);
+
+        let request = &schema["AiRequest"];
+        assert_eq!(request["properties"]["risk_score"]["maximum"], 100.0);
+        assert!(request["properties"]["metadata"].is_object());
+
+        let decision = &schema["PolicyDecision"];
+        let step_up = &decision["definitions"]["StepUp"]["properties"]["method"];
+        assert_eq!(step_up["enum"], serde_json::json!(["mfa", "reauthenticate"]));
+    }
+
+    #[test]
+    fn test_session_age_tolerance_band() {
+        let config = PolicyConfig {
+            session_age_tolerance_seconds: 30,
+            ..PolicyConfig::default()
+        };
+        let (mut req, mut ctx) = make_context("power_user", true, 10);
+        req.action = "write_notification".to_string();
+
+        ctx.session_age_seconds = 920;
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert!(decision.permitted);
+        assert!(decision.reason.contains("clock-skew tolerance"));
+
+        ctx.session_age_seconds = 900;
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert!(!decision.reason.contains("tolerance"));
+
+        ctx.session_age_seconds = 931;
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "SESSION_EXPIRED");
+    }
+
+    #[test]
+    fn test_control_mappings_cover_every_rule() {
+        let matrix: Vec<serde_json::Value> = serde_json::from_str(&control_mappings()).unwrap();
+        assert_eq!(matrix.len(), APPLIED_RULES.len());
+        for entry in &matrix {
+            let controls = entry["controls"].as_array().unwrap();
+            for framework in ["ISO27001:", "SOC2:", "NIST800-53:"] {
+                assert!(
+                    controls.iter().any(|c| c.as_str().unwrap().starts_with(framework)),
+                    "{} lacks {}",
+                    entry["rule"],
+                    framework
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_verbose_controls_match_iso_control() {
+        let config = PolicyConfig {
+            include_all_controls: true,
+            ..PolicyConfig::default()
+        };
+        let (mut req, ctx) = make_context("user", false, 10);
+        for action in ["read_public_cache", "disable_mfa", "clear_expired_cache", "unknown"] {
+            req.action = action.to_string();
+            let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+            assert_eq!(decision.controls[0], format!("ISO27001:{}", decision.iso_control));
+        }
+        // Off by default
+        assert!(evaluate_policy(&req, &ctx, 0).controls.is_empty());
+    }
+
+    #[test]
+    fn test_iso_controls_lead_with_iso_control() {
+        let (mut req, mut ctx) = make_context("user", false, 10);
+        req.action = "clear_expired_cache".to_string();
+        ctx.user_role = "power_user".to_string();
+        let decision = evaluate_policy(&req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "MFA_REQUIRED");
+        assert_eq!(decision.iso_controls, ["A.9.4.2", "A.9.4.3"]);
+
+        for action in ["read_public_cache", "disable_mfa", "unknown"] {
+            req.action = action.to_string();
+            let decision = evaluate_policy(&req, &ctx, 0);
+            assert_eq!(decision.iso_controls[0], decision.iso_control);
+        }
+        // A tenant's override is the primary, ahead of the mapped controls
+        let mut config = PolicyConfig::default();
+        config.tenant_control_overrides.insert(
+            "org-456".to_string(),
+            BTreeMap::from([("HARD_BLOCK".to_string(), "NIST800-53:AC-3".to_string())]),
+        );
+        req.action = "disable_mfa".to_string();
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert_eq!(decision.iso_controls, ["NIST800-53:AC-3", "A.9.4.1", "A.9.2.3"]);
+    }
+
+    #[test]
+    fn test_elevation_request_is_recorded_not_granted() {
+        let (mut req, ctx) = make_context("user", false, 10);
+        req.action = ELEVATION_ACTION.to_string();
+        req.metadata = Some(serde_json::json!({ "target_role": "power_user" }));
+        let decision = evaluate_policy(&req, &ctx, 0);
+        assert!(decision.permitted);
+        assert_eq!(decision.applied_rule, "ELEVATION_REQUESTED");
+        assert!(decision.audit_required && decision.requires_human_review);
+        assert_eq!(decision.requested_role.as_deref(), Some("power_user"));
+
+        // Above the maximum, unknown, and not an elevation at all
+        for target in ["super_admin", "root", "user"] {
+            req.metadata = Some(serde_json::json!({ "target_role": target }));
+            let decision = evaluate_policy(&req, &ctx, 0);
+            assert_eq!(decision.applied_rule, "ELEVATION_DENIED", "{}", target);
+            assert!(decision.requested_role.is_none());
+        }
+        req.metadata = None;
+        assert_eq!(evaluate_policy(&req, &ctx, 0).applied_rule, "ELEVATION_DENIED");
+    }
+
+    #[test]
+    fn test_audit_sampling_is_deterministic() {
+        let (req, ctx) = make_context("user", false, 10);
+        let sampled = |percent: u8, ts: u64| {
+            let config = PolicyConfig {
+                audit_sample_percent: percent,
+                ..PolicyConfig::default()
+            };
+            evaluate_policy_with_config(&config, &req, &ctx, ts).audit_required
+        };
+        assert!((0..200).all(|ts| !sampled(0, ts)));
+        assert!((0..200).all(|ts| sampled(100, ts)));
+
+        let hits: Vec<u64> = (0..1000).filter(|&ts| sampled(10, ts)).collect();
+        assert!((50..150).contains(&hits.len()), "{} sampled", hits.len());
+        assert!(hits.iter().all(|&ts| sampled(10, ts)));
+
+        let config = PolicyConfig {
+            audit_sample_percent: 100,
+            ..PolicyConfig::default()
+        };
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert!(decision.reason.contains("Sampled for audit"));
+    }
+
+    #[test]
+    fn test_classify_action_name() {
+        let classify = |action: &str| -> serde_json::Value {
+            serde_json::from_str(&classify_action_name(action)).unwrap()
+        };
+        assert_eq!(
+            classify("delete_user_data"),
+            serde_json::json!({"category": "blocked", "blocked": true})
+        );
+        assert_eq!(classify("read_public_cache")["category"], "read");
+        assert_eq!(classify("write_ai_suggestion")["category"], "write");
+        assert_eq!(classify("schedule_background_task")["category"], "system");
+        let unknown = classify("launch_rockets");
+        assert_eq!(unknown["category"], "unknown");
+        assert_eq!(unknown["blocked"], false);
+    }
+
+    #[test]
+    fn test_permit_margin_reports_closest_threshold() {
+        let (req, ctx) = make_context("user", false, 49);
+        let decision = evaluate_policy(&req, &ctx, 0);
+        assert!(decision.permitted);
+        assert_eq!(decision.margin, Some(2));
+
+        // Four of five failed attempts leaves 20% before lockout
+        let (req, mut ctx) = make_context("user", false, 0);
+        ctx.failed_attempts_last_hour = 4;
+        assert_eq!(evaluate_policy(&req, &ctx, 0).margin, Some(20));
+
+        // Session age only counts for session-checked actions
+        let (mut req, mut ctx) = make_context("power_user", true, 0);
+        ctx.session_age_seconds = MAX_SESSION_AGE_SENSITIVE * 9 / 10;
+        assert_eq!(evaluate_policy(&req, &ctx, 0).margin, Some(100));
+        req.action = "write_ai_suggestion".to_string();
+        assert_eq!(evaluate_policy(&req, &ctx, 0).margin, Some(10));
+
+        req.risk_score = 90;
+        assert_eq!(evaluate_policy(&req, &ctx, 0).margin, None);
+    }
+
+    #[test]
+    fn test_strict_fields_reject_unknown_keys() {
+        let strict = PolicyConfig { strict_fields: true, ..PolicyConfig::default() };
+        let (req, ctx) = make_context("user", false, 10);
+        let mut request = serde_json::to_value(&req).unwrap();
+        request["debug"] = serde_json::json!(true);
+        let context = serde_json::to_string(&ctx).unwrap();
+        let index = ActionIndex::new(&strict);
+
+        let decision =
+            validate_json_with_config(&strict, &index, &request.to_string(), &context, 0);
+        assert!(!decision.permitted);
+        assert_eq!(decision.applied_rule, "UNKNOWN_FIELD");
+        assert!(decision.reason.contains("'debug' in request"));
+
+        let mut extra = serde_json::to_value(&ctx).unwrap();
+        extra["is_admin"] = serde_json::json!(true);
+        let request = serde_json::to_string(&req).unwrap();
+        let decision =
+            validate_json_with_config(&strict, &index, &request, &extra.to_string(), 0);
+        assert_eq!(decision.applied_rule, "UNKNOWN_FIELD");
+
+        // Lenient by default
+        let (config, index) = &default_policy();
+        let decision = validate_json_with_config(config, index, &request, &extra.to_string(), 0);
+        assert!(decision.permitted);
+    }
+
+    #[test]
+    fn test_strict_field_lists_match_wire_types() {
+        let (mut req, mut ctx) = make_context("user", false, 10);
+        req.metadata = Some(serde_json::json!({}));
+        ctx.account_age_seconds = Some(1);
+        ctx.session_id = Some("s".to_string());
+        ctx.threat_score = Some(1);
+        ctx.oldest_failure_age_seconds = Some(1);
+        ctx.mfa_age_seconds = Some(1);
+        ctx.second_approver_id = Some("u".to_string());
+        ctx.second_approver_role = Some("r".to_string());
+        ctx.concurrent_sessions = Some(1);
+        let keys = |value: serde_json::Value| -> Vec<String> {
+            value.as_object().unwrap().keys().cloned().collect()
+        };
+        let sorted = |fields: &[&str]| -> Vec<String> {
+            let mut fields: Vec<String> = fields.iter().map(|f| f.to_string()).collect();
+            fields.sort();
+            fields
+        };
+        assert_eq!(keys(serde_json::to_value(&req).unwrap()), sorted(REQUEST_FIELDS));
+        assert_eq!(keys(serde_json::to_value(&ctx).unwrap()), sorted(CONTEXT_FIELDS));
+    }
+
+    #[test]
+    fn test_approval_token_lifts_risk_denial_for_bound_request() {
+        let mut config = PolicyConfig::default();
+        config.approval_tokens.insert(
+            config::sha256_hex(b"token-1"),
+            ApprovalToken {
+                action: "write_ai_suggestion".to_string(),
+                user_id: "user-123".to_string(),
+                expires_at_ms: 1_000,
+            },
+        );
+        let (mut req, ctx) = make_context("power_user", true, 90);
+        req.action = "write_ai_suggestion".to_string();
+        req.approval_token = Some("token-1".to_string());
+
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert!(decision.permitted);
+        assert_eq!(decision.applied_rule, "PRE_APPROVED");
+        assert!(decision.audit_required);
+        assert!(decision.reason.contains("overriding RISK_SCORE_EXCEEDED"));
+
+        // Expired, unknown, or bound to another user or action: normal evaluation
+        let expired = evaluate_policy_with_config(&config, &req, &ctx, 1_000);
+        assert_eq!(expired.applied_rule, "RISK_SCORE_EXCEEDED");
+        let unknown = AiRequest { approval_token: Some("token-2".to_string()), ..req.clone() };
+        let decision = evaluate_policy_with_config(&config, &unknown, &ctx, 0);
+        assert_eq!(decision.applied_rule, "RISK_SCORE_EXCEEDED");
+        let other_user = AiRequest { user_id: Some("user-999".to_string()), ..req.clone() };
+        assert!(!evaluate_policy_with_config(&config, &other_user, &ctx, 0).permitted);
+        let other_action = AiRequest { action: "write_notification".to_string(), ..req.clone() };
+        assert!(!evaluate_policy_with_config(&config, &other_action, &ctx, 0).permitted);
+    }
+
+    #[test]
+    fn test_approval_token_never_overrides_hard_block() {
+        let mut config = PolicyConfig::default();
+        config.approval_tokens.insert(
+            config::sha256_hex(b"token-1"),
+            ApprovalToken {
+                action: "delete_user_data".to_string(),
+                user_id: "user-123".to_string(),
+                expires_at_ms: 1_000,
+            },
+        );
+        assert!(config.validate().is_err());
+        let (mut req, ctx) = make_context("super_admin", true, 90);
+        req.action = "delete_user_data".to_string();
+        req.approval_token = Some("token-1".to_string());
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "HARD_BLOCK");
+    }
+
+    #[test]
+    fn test_approval_token_lifts_only_risk_and_role() {
+        let token = |action: &str| {
+            let mut config = PolicyConfig::default();
+            config.approval_tokens.insert(
+                config::sha256_hex(b"token-1"),
+                ApprovalToken {
+                    action: action.to_string(),
+                    user_id: "user-123".to_string(),
+                    expires_at_ms: 1_000,
+                },
+            );
+            config
+        };
+        let (mut req, mut ctx) = make_context("power_user", true, 99);
+        req.approval_token = Some("token-1".to_string());
+
+        // A lockout still stands behind the lifted risk ceiling
+        let config = token("write_ai_suggestion");
+        req.action = "write_ai_suggestion".to_string();
+        ctx.failed_attempts_last_hour = 50;
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert!(!decision.permitted);
+        assert_eq!(decision.applied_rule, "ACCOUNT_LOCKOUT");
+
+        // So does the whitelist, and such a token is refused up front
+        let config = token("launch_rockets");
+        assert!(config.validate().is_err());
+        req.action = "launch_rockets".to_string();
+        ctx.failed_attempts_last_hour = 0;
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert!(!decision.permitted);
+        assert_eq!(decision.applied_rule, "NOT_IN_WHITELIST");
+
+        // A role denial is lifted like a risk denial
+        let config = token("clear_expired_cache");
+        req.action = "clear_expired_cache".to_string();
+        req.risk_score = 10;
+        ctx.user_role = "user".to_string();
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "PRE_APPROVED");
+        assert!(decision.reason.contains("overriding INSUFFICIENT_ROLE"));
+    }
+
+    #[test]
+    fn test_mfa_required_overrides_category_default() {
+        let config = PolicyConfig {
+            mfa_required: Some(vec![
+                "write_notification".to_string(),
+                "read_public_cache".to_string(),
+            ]),
+            ..PolicyConfig::default()
+        };
+        let (mut req, ctx) = make_context("power_user", false, 10);
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "MFA_REQUIRED");
+
+        req.action = "write_notification".to_string();
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "MFA_REQUIRED");
+        assert!(decision.reason.contains("'write_notification'"));
+
+        // Dropped from the set, a system action no longer needs MFA
+        req.action = "clear_expired_cache".to_string();
+        assert!(evaluate_policy_with_config(&config, &req, &ctx, 0).permitted);
+        assert_eq!(evaluate_policy(&req, &ctx, 0).applied_rule, "MFA_REQUIRED");
+    }
+
+    #[test]
+    fn test_lockout_reports_retry_after() {
+        let (req, mut ctx) = make_context("user", false, 10);
+        ctx.failed_attempts_last_hour = MAX_FAILED_ATTEMPTS;
+        let decision = evaluate_policy(&req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "ACCOUNT_LOCKOUT");
+        assert_eq!(decision.retry_after_ms, None);
+
+        ctx.oldest_failure_age_seconds = Some(3000);
+        assert_eq!(evaluate_policy(&req, &ctx, 0).retry_after_ms, Some(600_000));
+
+        // More than one failure must expire: bounded by the full window
+        ctx.failed_attempts_last_hour = MAX_FAILED_ATTEMPTS + 2;
+        assert_eq!(evaluate_policy(&req, &ctx, 0).retry_after_ms, Some(3_600_000));
+    }
+
+    #[test]
+    fn test_trusted_network_required_ignores_risk() {
+        let config = PolicyConfig {
+            trusted_network_required: vec!["write_ai_suggestion".to_string()],
+            ..PolicyConfig::default()
+        };
+        let (mut req, mut ctx) = make_context("power_user", true, 0);
+        req.action = "write_ai_suggestion".to_string();
+        assert!(evaluate_policy_with_config(&config, &req, &ctx, 0).permitted);
+
+        ctx.trusted_network = false;
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert!(!decision.permitted);
+        assert_eq!(decision.applied_rule, "TRUSTED_NETWORK_REQUIRED");
+        assert!(decision.audit_required);
+
+        // Unlisted actions are unaffected off-network
+        req.action = "write_notification".to_string();
+        assert!(evaluate_policy_with_config(&config, &req, &ctx, 0).permitted);
+    }
+
+    // Compares whole decisions, and timings differ run to run
+    #[cfg(not(feature = "trace-timing"))]
+    #[test]
+    fn test_typed_and_json_paths_agree() {
+        let mut deep = serde_json::json!(1);
+        for _ in 0..32 {
+            deep = serde_json::json!([deep]);
+        }
+        for (action, risk, metadata) in [
+            ("read_public_cache", 10, None),
+            ("write_ai_suggestion", 90, None),
+            ("delete_user_data", 0, None),
+            ("read_public_cache", 0, Some(deep)),
+        ] {
+            let (mut req, ctx) = make_context("power_user", false, risk);
+            req.action = action.to_string();
+            req.metadata = metadata;
+            let typed = serde_json::to_value(evaluate_policy(&req, &ctx, 7)).unwrap();
+            let json = validate_ai_action(
+                &serde_json::to_string(&req).unwrap(),
+                &serde_json::to_string(&ctx).unwrap(),
+                7,
+            );
+            assert_eq!(typed, serde_json::from_str::<serde_json::Value>(&json).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_system_action_min_role() {
+        let mut config = PolicyConfig::default();
+        config
+            .system_action_min_role
+            .insert("schedule_background_task".to_string(), "org_admin".to_string());
+        assert!(config.validate().is_ok());
+
+        let (mut req, ctx) = make_context("power_user", true, 10);
+        req.action = "schedule_background_task".to_string();
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "INSUFFICIENT_ROLE");
+        assert!(decision.reason.contains("Requires 'org_admin'"));
+        let (_, admin) = make_context("org_admin", true, 10);
+        assert!(evaluate_policy_with_config(&config, &req, &admin, 0).permitted);
+
+        // Unlisted system actions keep the power_user default
+        req.action = "update_search_ranking".to_string();
+        assert!(evaluate_policy_with_config(&config, &req, &ctx, 0).permitted);
+        let (_, user) = make_context("user", true, 10);
+        let decision = evaluate_policy_with_config(&config, &req, &user, 0);
+        assert!(decision.reason.contains("Requires 'power_user'"));
+
+        config
+            .system_action_min_role
+            .insert("compress_old_logs".to_string(), "root".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_unranked_system_min_role_is_never_met() {
+        let config = PolicyConfig {
+            role_hierarchy: vec!["guest".to_string(), "user".to_string(), "admin".to_string()],
+            authenticated_read_min_role: "user".to_string(),
+            max_elevation_role: "admin".to_string(),
+            dual_control_min_role: "admin".to_string(),
+            ..PolicyConfig::default()
+        };
+        let error = config.validate().unwrap_err();
+        assert!(error.starts_with("System action"), "{}", error);
+
+        for role in ["guest", "admin"] {
+            let (mut req, ctx) = make_context(role, true, 10);
+            for action in ["schedule_background_task", "compress_old_logs"] {
+                req.action = action.to_string();
+                let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+                assert!(!decision.permitted, "{} {}", role, action);
+                assert_eq!(decision.applied_rule, "INSUFFICIENT_ROLE");
+            }
+        }
+    }
+
+    #[cfg(feature = "trace-timing")]
+    #[test]
+    fn test_rule_timings_follow_cascade_order() {
+        let (mut req, ctx) = make_context("power_user", true, 10);
+        req.action = "write_ai_suggestion".to_string();
+        let decision = evaluate_policy(&req, &ctx, 0);
+        let stages: Vec<&str> = decision.rule_timings.iter().map(|t| t.stage.as_str()).collect();
+        assert_eq!(stages.first(), Some(&"PANIC MODE"));
+        assert_eq!(stages.last(), Some(&"PERMITTED"));
+        assert!(decision.rule_timings.iter().all(|t| t.elapsed_ms >= 0.0));
+
+        // A hard block stops the trace at its rule
+        req.action = "delete_user_data".to_string();
+        let stages: Vec<String> =
+            evaluate_policy(&req, &ctx, 0).rule_timings.into_iter().map(|t| t.stage).collect();
+        assert_eq!(stages, ["PANIC MODE", "RULE 0", "RULE 1"]);
+    }
+
+    #[test]
+    fn test_panic_mode_denies_everything() {
+        let config = PolicyConfig { panic_mode: true, ..PolicyConfig::default() };
+        for action in ["read_public_cache", "write_ai_suggestion", "delete_user_data", "nope"] {
+            let (mut req, ctx) = make_context("super_admin", true, 0);
+            req.action = action.to_string();
+            let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+            assert!(!decision.permitted);
+            assert_eq!(decision.applied_rule, "PANIC_MODE");
+            assert_eq!(decision.iso_control, "A.16.1");
+        }
+    }
+
+    #[test]
+    fn test_ruleset_graph_lists_known_rules_in_cascade_order() {
+        let graph: serde_json::Value = serde_json::from_str(&ruleset_graph()).unwrap();
+        let nodes = graph["nodes"].as_array().unwrap();
+        assert_eq!(nodes[0]["stage"], "INPUT");
+        assert_eq!(nodes[1]["stage"], "PANIC MODE");
+        for node in nodes {
+            for rule in node["rules"].as_array().unwrap() {
+                assert!(APPLIED_RULES.contains(&rule.as_str().unwrap()), "{}", rule);
+            }
+        }
+        let enabled = |nodes: &[serde_json::Value], stage: &str| {
+            nodes.iter().find(|n| n["stage"] == stage).unwrap()["enabled"].clone()
+        };
+        assert_eq!(enabled(nodes, "PANIC MODE"), false);
+        assert_eq!(enabled(nodes, "RULE 1"), true);
+
+        // Conditions and switches come from the configuration in force
+        let config =
+            PolicyConfig { panic_mode: true, max_risk_score: 42, ..PolicyConfig::default() };
+        let nodes = ruleset_nodes(&config);
+        assert_eq!(enabled(&nodes, "PANIC MODE"), true);
+        let risk = nodes.iter().find(|n| n["stage"] == "RULE 2").unwrap();
+        assert!(risk["condition"].as_str().unwrap().contains("42"));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_builtin_rules_match_graph_and_pin_hard_block() {
+        let nodes = ruleset_nodes(&PolicyConfig::default());
+        let graph: Vec<(&str, Vec<&str>)> = nodes
+            .iter()
+            .map(|n| {
+                let rules = n["rules"].as_array().unwrap().iter();
+                (n["stage"].as_str().unwrap(), rules.map(|r| r.as_str().unwrap()).collect())
+            })
+            .collect();
+        let builtins: Vec<(&str, Vec<&str>)> =
+            BUILTIN_RULES.iter().map(|rule| (rule.stage, rule.rules.to_vec())).collect();
+        // The cascade is the graph between input checks and pre-approval
+        assert_eq!(builtins, graph[1..graph.len() - 1]);
+
+        let pinned: Vec<&str> = BUILTIN_RULES[..PINNED_RULES].iter().map(|r| r.stage).collect();
+        assert_eq!(pinned, ["PANIC MODE", "RULE 0", "RULE 1"]);
+        assert_eq!(denying_stage("HARD_BLOCK"), Some(PINNED_RULES - 1));
+        assert_eq!(denying_stage("QUOTA_EXCEEDED"), None);
+    }
+
+    #[cfg(feature = "trace-timing")]
+    #[test]
+    fn test_ruleset_graph_matches_trace_stages() {
+        let (mut req, ctx) = make_context("power_user", true, 10);
+        req.action = "write_ai_suggestion".to_string();
+        let decision = evaluate_policy(&req, &ctx, 0);
+        let nodes = ruleset_nodes(&PolicyConfig::default());
+        let mut stages = nodes.iter().map(|n| n["stage"].as_str().unwrap());
+        for timing in &decision.rule_timings {
+            assert!(stages.any(|stage| stage == timing.stage), "{}", timing.stage);
+        }
     }
 
     #[test]
-    fn test_high_risk_score_denied() {
-        let (mut req, ctx) = make_context("user", false, 75);
-        req.action = "read_public_cache".to_string();
+    fn test_user_scoped_writes_only_target_authenticated_user() {
+        let (mut req, mut ctx) = make_context("user", false, 10);
+        req.action = "write_user_preferences".to_string();
+        assert!(evaluate_policy(&req, &ctx, 0).permitted);
+
+        req.user_id = Some("user-999".to_string());
         let decision = evaluate_policy(&req, &ctx, 0);
         assert!(!decision.permitted);
-        assert_eq!(decision.applied_rule, "RISK_SCORE_EXCEEDED");
+        assert_eq!(decision.applied_rule, "CROSS_USER_WRITE");
+        assert!(decision.audit_required);
+
+        // No authenticated subject fails closed, even for the request's own user
+        req.user_id = Some("user-123".to_string());
+        ctx.authenticated_user_id = None;
+        assert_eq!(evaluate_policy(&req, &ctx, 0).applied_rule, "CROSS_USER_WRITE");
     }
 
     #[test]
-    fn test_unknown_action_denied() {
-        let (mut req, ctx) = make_context("super_admin", true, 0);
-        req.action = "some_unknown_action".to_string();
+    fn test_cross_user_exempt_role_by_rank() {
+        let config = PolicyConfig {
+            cross_user_exempt_role: Some("org_admin".to_string()),
+            ..PolicyConfig::default()
+        };
+        assert!(config.validate().is_ok());
+        let (mut req, ctx) = make_context("super_admin", true, 10);
+        req.action = "write_user_preferences".to_string();
+        req.user_id = Some("user-999".to_string());
+        assert!(evaluate_policy_with_config(&config, &req, &ctx, 0).permitted);
+
+        let (mut req, ctx) = make_context("power_user", true, 10);
+        req.action = "write_user_preferences".to_string();
+        req.user_id = Some("user-999".to_string());
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "CROSS_USER_WRITE");
+
+        let config = PolicyConfig {
+            cross_user_exempt_role: Some("overlord".to_string()),
+            ..PolicyConfig::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_rule_precedence_follows_rule_order() {
+        type Trip = fn(&mut PolicyConfig, &mut AiRequest, &mut SecurityContext);
+        // Action-keyed settings cover both the base system action and the
+        // unlisted one NOT_IN_WHITELIST switches to
+        fn both() -> Vec<String> {
+            vec!["clear_expired_cache".to_string(), "format_disk".to_string()]
+        }
+        let trips: &[(&str, Trip)] = &[
+            ("PANIC_MODE", |c, _, _| c.panic_mode = true),
+            ("INTEGRITY_FAILURE", |c, _, _| c.expected_blocklist_checksum = Some("0".repeat(64))),
+            ("HARD_BLOCK", |_, r, _| r.action = "delete_user_data".to_string()),
+            ("CONTEXT_IMPLAUSIBLE", |_, _, x| x.failed_attempts_last_hour = 1_000_000),
+            ("MAINTENANCE_FREEZE", |c, _, _| c.freeze_writes = true),
+            ("RESOURCE_DENYLISTED", |c, r, _| {
+                c.resource_deny_patterns = vec!["vault:*".to_string()];
+                r.target_resource = "vault:root".to_string();
+            }),
+            ("DEPRECATED_MODULE", |c, r, _| {
+                c.deprecated_module_versions = vec!["*@1.2.0".to_string()];
+                r.requesting_module = "com.infinity-os.shell@1.2.0".to_string();
+            }),
+            ("DISALLOWED_METADATA", |c, r, _| {
+                c.allowed_metadata_keys = Some(Vec::new());
+                r.metadata = Some(serde_json::json!({ "unexpected": 1 }));
+            }),
+            ("MISSING_ORG_CONTEXT", |c, r, _| {
+                c.actions_requiring_org = both();
+                r.organisation_id = None;
+            }),
+            ("METADATA_REQUIRED", |c, r, _| {
+                c.actions_requiring_metadata = both();
+                r.metadata = Some(serde_json::Value::Null);
+            }),
+            ("INVALID_ARGUMENT", |c, _, _| {
+                let schema: BTreeMap<String, ArgumentSchema> =
+                    [("theme".to_string(), ArgumentSchema::default())].into();
+                for action in both() {
+                    c.action_arguments.insert(action, schema.clone());
+                }
+            }),
+            ("MISSING_TRACE_ID", |c, _, _| c.require_trace_id = true),
+            ("TRUSTED_NETWORK_REQUIRED", |c, _, x| {
+                c.trusted_network_required = both();
+                x.trusted_network = false;
+            }),
+            ("CROSS_USER_WRITE", |c, r, _| {
+                c.user_scoped_actions = both();
+                r.user_id = Some("user-999".to_string());
+            }),
+            ("RISK_SCORE_EXCEEDED", |_, r, _| r.risk_score = 90),
+            ("RISK_UNDERREPORTED", |c, _, _| {
+                c.min_risk_for_action = both().into_iter().map(|a| (a, 20)).collect();
+            }),
+            ("ACCOUNT_LOCKOUT", |_, _, x| x.failed_attempts_last_hour = 5),
+            ("ELEVATED_THREAT", |_, _, x| x.threat_score = Some(95)),
+            ("UNRECOGNIZED_ROLE", |_, _, x| x.user_role = "intern".to_string()),
+            ("SESSION_EXPIRED", |_, _, x| x.session_age_seconds = 100_000),
+            ("TOO_MANY_SESSIONS", |c, _, x| {
+                c.max_concurrent_sessions = Some(1);
+                x.concurrent_sessions = Some(2);
+            }),
+            ("MFA_REQUIRED", |c, _, x| {
+                c.mfa_required = None;
+                x.mfa_verified = false;
+            }),
+            ("MFA_REAUTH_REQUIRED", |c, _, x| {
+                c.max_mfa_age_seconds = Some(60);
+                x.mfa_age_seconds = Some(600);
+            }),
+            ("SENSITIVE_TARGET", |c, _, x| {
+                c.sensitive_resource_prefixes = vec!["cache:".to_string()];
+                c.mfa_required = Some(Vec::new());
+                x.mfa_verified = false;
+            }),
+            ("INSUFFICIENT_ROLE", |_, _, x| x.user_role = "user".to_string()),
+            ("NEW_ACCOUNT_RESTRICTION", |c, _, x| {
+                c.mfa_required = Some(Vec::new());
+                x.mfa_verified = false;
+                x.account_age_seconds = Some(60);
+            }),
+            ("DUAL_CONTROL_REQUIRED", |c, _, _| c.dual_control_actions = both()),
+            ("NOT_IN_WHITELIST", |_, r, _| r.action = "format_disk".to_string()),
+            ("METADATA_CONDITION_FAILED", |c, r, _| {
+                let condition = MetadataCondition { key: "rows".to_string(), max_value: 5.0 };
+                c.metadata_conditions =
+                    both().into_iter().map(|a| (a, condition.clone())).collect();
+                r.metadata = Some(serde_json::json!({ "rows": 10 }));
+            }),
+        ];
+        // The write-only rules (the trace check and 3b to 6a) gate whitelisted
+        // writes and system actions, so never coincide with NOT_IN_WHITELIST.
+        // Stale MFA needs verified MFA, which a new-account denial lacks.
+        let disjoint = |a: &str, b: &str| {
+            (b == "NOT_IN_WHITELIST"
+                && matches!(
+                    a,
+                    "MISSING_TRACE_ID"
+                        | "ELEVATED_THREAT"
+                        | "UNRECOGNIZED_ROLE"
+                        | "SESSION_EXPIRED"
+                        | "TOO_MANY_SESSIONS"
+                        | "MFA_REQUIRED"
+                        | "MFA_REAUTH_REQUIRED"
+                        | "SENSITIVE_TARGET"
+                        | "INSUFFICIENT_ROLE"
+                        | "NEW_ACCOUNT_RESTRICTION"
+                ))
+                // Both turn MFA off, which leaves MFA recency nothing to check
+                || (a == "MFA_REAUTH_REQUIRED"
+                    && matches!(b, "SENSITIVE_TARGET" | "NEW_ACCOUNT_RESTRICTION"))
+        };
+        let evaluate = |tripped: &[&(&str, Trip)]| {
+            let mut config =
+                PolicyConfig { consolidate_auth_requirements: false, ..PolicyConfig::default() };
+            let (mut req, mut ctx) = make_context("power_user", true, 10);
+            req.action = "clear_expired_cache".to_string();
+            // Later rules first, so an earlier rule's setup wins any overlap
+            for (_, trip) in tripped.iter().rev() {
+                trip(&mut config, &mut req, &mut ctx);
+            }
+            evaluate_policy_with_config(&config, &req, &ctx, 0).applied_rule
+        };
+        let position = |rule: &str| RULE_ORDER.iter().position(|r| *r == rule).unwrap();
+
+        assert_eq!(evaluate(&[]), "WHITELIST_APPROVED");
+        for (i, earlier) in trips.iter().enumerate() {
+            assert_eq!(evaluate(&[earlier]), earlier.0);
+            for later in &trips[i + 1..] {
+                assert!(position(earlier.0) < position(later.0));
+                if !disjoint(earlier.0, later.0) {
+                    assert_eq!(evaluate(&[earlier, later]), earlier.0, "vs {}", later.0);
+                }
+            }
+        }
+
+        // The documented example: blocked, risky and stale still reports HARD_BLOCK
+        let (mut req, mut ctx) = make_context("user", false, 99);
+        req.action = "delete_user_data".to_string();
+        ctx.session_age_seconds = u64::MAX;
+        assert_eq!(evaluate_policy(&req, &ctx, 0).applied_rule, "HARD_BLOCK");
+
+        // The published graph lists the same rules in the same order
+        let graph: Vec<String> = ruleset_nodes(&PolicyConfig::default())
+            .iter()
+            .flat_map(|n| n["rules"].as_array().unwrap().clone())
+            .map(|r| r.as_str().unwrap().to_string())
+            .collect();
+        let mut graph_rules = graph.iter();
+        for rule in RULE_ORDER {
+            assert!(graph_rules.any(|r| r == rule), "{}", rule);
+        }
+    }
+
+    #[test]
+    fn test_redact_reasons_masks_resource_and_user() {
+        let config = PolicyConfig {
+            redact_reasons: true,
+            resource_deny_patterns: vec!["vault:*".to_string()],
+            ..PolicyConfig::default()
+        };
+        let (mut req, ctx) = make_context("user", false, 10);
+        req.target_resource = "vault:root-token".to_string();
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "RESOURCE_DENYLISTED");
+        assert_eq!(decision.iso_control, "A.9.4.1");
+        assert!(!decision.reason.contains("vault:root-token"));
+        assert!(decision.reason.contains("[redacted]"));
+
+        let unredacted = PolicyConfig { redact_reasons: false, ..config };
+        let decision = evaluate_policy_with_config(&unredacted, &req, &ctx, 0);
+        assert!(decision.reason.contains("vault:root-token"));
+    }
+
+    #[test]
+    fn test_validate_ai_action_redacted_covers_every_branch() {
+        // A resource named like the action is echoed by every rule's reason
+        let request = |action: &str, user_id: &str| {
+            serde_json::json!({
+                "action": action,
+                "target_resource": action,
+                "risk_score": 10,
+                "requesting_module": "com.infinity-os.shell",
+                "user_id": user_id,
+            })
+            .to_string()
+        };
+        let context = r#"{"user_role":"user","mfa_verified":true,"session_age_seconds":300}"#;
+        for action in ["delete_user_data", "format_disk", "clear_expired_cache"] {
+            let redacted: PolicyDecision = serde_json::from_str(&validate_ai_action_redacted(
+                &request(action, "user-123"),
+                context,
+                0,
+            ))
+            .unwrap();
+            let raw: PolicyDecision =
+                serde_json::from_str(&validate_ai_action(&request(action, "user-123"), context, 0))
+                    .unwrap();
+            assert_eq!(redacted.applied_rule, raw.applied_rule);
+            assert!(raw.reason.contains(action), "{}", raw.reason);
+            assert!(!redacted.reason.contains(action), "{}", redacted.reason);
+        }
+    }
+
+    #[test]
+    fn test_composite_risk_from_weighted_components() {
+        let config = PolicyConfig {
+            risk_component_weights: [("uncertainty", 0.5), ("sensitivity", 0.3), ("blast", 0.2)]
+                .into_iter()
+                .map(|(name, weight)| (name.to_string(), weight))
+                .collect(),
+            ..PolicyConfig::default()
+        };
+        assert!(config.validate().is_ok());
+        let components = |values: [u8; 3]| {
+            ["uncertainty", "sensitivity", "blast"]
+                .into_iter()
+                .map(str::to_string)
+                .zip(values)
+                .collect::<BTreeMap<String, u8>>()
+        };
+        // A low flat score no longer hides a high composite
+        let (mut req, ctx) = make_context("user", false, 10);
+        req.risk_components = Some(components([90, 80, 40]));
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "RISK_SCORE_EXCEEDED");
+        assert_eq!(decision.effective_risk_score, Some(77));
+        assert_eq!(decision.risk_components, req.risk_components);
+
+        req.risk_components = Some(components([20, 10, 0]));
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert!(decision.permitted);
+        assert_eq!(decision.effective_risk_score, Some(13));
+        assert!(!decision.reason.contains("trust decay"));
+
+        // An incomplete breakdown falls back to the flat score
+        let mut partial = components([90, 80, 40]);
+        partial.remove("blast");
+        req.risk_components = Some(partial);
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert!(decision.permitted);
+        assert_eq!(decision.effective_risk_score, Some(10));
+
+        // Without configured weights components are ignored entirely
         let decision = evaluate_policy(&req, &ctx, 0);
+        assert!(decision.effective_risk_score.is_none() && decision.risk_components.is_none());
+
+        let negative = PolicyConfig {
+            risk_component_weights: [("blast".to_string(), -1.0)].into_iter().collect(),
+            ..PolicyConfig::default()
+        };
+        assert!(negative.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_and_audit_matches_validate_ai_action() {
+        let request = |action: &str| {
+            serde_json::json!({
+                "action": action,
+                "target_resource": "cache:public",
+                "risk_score": 10,
+                "requesting_module": "com.infinity-os.shell",
+                "user_id": "user-123",
+                "approval_token": "secret",
+            })
+            .to_string()
+        };
+        let context = r#"{"user_role":"user","session_age_seconds":300}"#;
+        let requests = [request("read_public_cache"), request("delete_user_data"), "{".to_string()];
+        for request in requests {
+            let raw = validate_and_audit(&request, context, 7);
+            let combined: serde_json::Value = serde_json::from_str(&raw).unwrap();
+            let end = raw.rfind(r#","audit_record":"#).unwrap();
+            let decision_bytes = &raw[r#"{"decision":"#.len()..end];
+            // Same bytes, not merely the same JSON value (timings differ per run)
+            #[cfg(not(feature = "trace-timing"))]
+            assert_eq!(decision_bytes, validate_ai_action(&request, context, 7));
+            let decision: PolicyDecision = serde_json::from_str(decision_bytes).unwrap();
+            let record = &combined["audit_record"];
+            assert_eq!(record.is_null(), !decision.audit_required);
+            if decision.audit_required {
+                assert_eq!(record["applied_rule"], decision.applied_rule.as_str());
+                let digest = config::sha256_hex(decision_bytes.as_bytes());
+                assert_eq!(record["decision_sha256"], digest);
+                let logged = &record["request"];
+                assert!(logged.is_null() || logged["approval_token"].is_null());
+            }
+        }
+        let blocked: serde_json::Value = serde_json::from_str(&validate_and_audit(
+            &request("delete_user_data"),
+            context,
+            7,
+        ))
+        .unwrap();
+        assert_eq!(blocked["audit_record"]["request"]["action"], "delete_user_data");
+    }
+
+    #[test]
+    fn test_metadata_required_rejects_absent_and_null() {
+        let config = PolicyConfig {
+            actions_requiring_metadata: vec!["write_analytics_event".to_string()],
+            ..PolicyConfig::default()
+        };
+        let (mut req, ctx) = make_context("user", false, 10);
+        req.action = "write_analytics_event".to_string();
+        for metadata in [None, Some(serde_json::Value::Null)] {
+            req.metadata = metadata;
+            let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+            assert_eq!(decision.applied_rule, "METADATA_REQUIRED");
+            assert_eq!(decision.iso_control, "A.8.16");
+        }
+        req.metadata = Some(serde_json::json!({ "event": "page_view" }));
+        assert!(evaluate_policy_with_config(&config, &req, &ctx, 0).permitted);
+
+        // Unlisted actions ignore metadata presence as before
+        req.action = "read_public_cache".to_string();
+        req.metadata = None;
+        assert!(evaluate_policy_with_config(&config, &req, &ctx, 0).permitted);
+    }
+
+    #[test]
+    fn test_audit_retention_by_rule_then_action() {
+        let config = PolicyConfig {
+            audit_retention_days: [("HARD_BLOCK", 365), ("write_analytics_event", 30)]
+                .into_iter()
+                .map(|(key, days)| (key.to_string(), days))
+                .collect(),
+            ..PolicyConfig::default()
+        };
+        let (mut req, mut ctx) = make_context("user", false, 10);
+        req.action = "delete_user_data".to_string();
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert_eq!(decision.audit_retention_days, Some(365));
+
+        // An unaudited permit omits the hint; an audited one picks up its action's
+        req.action = "write_analytics_event".to_string();
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert!(!decision.audit_required && decision.audit_retention_days.is_none());
+        ctx.mfa_verified = false;
+        ctx.account_age_seconds = Some(60);
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "NEW_ACCOUNT_RESTRICTION");
+        assert_eq!(decision.audit_retention_days, Some(30));
+
+        // Unmapped actions omit it too
+        req.action = "write_ai_suggestion".to_string();
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert!(decision.audit_required && decision.audit_retention_days.is_none());
+    }
+
+    #[test]
+    fn test_lockout_exempt_reads_stay_available() {
+        let config = PolicyConfig {
+            lockout_exempt_reads: vec!["read_notification_settings".to_string()],
+            ..PolicyConfig::default()
+        };
+        assert!(config.validate().is_ok());
+        let (mut req, mut ctx) = make_context("user", false, 10);
+        ctx.failed_attempts_last_hour = 10;
+        req.action = "read_notification_settings".to_string();
+        assert_eq!(evaluate_policy(&req, &ctx, 0).applied_rule, "ACCOUNT_LOCKOUT");
+        assert!(evaluate_policy_with_config(&config, &req, &ctx, 0).permitted);
+
+        // Every other action stays locked
+        req.action = "read_public_cache".to_string();
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "ACCOUNT_LOCKOUT");
+
+        // Writes, blocked actions and sensitive reads cannot be exempted
+        for action in ["write_user_preferences", "delete_user_data"] {
+            let config = PolicyConfig {
+                lockout_exempt_reads: vec![action.to_string()],
+                ..PolicyConfig::default()
+            };
+            assert!(config.validate().is_err());
+        }
+        let config = PolicyConfig {
+            authenticated_reads: vec!["read_notification_settings".to_string()],
+            ..config
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_argument_schema_names_offending_argument() {
+        let config: PolicyConfig = serde_json::from_value(serde_json::json!({
+            "action_arguments": {
+                "set_ui_theme": {
+                    "theme": { "allowed_values": ["light", "dark"] },
+                    "font_scale": { "min": 0.5, "max": 2.0 }
+                }
+            }
+        }))
+        .unwrap();
+        assert!(config.validate().is_ok());
+        let (mut req, ctx) = make_context("user", false, 10);
+        req.action = "set_ui_theme".to_string();
+        let check = |metadata: serde_json::Value| {
+            let req = AiRequest { metadata: Some(metadata), ..req.clone() };
+            evaluate_policy_with_config(&config, &req, &ctx, 0)
+        };
+
+        let decision = check(serde_json::json!({ "theme": "dark", "font_scale": 1.25 }));
+        assert_ne!(decision.applied_rule, "INVALID_ARGUMENT");
+        assert!(decision.invalid_argument.is_none());
+        for (metadata, offending) in [
+            (serde_json::json!({ "theme": "neon", "font_scale": 1 }), "theme"),
+            (serde_json::json!({ "theme": "light", "font_scale": 3 }), "font_scale"),
+            (serde_json::json!({ "theme": "light", "font_scale": "big" }), "font_scale"),
+            (serde_json::json!({ "theme": "light" }), "font_scale"),
+        ] {
+            let decision = check(metadata);
+            assert_eq!(decision.applied_rule, "INVALID_ARGUMENT");
+            assert_eq!(decision.invalid_argument.as_deref(), Some(offending));
+            assert!(decision.reason.contains(offending));
+        }
+
+        // No schema, no check; inverted bounds are refused
+        req.action = "write_user_preferences".to_string();
+        req.metadata = Some(serde_json::json!({ "theme": "neon" }));
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert_ne!(decision.applied_rule, "INVALID_ARGUMENT");
+        let inverted = ArgumentSchema { min: Some(2.0), max: Some(1.0), ..Default::default() };
+        let mut config = config;
+        let schema = [("x".to_string(), inverted)].into();
+        config.action_arguments.insert("set_ui_theme".to_string(), schema);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_decision_id_is_predictable_from_inputs() {
+        let (req, ctx) = make_context("user", false, 10);
+        let decision = evaluate_policy(&req, &ctx, 1000);
+        assert_eq!(decision.decision_id, decision_id(&req, 1000));
+        assert_eq!(decision.decision_id.len(), 32);
+        assert!(decision.decision_id.bytes().all(|b| b.is_ascii_hexdigit()));
+        assert_eq!(evaluate_policy(&req, &ctx, 1000).decision_id, decision.decision_id);
+
+        // Timestamp and request both feed it; the approval token does not
+        assert_ne!(decision_id(&req, 1001), decision.decision_id);
+        let other = AiRequest { risk_score: 11, ..req.clone() };
+        assert_ne!(decision_id(&other, 1000), decision.decision_id);
+        let approved = AiRequest { approval_token: Some("token-1".to_string()), ..req.clone() };
+        assert_eq!(decision_id(&approved, 1000), decision.decision_id);
+
+        // Key order and whitespace in the JSON do not matter
+        let json = serde_json::to_string(&req).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let pretty = serde_json::to_string_pretty(&value).unwrap();
+        let predicted: serde_json::Value =
+            serde_json::from_str(&predict_decision_id(&pretty, 1000)).unwrap();
+        assert_eq!(predicted["decision_id"], decision.decision_id.as_str());
+        assert!(predict_decision_id("{", 1000).contains("error"));
+    }
+
+    #[test]
+    fn test_deprecated_module_versions_denied() {
+        let config = PolicyConfig {
+            deprecated_module_versions: vec!["com.infinity-os.shell@1.2.*".to_string()],
+            ..PolicyConfig::default()
+        };
+        let (mut req, ctx) = make_context("user", false, 10);
+        req.requesting_module = "com.infinity-os.shell@1.2.0".to_string();
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
         assert!(!decision.permitted);
-        assert_eq!(decision.applied_rule, "NOT_IN_WHITELIST");
+        assert_eq!(decision.applied_rule, "DEPRECATED_MODULE");
+        assert!(decision.audit_required);
+        assert!(decision.reason.contains("1.2.0"));
+        assert!(evaluate_policy(&req, &ctx, 0).permitted);
+
+        // Other versions, unversioned modules and empty suffixes pass
+        for module in [
+            "com.infinity-os.shell@1.3.0",
+            "com.infinity-os.shell",
+            "com.infinity-os.shell@",
+        ] {
+            req.requesting_module = module.to_string();
+            assert!(evaluate_policy_with_config(&config, &req, &ctx, 0).permitted, "{}", module);
+        }
     }
 
     #[test]
-    fn test_system_action_requires_mfa() {
-        let (mut req, ctx) = make_context("power_user", false, 10);
+    fn test_stale_mfa_requires_reauth() {
+        let config = PolicyConfig {
+            max_mfa_age_seconds: Some(900),
+            max_mfa_age_for_action: [("clear_expired_cache".to_string(), 300)].into(),
+            ..PolicyConfig::default()
+        };
+        assert!(config.validate().is_ok());
+        let (mut req, mut ctx) = make_context("power_user", true, 10);
+        req.action = "clear_expired_cache".to_string();
+
+        // Unknown age is trusted unless strict; fresh MFA passes
+        assert!(evaluate_policy_with_config(&config, &req, &ctx, 0).permitted);
+        ctx.mfa_age_seconds = Some(300);
+        assert!(evaluate_policy_with_config(&config, &req, &ctx, 0).permitted);
+
+        // The per-action limit wins over the global one
+        ctx.mfa_age_seconds = Some(600);
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "MFA_REAUTH_REQUIRED");
+        assert_eq!(decision.step_up_required.unwrap().method, "mfa");
         req.action = "schedule_background_task".to_string();
-        let decision = evaluate_policy(&req, &ctx, 0);
-        assert!(!decision.permitted);
+        assert!(evaluate_policy_with_config(&config, &req, &ctx, 0).permitted);
+
+        // Strict mode demands the age; unverified MFA is still MFA_REQUIRED
+        let strict = PolicyConfig { require_mfa_age: true, ..config.clone() };
+        ctx.mfa_age_seconds = None;
+        let decision = evaluate_policy_with_config(&strict, &req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "MFA_REAUTH_REQUIRED");
+        ctx.mfa_verified = false;
+        let decision = evaluate_policy_with_config(&strict, &req, &ctx, 0);
         assert_eq!(decision.applied_rule, "MFA_REQUIRED");
+
+        // Only MFA-gated actions may carry a limit
+        let config = PolicyConfig {
+            max_mfa_age_for_action: [("write_user_preferences".to_string(), 60)].into(),
+            ..PolicyConfig::default()
+        };
+        assert!(config.validate().is_err());
     }
 
     #[test]
-    fn test_account_lockout() {
-        let (req, mut ctx) = make_context("user", false, 10);
-        ctx.failed_attempts_last_hour = 10;
-        let decision = evaluate_policy(&req, &ctx, 0);
+    fn test_check_allowlist_safety() {
+        let allowlist = serde_json::json!([
+            "read_public_cache",
+            "delete_user_data",
+            "modify_*",
+            "*_cache",
+            "delete_draft",
+            "read_vault_status",
+        ]);
+        let report: serde_json::Value =
+            serde_json::from_str(&check_allowlist_safety(&allowlist.to_string())).unwrap();
+        assert_eq!(report["safe"], false);
+        let flagged = |kind: &str| -> Vec<String> {
+            report[kind]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|entry| entry["action"].as_str().unwrap().to_string())
+                .collect()
+        };
+        // Errors are not repeated as warnings
+        assert_eq!(flagged("errors"), vec!["delete_user_data", "modify_*"]);
+        assert_eq!(flagged("warnings"), vec!["delete_draft", "read_vault_status"]);
+        assert!(report["errors"][1]["message"]
+            .as_str()
+            .unwrap()
+            .contains("modify_security_policy"));
+
+        let report: serde_json::Value =
+            serde_json::from_str(&check_allowlist_safety(r#"["read_public_cache"]"#)).unwrap();
+        assert_eq!(report, serde_json::json!({ "safe": true, "errors": [], "warnings": [] }));
+        assert!(check_allowlist_safety("{}").contains("error"));
+    }
+
+    #[test]
+    fn test_dual_control_needs_two_distinct_admins() {
+        let config = PolicyConfig {
+            dual_control_actions: vec!["clear_expired_cache".to_string()],
+            ..PolicyConfig::default()
+        };
+        assert!(config.validate().is_ok());
+        let (mut req, mut ctx) = make_context("org_admin", true, 10);
+        req.action = "clear_expired_cache".to_string();
+        let rule = |ctx: &SecurityContext| {
+            evaluate_policy_with_config(&config, &req, ctx, 0).applied_rule
+        };
+        assert_eq!(rule(&ctx), "DUAL_CONTROL_REQUIRED");
+
+        // Same-person approval and an under-privileged approver are refused
+        ctx.second_approver_id = Some("user-123".to_string());
+        ctx.second_approver_role = Some("super_admin".to_string());
+        assert_eq!(rule(&ctx), "DUAL_CONTROL_REQUIRED");
+        ctx.second_approver_id = Some("user-777".to_string());
+        ctx.second_approver_role = Some("power_user".to_string());
+        assert_eq!(rule(&ctx), "DUAL_CONTROL_REQUIRED");
+        ctx.second_approver_role = Some("super_admin".to_string());
+        assert_eq!(rule(&ctx), "WHITELIST_APPROVED");
+
+        // The acting user must be admin-tier and identified too
+        ctx.user_role = "power_user".to_string();
+        assert_eq!(rule(&ctx), "DUAL_CONTROL_REQUIRED");
+        ctx.user_role = "org_admin".to_string();
+        ctx.authenticated_user_id = None;
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "DUAL_CONTROL_REQUIRED");
+        assert!(decision.audit_required);
+        assert!(decision.reason.contains("authenticated_user_id"));
+
+        let config = PolicyConfig {
+            dual_control_min_role: "root".to_string(),
+            ..PolicyConfig::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_dual_control_read_skips_fast_path() {
+        let config = PolicyConfig {
+            dual_control_actions: vec!["read_file_metadata".to_string()],
+            ..PolicyConfig::default()
+        };
+        let (mut req, mut ctx) = make_context("user", false, 10);
+        req.action = "read_file_metadata".to_string();
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
         assert!(!decision.permitted);
+        assert_eq!(decision.applied_rule, "DUAL_CONTROL_REQUIRED");
+
+        ctx.user_role = "org_admin".to_string();
+        ctx.second_approver_id = Some("user-777".to_string());
+        ctx.second_approver_role = Some("org_admin".to_string());
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "WHITELIST_APPROVED");
+    }
+
+    #[test]
+    fn test_implausible_context_fails_closed_at_limits() {
+        let config = PolicyConfig {
+            max_plausible_session_age_seconds: Some(86_400),
+            max_plausible_failed_attempts: Some(100),
+            ..PolicyConfig::default()
+        };
+        let (req, mut ctx) = make_context("user", false, 10);
+        let rule = |ctx: &SecurityContext| {
+            evaluate_policy_with_config(&config, &req, ctx, 0).applied_rule
+        };
+
+        // At each limit the context is plausible; one past it is not
+        ctx.session_age_seconds = 86_400;
+        assert_eq!(rule(&ctx), "WHITELIST_APPROVED");
+        ctx.session_age_seconds = 86_401;
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "CONTEXT_IMPLAUSIBLE");
+        assert!(decision.audit_required);
+        assert!(decision.reason.contains("session_age_seconds=86401"));
+        ctx.session_age_seconds = 300;
+        ctx.failed_attempts_last_hour = 100;
+        assert_eq!(rule(&ctx), "ACCOUNT_LOCKOUT");
+        ctx.failed_attempts_last_hour = 101;
+        assert_eq!(rule(&ctx), "CONTEXT_IMPLAUSIBLE");
+
+        // The "unknown" session default stays a plain stale session
+        ctx.failed_attempts_last_hour = 0;
+        ctx.session_age_seconds = u64::MAX;
+        assert_eq!(rule(&ctx), "WHITELIST_APPROVED");
+
+        // Disabled checks accept anything; a limit below lockout is refused
+        let open = PolicyConfig {
+            max_plausible_session_age_seconds: None,
+            max_plausible_failed_attempts: None,
+            ..PolicyConfig::default()
+        };
+        ctx.failed_attempts_last_hour = u32::MAX;
+        let decision = evaluate_policy_with_config(&open, &req, &ctx, 0);
         assert_eq!(decision.applied_rule, "ACCOUNT_LOCKOUT");
+        let config = PolicyConfig { max_plausible_failed_attempts: Some(3), ..config };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_batch_session_streams_decisions_and_counts() {
+        let (mut req, ctx) = make_context("user", false, 10);
+        let context_json = serde_json::to_string(&ctx).unwrap();
+        let mut session = BatchSession::new(&context_json);
+        for (i, action) in ["read_public_cache", "delete_user_data", "read_search_index"]
+            .iter()
+            .enumerate()
+        {
+            req.action = action.to_string();
+            let request_json = serde_json::to_string(&req).unwrap();
+            let timestamp_ms = i as u64;
+            #[cfg(not(feature = "trace-timing"))]
+            assert_eq!(
+                session.push(&request_json, timestamp_ms),
+                validate_ai_action(&request_json, &context_json, timestamp_ms)
+            );
+            #[cfg(feature = "trace-timing")]
+            session.push(&request_json, timestamp_ms);
+        }
+        session.push("{", 3);
+        let summary: serde_json::Value = serde_json::from_str(&session.summary()).unwrap();
+        assert_eq!(summary["total"], 4);
+        assert_eq!(summary["permitted"], 2);
+        assert_eq!(summary["denied"], 2);
+        assert_eq!(
+            summary["by_rule"],
+            serde_json::json!({
+                "HARD_BLOCK": 1,
+                "REQUEST_PARSE_ERROR": 1,
+                "WHITELIST_APPROVED": 2,
+            })
+        );
+
+        // A bad context denies every push, stamped with the push's time
+        let mut broken = BatchSession::new("not json");
+        let decision: serde_json::Value =
+            serde_json::from_str(&broken.push(&serde_json::to_string(&req).unwrap(), 7)).unwrap();
+        assert_eq!(decision["applied_rule"], "CONTEXT_PARSE_ERROR");
+        assert_eq!(decision["timestamp_ms"], 7);
+    }
+
+    #[test]
+    fn test_role_risk_ceilings() {
+        let config = PolicyConfig {
+            role_max_risk: [("super_admin".to_string(), 80), ("user".to_string(), 30)].into(),
+            ..PolicyConfig::default()
+        };
+        assert!(config.validate().is_ok());
+        let rule = |role: &str, risk: u8| {
+            let (req, ctx) = make_context(role, true, risk);
+            evaluate_policy_with_config(&config, &req, &ctx, 0)
+        };
+
+        assert!(rule("super_admin", 80).permitted);
+        let decision = rule("super_admin", 81);
+        assert_eq!(decision.applied_rule, "RISK_SCORE_EXCEEDED");
+        assert!(decision.reason.contains("maximum allowed 80 for role 'super_admin'"));
+        assert_eq!(rule("user", 31).applied_rule, "RISK_SCORE_EXCEEDED");
+        // Unlisted roles keep the global ceiling, unknown ones the lowest
+        assert!(rule("power_user", 50).permitted);
+        let decision = rule("power_user", 51);
+        assert!(decision.reason.contains("maximum allowed 50. Human"));
+        assert_eq!(rule("intern", 31).applied_rule, "RISK_SCORE_EXCEEDED");
+        assert!(rule("intern", 30).permitted);
+
+        // Ceilings must respect the cap and name real roles
+        let capped = PolicyConfig { role_max_risk_cap: 70, ..config.clone() };
+        assert!(capped.validate().is_err());
+        let unknown =
+            PolicyConfig { role_max_risk: [("root".to_string(), 10)].into(), ..config };
+        assert!(unknown.validate().is_err());
+    }
+
+    #[test]
+    fn test_require_trace_id_for_writes() {
+        let config = PolicyConfig { require_trace_id: true, ..PolicyConfig::default() };
+        let (mut req, ctx) = make_context("user", false, 10);
+        req.action = "write_user_preferences".to_string();
+        for metadata in [None, Some(serde_json::json!({ "trace_id": "" }))] {
+            req.metadata = metadata;
+            let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+            assert_eq!(decision.applied_rule, "MISSING_TRACE_ID");
+            assert!(decision.trace_id.is_none());
+        }
+        req.metadata = Some(serde_json::json!({ "trace_id": "abc-123" }));
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert!(decision.permitted);
+        assert_eq!(decision.trace_id.as_deref(), Some("abc-123"));
+
+        // Reads are exempt; with the flag off the ID is still echoed
+        req.action = "read_public_cache".to_string();
+        req.metadata = None;
+        assert!(evaluate_policy_with_config(&config, &req, &ctx, 0).permitted);
+        req.action = "write_user_preferences".to_string();
+        req.metadata = Some(serde_json::json!({ "trace_id": "abc-123" }));
+        assert_eq!(evaluate_policy(&req, &ctx, 0).trace_id.as_deref(), Some("abc-123"));
+
+        // The metadata allowlist must leave room for it
+        let config = PolicyConfig { allowed_metadata_keys: Some(Vec::new()), ..config };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_tenant_control_overrides() {
+        let mut config = PolicyConfig::default();
+        config.tenant_control_overrides.insert(
+            "org-456".to_string(),
+            BTreeMap::from([("HARD_BLOCK".to_string(), "NIST800-53:CM-7".to_string())]),
+        );
+        assert!(config.validate().is_ok());
+        let (mut req, ctx) = make_context("user", false, 10);
+        req.action = "delete_user_data".to_string();
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "HARD_BLOCK");
+        assert_eq!(decision.iso_control, "NIST800-53:CM-7");
+
+        // Other tenants and other rules keep the ISO default
+        req.organisation_id = Some("org-789".to_string());
+        assert_eq!(evaluate_policy_with_config(&config, &req, &ctx, 0).iso_control, "A.9.4.1");
+        req.organisation_id = Some("org-456".to_string());
+        req.action = "read_public_cache".to_string();
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert_eq!(decision.iso_control, evaluate_policy(&req, &ctx, 0).iso_control);
+
+        let overrides = config.tenant_control_overrides.get_mut("org-456").unwrap();
+        overrides.insert("NOT_A_RULE".to_string(), "X-1".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_deprecated_action_sunsets() {
+        let mut config = PolicyConfig::default();
+        config.deprecated_actions.insert("read_public_cache".to_string(), 5000);
+        assert!(config.validate().is_ok());
+        let (req, ctx) = make_context("user", false, 10);
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 4999);
+        assert!(decision.permitted);
+        assert_eq!(decision.sunset_timestamp_ms, Some(5000));
+        assert!(decision.deprecation_warning.unwrap().contains("read_public_cache"));
+
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 5000);
+        assert!(!decision.permitted);
+        assert_eq!(decision.applied_rule, "NOT_IN_WHITELIST");
+        assert!(decision.reason.contains("retired"));
+        assert!(decision.deprecation_warning.is_none());
+
+        // Only whitelisted actions can be deprecated
+        config.deprecated_actions.insert("format_disk".to_string(), 5000);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_effective_risk_ceiling_matches_rule_2() {
+        let ceiling = |context: &str| -> serde_json::Value {
+            serde_json::from_str(&effective_risk_ceiling("read_public_cache", context)).unwrap()
+        };
+        let (req, ctx) = make_context("user", false, 10);
+        let context_json = serde_json::to_string(&ctx).unwrap();
+        let report = ceiling(&context_json);
+        assert_eq!(report["action"], "read_public_cache");
+        assert_eq!(report["ceiling"], MAX_RISK_SCORE);
+        assert_eq!(report["default"], MAX_RISK_SCORE);
+
+        // The reported ceiling is the last score RULE 2 lets through
+        let mut req = req;
+        req.risk_score = MAX_RISK_SCORE;
+        assert!(evaluate_policy(&req, &ctx, 0).permitted);
+        req.risk_score += 1;
+        assert_eq!(evaluate_policy(&req, &ctx, 0).applied_rule, "RISK_SCORE_EXCEEDED");
+
+        assert!(ceiling("{not json").get("error").is_some());
+    }
+
+    #[test]
+    fn test_preview_with_role_substitutes_the_role() {
+        let (mut req, ctx) = make_context("user", true, 10);
+        req.action = "clear_expired_cache".to_string();
+        let request = serde_json::to_string(&req).unwrap();
+        let context = serde_json::to_string(&ctx).unwrap();
+        let decision = |json: String| -> PolicyDecision { serde_json::from_str(&json).unwrap() };
+
+        let current = decision(validate_ai_action(&request, &context, 0));
+        assert_eq!(current.applied_rule, "INSUFFICIENT_ROLE");
+        let elevated = decision(preview_with_role(&request, &context, "power_user", 0));
+        assert!(elevated.permitted);
+        #[cfg(not(feature = "trace-timing"))]
+        assert_eq!(
+            preview_with_role(&request, &context, "user", 0),
+            validate_ai_action(&request, &context, 0)
+        );
+        let unknown = decision(preview_with_role(&request, &context, "overlord", 0));
+        assert!(!unknown.permitted);
+        assert!(!decision(preview_with_role("{", &context, "power_user", 0)).permitted);
+    }
+
+    #[test]
+    fn test_too_many_sessions_for_writes() {
+        let config = PolicyConfig { max_concurrent_sessions: Some(2), ..PolicyConfig::default() };
+        let (mut req, mut ctx) = make_context("user", false, 10);
+        req.action = "write_user_preferences".to_string();
+        ctx.concurrent_sessions = Some(3);
+        let decision = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert_eq!(decision.applied_rule, "TOO_MANY_SESSIONS");
+        assert!(decision.audit_required);
+
+        // At the limit, without a count, or for a read, nothing is denied
+        ctx.concurrent_sessions = Some(2);
+        assert!(evaluate_policy_with_config(&config, &req, &ctx, 0).permitted);
+        ctx.concurrent_sessions = None;
+        assert!(evaluate_policy_with_config(&config, &req, &ctx, 0).permitted);
+        ctx.concurrent_sessions = Some(9);
+        req.action = "read_public_cache".to_string();
+        assert!(evaluate_policy_with_config(&config, &req, &ctx, 0).permitted);
+
+        let config = PolicyConfig { max_concurrent_sessions: Some(0), ..config };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_route_hints_follow_the_rule() {
+        let (mut req, ctx) = make_context("user", false, 10);
+        let route = |config: &PolicyConfig, req: &AiRequest| {
+            evaluate_policy_with_config(config, req, &ctx, 0).route_hint.unwrap()
+        };
+        let config = PolicyConfig::default();
+        assert_eq!(route(&config, &req), "audit-log");
+        req.risk_score = 90;
+        assert_eq!(route(&config, &req), "review-queue");
+        req.action = "delete_user_data".to_string();
+        assert_eq!(route(&config, &req), "security-alerts");
+        req.action = "format_disk".to_string();
+        req.risk_score = 10;
+        assert_eq!(route(&config, &req), DEFAULT_ROUTE);
+
+        let mut config = PolicyConfig::default();
+        config.route_hints.insert("NOT_IN_WHITELIST".to_string(), "triage".to_string());
+        assert_eq!(route(&config, &req), "triage");
+        config.route_hints.insert("NOT_A_RULE".to_string(), "triage".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_sensitive_targets_audit_and_need_mfa_for_writes() {
+        let config = PolicyConfig {
+            sensitive_resource_prefixes: vec!["finance:".to_string()],
+            ..PolicyConfig::default()
+        };
+        let (mut req, ctx) = make_context("user", false, 10);
+        req.target_resource = "finance:ledger".to_string();
+        let read = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert!(read.permitted && read.audit_required);
+
+        req.action = "write_user_preferences".to_string();
+        let write = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert_eq!(write.applied_rule, "SENSITIVE_TARGET");
+        assert_eq!(write.step_up_required.unwrap().method, "mfa");
+        let (_, mfa_ctx) = make_context("user", true, 10);
+        let write = evaluate_policy_with_config(&config, &req, &mfa_ctx, 0);
+        assert!(write.permitted && write.audit_required);
+
+        // Other resources behave as before
+        req.target_resource = "cache:public".to_string();
+        let write = evaluate_policy_with_config(&config, &req, &ctx, 0);
+        assert!(write.permitted && !write.audit_required);
+
+        let config = PolicyConfig { sensitive_resource_prefixes: vec![String::new()], ..config };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_try_validate_reports_each_input_error() {
+        let (req, ctx) = make_context("user", false, 10);
+        let req_json = serde_json::to_string(&req).unwrap();
+        let ctx_json = serde_json::to_string(&ctx).unwrap();
+
+        let error = try_validate_ai_action("{\n  \"action\": }", &ctx_json, 0).unwrap_err();
+        assert!(matches!(error, PolicyError::ParseRequest { position: Some((2, 13)), .. }));
+        let error = try_validate_ai_action(&req_json, "not json", 0).unwrap_err();
+        assert!(matches!(error, PolicyError::ParseContext { position: Some((1, _)), .. }));
+        let oversized = "x".repeat(PolicyConfig::default().max_input_bytes + 1);
+        let error = try_validate_ai_action(&oversized, &ctx_json, 0).unwrap_err();
+        assert!(matches!(error, PolicyError::InputTooLarge(_)));
+
+        let strict = PolicyConfig { strict_fields: true, ..PolicyConfig::default() };
+        let mut extra = serde_json::to_value(&ctx).unwrap();
+        extra["is_admin"] = serde_json::json!(true);
+        let index = ActionIndex::new(&strict);
+        let error =
+            try_validate_json_with_config(&strict, &index, &req_json, &extra.to_string(), 0)
+                .unwrap_err();
+        assert_eq!(
+            error,
+            PolicyError::UnknownField { input: "context", field: "is_admin".to_string() }
+        );
+
+        // The JSON boundary emits exactly the error's fail-closed decision
+        for (request, context) in [
+            ("{\n  \"action\": }", ctx_json.as_str()),
+            (req_json.as_str(), "not json"),
+            (oversized.as_str(), ctx_json.as_str()),
+        ] {
+            let error = try_validate_ai_action(request, context, 7).unwrap_err();
+            let decision: PolicyDecision =
+                serde_json::from_str(&validate_ai_action(request, context, 7)).unwrap();
+            let expected = error.clone().into_decision(7);
+            assert!(!decision.permitted);
+            assert_eq!(decision.applied_rule, error.applied_rule());
+            assert_eq!(decision.reason, error.to_string());
+            assert_eq!(decision.error_line, expected.error_line);
+            assert_eq!(decision.decision_id, expected.decision_id);
+        }
+
+        let decision = try_validate_ai_action(&req_json, &ctx_json, 0).unwrap();
+        assert!(decision.permitted);
+        #[cfg(not(feature = "trace-timing"))]
+        assert_eq!(
+            serde_json::to_string(&decision).unwrap(),
+            validate_ai_action(&req_json, &ctx_json, 0)
+        );
+    }
+
+    #[test]
+    fn test_policy_error_decisions_fail_closed() {
+        let error = PolicyConfig::from_json(r#"{"blocked_actions": []}"#).unwrap_err();
+        assert!(matches!(error, PolicyError::ConfigInvalid(_)));
+        let error = PolicyConfig::from_json("not json").unwrap_err();
+        assert!(error.to_string().starts_with("Invalid config JSON:"));
+        let decision = error.into_decision(5);
+        assert!(!decision.permitted);
+        assert_eq!(decision.applied_rule, "ENGINE_DEGRADED");
+        assert_eq!(decision.iso_control, "A.17.1.1");
+        assert!(decision.audit_required);
+        assert_eq!(decision.timestamp_ms, 5);
+
+        let decision = PolicyError::Serialize("map key".to_string()).into_decision(5);
+        assert!(!decision.permitted);
+        assert_eq!(decision.applied_rule, "SERIALIZE_ERROR");
+        assert_eq!(decision.iso_control, "A.12.1.2");
+        assert_eq!(decision.reason, "Decision could not be serialized: map key");
+        assert!(decision.audit_required);
+
+        let decision = PolicyError::InputTooLarge("too big".to_string()).into_decision(5);
+        assert_eq!(decision.applied_rule, "INPUT_TOO_LARGE");
+        assert_eq!(decision.reason, "too big");
+    }
+
+    #[test]
+    fn test_merge_audit_only_raises_flags() {
+        let (req, ctx) = make_context("user", false, 10);
+        let mut decision = evaluate_policy(&req, &ctx, 0);
+        assert!(!decision.audit_required);
+
+        merge_audit(&mut decision, true, false);
+        assert!(decision.audit_required);
+        assert!(!decision.requires_human_review);
+
+        // Clearing is a no-op
+        merge_audit(&mut decision, false, false);
+        assert!(decision.audit_required);
+
+        let escalated: serde_json::Value = serde_json::from_str(&escalate_decision(
+            &serde_json::to_string(&decision).unwrap(),
+            false,
+            true,
+        ))
+        .unwrap();
+        assert_eq!(escalated["audit_required"], true);
+        assert_eq!(escalated["requires_human_review"], true);
+        assert_eq!(escalated["permitted"], true);
+
+        let error: serde_json::Value =
+            serde_json::from_str(&escalate_decision("{", false, false)).unwrap();
+        assert_eq!(error["applied_rule"], "ESCALATE_ERROR");
+        assert_eq!(error["permitted"], false);
     }
 
     #[test]
@@ -471,4 +6560,101 @@ mod tests {
         let decision: serde_json::Value = serde_json::from_str(&result).unwrap();
         assert_eq!(decision["permitted"], false);
     }
+
+    #[test]
+    fn test_gdpr_deletion_batch_preserves_order() {
+        let batch = serde_json::json!([
+            {"user_id": "u1", "requesting_user_id": "u1", "requester_role": "user"},
+            {"user_id": "u2", "requesting_user_id": "u1", "requester_role": "user"},
+            {"user_id": "u3"},
+            {"user_id": "u4", "requesting_user_id": "a1", "requester_role": "super_admin"},
+        ]);
+        let output = validate_gdpr_deletion_batch(&batch.to_string(), 5);
+        let decisions: Vec<GdprDecision> = serde_json::from_str(&output).unwrap();
+        let permitted: Vec<bool> = decisions.iter().map(|d| d.permitted).collect();
+        assert_eq!(permitted, [true, false, false, true]);
+        assert!(decisions[2].reason.starts_with("Invalid GDPR deletion entry"));
+        assert!(decisions.iter().all(|d| d.timestamp_ms == 5));
+        assert_eq!(
+            decisions[0],
+            serde_json::from_str(&validate_gdpr_deletion("u1", "u1", "user", 5)).unwrap()
+        );
+
+        let error: serde_json::Value =
+            serde_json::from_str(&validate_gdpr_deletion_batch("{}", 0)).unwrap();
+        assert!(error["error"].is_string());
+    }
+
+    #[test]
+    fn test_gdpr_decision_keeps_wire_keys() {
+        let result = validate_gdpr_deletion("user-123", "user-123", "user", 7);
+        let decision: serde_json::Value = serde_json::from_str(&result).unwrap();
+        let keys: Vec<&String> = decision.as_object().unwrap().keys().collect();
+        assert_eq!(
+            keys,
+            ["action", "gdpr_article", "iso_control", "permitted", "reason", "timestamp_ms"]
+        );
+        let typed: GdprDecision = serde_json::from_str(&result).unwrap();
+        assert_eq!(typed, gdpr_decision("user-123", "user-123", "user", 7));
+
+        // Erasure on someone else's behalf is audited, allowed or not
+        assert!(gdpr_decision("user-123", "admin-1", "org_admin", 0).audit_required);
+        assert!(gdpr_decision("user-123", "user-456", "user", 0).audit_required);
+    }
+
+    #[test]
+    fn test_gdpr_min_roles_per_request_type() {
+        let mut config = PolicyConfig::default();
+        config.gdpr_min_roles.insert("erasure".to_string(), "super_admin".to_string());
+        assert!(config.validate().is_ok());
+        let decide = |kind: &str, requester: &str, role: &str| {
+            gdpr_request_decision(&config, kind, "user-123", requester, role, 0)
+        };
+
+        let erasure = decide("erasure", "admin-1", "org_admin");
+        assert!(!erasure.permitted);
+        assert_eq!(erasure.required_role.as_deref(), Some("super_admin"));
+        assert!(erasure.reason.contains("super_admin"));
+        assert!(decide("erasure", "admin-1", "super_admin").permitted);
+        let portability = decide("portability", "admin-1", "org_admin");
+        assert!(portability.permitted);
+        assert_eq!(portability.action, "EXPORT_USER_DATA");
+        assert!(portability.gdpr_article.starts_with("Article 20"));
+
+        // Subjects file their own regardless of role; unknown types fail closed
+        assert!(decide("erasure", "user-123", "user").permitted);
+        assert!(decide("portability", "user-123", "intern").permitted);
+        assert!(!decide("rectification", "user-123", "super_admin").permitted);
+        let request = validate_gdpr_request("erasure", "user-123", "admin-1", "org_admin", 0);
+        assert!(serde_json::from_str::<GdprDecision>(&request).unwrap().permitted);
+
+        config.gdpr_min_roles.insert("rectification".to_string(), "user".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_gdpr_min_role_outside_hierarchy_is_never_met() {
+        let mut config = PolicyConfig {
+            role_hierarchy: vec!["user".to_string(), "power_user".to_string(), "admin".to_string()],
+            max_elevation_role: "admin".to_string(),
+            dual_control_min_role: "admin".to_string(),
+            ..PolicyConfig::default()
+        };
+        let error = config.validate().unwrap_err();
+        assert!(error.contains("'org_admin'"), "{}", error);
+        for role in ["user", "admin"] {
+            let decision = gdpr_request_decision(&config, "erasure", "victim", "attacker", role, 1);
+            assert!(!decision.permitted, "{}", role);
+            assert_eq!(decision.required_role.as_deref(), Some("org_admin"));
+        }
+
+        config.gdpr_min_roles.insert("erasure".to_string(), "admin".to_string());
+        config.gdpr_min_roles.insert("portability".to_string(), "admin".to_string());
+        assert!(config.validate().is_ok());
+        let decide = |requester: &str, role: &str| {
+            gdpr_request_decision(&config, "erasure", "victim", requester, role, 1).permitted
+        };
+        assert!(decide("operator", "admin"));
+        assert!(!decide("attacker", "user"));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,89 @@
+// ============================================================
+// Infinity OS — Rule Timing (feature `trace-timing`)
+//
+// Records how long each stage of the rule cascade took, for
+// performance debugging. Stages are opened by `trace_rule!`
+// marks in the cascade; each runs until the next mark. Without
+// the feature the marks expand to nothing.
+// ============================================================
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+
+/// Wall time spent in one cascade stage
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct RuleTiming {
+    /// Stage label, as in the cascade's rule banners (e.g. `RULE 2`)
+    pub stage: String,
+    pub elapsed_ms: f64,
+}
+
+#[derive(Default)]
+struct Trace {
+    stages: Vec<RuleTiming>,
+    /// Stage in progress and when it started (ms)
+    open: Option<(&'static str, f64)>,
+}
+
+impl Trace {
+    fn close(&mut self, now: f64) {
+        if let Some((stage, started)) = self.open.take() {
+            self.stages.push(RuleTiming {
+                stage: stage.to_string(),
+                elapsed_ms: now - started,
+            });
+        }
+    }
+}
+
+thread_local! {
+    static TRACE: RefCell<Option<Trace>> = const { RefCell::new(None) };
+}
+
+/// Start recording a cascade evaluation on this thread
+pub(crate) fn begin() {
+    TRACE.with(|trace| *trace.borrow_mut() = Some(Trace::default()));
+}
+
+/// Close the open stage, if any, and open `stage`
+pub(crate) fn mark(stage: &'static str) {
+    let now = now_ms();
+    TRACE.with(|trace| {
+        if let Some(trace) = trace.borrow_mut().as_mut() {
+            trace.close(now);
+            trace.open = Some((stage, now));
+        }
+    });
+}
+
+/// Stop recording and return the stages in evaluation order
+pub(crate) fn end() -> Vec<RuleTiming> {
+    let now = now_ms();
+    TRACE.with(|trace| {
+        let mut trace = trace.borrow_mut().take().unwrap_or_default();
+        trace.close(now);
+        trace.stages
+    })
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+fn now_ms() -> f64 {
+    use wasm_bindgen::prelude::*;
+
+    #[wasm_bindgen]
+    extern "C" {
+        #[wasm_bindgen(js_namespace = performance, js_name = now)]
+        fn performance_now() -> f64;
+    }
+    performance_now()
+}
+
+#[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+fn now_ms() -> f64 {
+    use std::sync::OnceLock;
+    use std::time::Instant;
+
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    EPOCH.get_or_init(Instant::now).elapsed().as_secs_f64() * 1000.0
+}
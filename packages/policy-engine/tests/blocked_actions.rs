@@ -0,0 +1,103 @@
+// ============================================================
+// Infinity OS — Blocked-action invariant
+//
+// No context, risk score or engine overlay may ever permit an
+// action on the blocklist. Guards against rule reordering
+// letting a blocked action slip past RULE 1.
+// ============================================================
+
+use infinity_os_policy_engine::{validate_ai_action, PolicyConfig, PolicyEngine};
+use proptest::prelude::*;
+use serde_json::{json, Value};
+
+fn blocked_action() -> impl Strategy<Value = String> {
+    proptest::sample::select(PolicyConfig::default().blocked_actions)
+}
+
+fn any_role() -> impl Strategy<Value = String> {
+    prop_oneof![
+        proptest::sample::select(PolicyConfig::default().role_hierarchy),
+        "[a-z_]{0,12}",
+    ]
+}
+
+fn any_context() -> impl Strategy<Value = Value> {
+    (
+        any_role(),
+        any::<bool>(),
+        any::<u64>(),
+        any::<bool>(),
+        any::<u32>(),
+        proptest::option::of(any::<u64>()),
+        proptest::option::of("[a-z0-9-]{1,16}"),
+        proptest::option::of(0u8..=100),
+    )
+        .prop_map(|(role, mfa, session_age, trusted, failed, account_age, session_id, threat)| {
+            json!({
+                "user_role": role,
+                "mfa_verified": mfa,
+                "session_age_seconds": session_age,
+                "trusted_network": trusted,
+                "failed_attempts_last_hour": failed,
+                "account_age_seconds": account_age,
+                "session_id": session_id,
+                "threat_score": threat,
+            })
+        })
+}
+
+fn request(action: &str, risk_score: u8, user_id: &str) -> String {
+    json!({
+        "action": action,
+        "target_resource": "res:any",
+        "risk_score": risk_score,
+        "requesting_module": "proptest",
+        "user_id": user_id,
+        "organisation_id": "org-1",
+        "metadata": null,
+    })
+    .to_string()
+}
+
+fn assert_hard_block(decision_json: &str) {
+    let decision: Value = serde_json::from_str(decision_json).unwrap();
+    assert_eq!(decision["permitted"], false, "{}", decision_json);
+    assert_eq!(decision["applied_rule"], "HARD_BLOCK", "{}", decision_json);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    #[test]
+    fn blocked_actions_are_never_permitted(
+        action in blocked_action(),
+        context in any_context(),
+        risk_score in any::<u8>(),
+        timestamp_ms in any::<u64>(),
+    ) {
+        let decision = validate_ai_action(
+            &request(&action, risk_score, "user-1"),
+            &context.to_string(),
+            timestamp_ms,
+        );
+        assert_hard_block(&decision);
+    }
+
+    #[test]
+    fn engine_overlays_never_permit_blocked_actions(
+        action in blocked_action(),
+        context in any_context(),
+        risk_score in any::<u8>(),
+        timestamp_ms in 0u64..u64::MAX / 2,
+    ) {
+        let mut engine = PolicyEngine::new();
+        // A grant for a blocked action must be refused outright
+        prop_assert!(engine.add_temporary_grant(&action, "user-1", timestamp_ms + 60_000).is_err());
+        let decision = engine.validate_ai_action(
+            &request(&action, risk_score, "user-1"),
+            &context.to_string(),
+            timestamp_ms,
+        );
+        assert_hard_block(&decision);
+    }
+}
@@ -0,0 +1,52 @@
+// ============================================================
+// Infinity OS — Golden decision corpus
+//
+// Runs every case in `tests/fixtures/*.json` through
+// `run_fixtures`. Add a file or a case to pin a decision;
+// a failure lists the case indices and their diffs.
+// ============================================================
+
+use infinity_os_policy_engine::run_fixtures;
+use serde_json::Value;
+
+#[test]
+fn golden_corpus_passes() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let report: Value =
+            serde_json::from_str(&run_fixtures(&std::fs::read_to_string(&path).unwrap())).unwrap();
+        assert_eq!(report["failed"], 0, "{}: {}", path.display(), report);
+        assert!(report["total"].as_u64().unwrap() > 0, "{}", path.display());
+    }
+}
+
+#[test]
+fn failures_report_indices_and_diffs() {
+    let fixtures = serde_json::json!([
+        {
+            "request": {
+                "action": "read_public_cache",
+                "target_resource": "fixture",
+                "risk_score": 10,
+                "requesting_module": "com.infinity-os.shell"
+            },
+            "context": { "user_role": "user" },
+            "timestamp_ms": 0,
+            "expected_permitted": false,
+            "expected_rule": "HARD_BLOCK"
+        },
+        { "request": {} }
+    ]);
+    let report: Value = serde_json::from_str(&run_fixtures(&fixtures.to_string())).unwrap();
+    assert_eq!(report["total"], 2);
+    assert_eq!(report["passed"], 0);
+    assert_eq!(report["failing_cases"], serde_json::json!([0, 1]));
+    let diffs = &report["cases"][0]["diffs"];
+    assert_eq!(diffs[0]["field"], "permitted");
+    assert_eq!(diffs[1]["field"], "applied_rule");
+    assert_eq!(diffs[1]["actual"], "WHITELIST_APPROVED");
+    assert_eq!(report["cases"][1]["diffs"][0]["field"], "fixture");
+
+    assert!(serde_json::from_str::<Value>(&run_fixtures("{}")).unwrap()["error"].is_string());
+}